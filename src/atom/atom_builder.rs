@@ -2,9 +2,9 @@ use std::{cmp::Ordering, fmt::Display, marker::PhantomData};
 
 use nalgebra::Point3;
 
-use crate::{builder_typestate::No, ModelInfo};
+use crate::{builder_typestate::No, model_type::msi::MsiAttribute, ModelInfo};
 
-use super::AtomCollection;
+use super::{default_magnetic_moment, AtomCollection};
 
 pub trait BuildState {}
 pub struct Ready {}
@@ -18,9 +18,13 @@ where
 {
     element_symbols: Option<Vec<String>>,
     atomic_nums: Option<Vec<u8>>,
+    species_labels: Option<Vec<Option<String>>>,
     xyz_coords: Option<Vec<Point3<f64>>>,
     fractional_xyz: Option<Vec<Option<Point3<f64>>>>,
     atom_ids: Option<Vec<u32>>,
+    fixed_axes: Option<Vec<[bool; 3]>>,
+    magnetic_moments: Option<Vec<f64>>,
+    unknown_attributes: Option<Vec<Vec<MsiAttribute>>>,
     size: usize,
     format_type: T,
     state: PhantomData<S>,
@@ -52,9 +56,13 @@ impl<T: ModelInfo, S: BuildState> AtomCollectionBuilder<T, S> {
         AtomCollectionBuilder {
             element_symbols: None,
             atomic_nums: None,
+            species_labels: None,
             xyz_coords: None,
             fractional_xyz: None,
             atom_ids: None,
+            fixed_axes: None,
+            magnetic_moments: None,
+            unknown_attributes: None,
             size,
             format_type: T::default(),
             state: PhantomData,
@@ -102,6 +110,28 @@ impl<T: ModelInfo, S: BuildState> AtomCollectionBuilder<T, S> {
             }),
         }
     }
+    /// Supply the `species_labels` for an `AtomCollection`.
+    /// Optional, all atoms are of the bare elements if not supplied.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the `species_labels` has a different vector size
+    /// with the builder's given size.
+    pub fn with_species_labels(
+        mut self,
+        species_labels: &[Option<String>],
+    ) -> Result<Self, AtomCollectionBuildingError> {
+        match species_labels.len().cmp(&self.size) {
+            Ordering::Equal => {
+                self.species_labels = Some(species_labels.to_vec());
+                Ok(self)
+            }
+            _ => Err(AtomCollectionBuildingError::InconsistentSize {
+                curr: species_labels.len(),
+                expected: self.size,
+            }),
+        }
+    }
     /// Supply the `xyz_coords` for an `AtomCollection`.
     ///
     /// # Errors
@@ -162,6 +192,72 @@ impl<T: ModelInfo, S: BuildState> AtomCollectionBuilder<T, S> {
             }),
         }
     }
+    /// Supply the `fixed_axes` for an `AtomCollection`.
+    /// Optional, all atoms are free to move if not supplied.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the `fixed_axes` has a different vector size
+    /// with the builder's given size.
+    pub fn with_fixed_axes(
+        mut self,
+        fixed_axes: &[[bool; 3]],
+    ) -> Result<Self, AtomCollectionBuildingError> {
+        match fixed_axes.len().cmp(&self.size) {
+            Ordering::Equal => {
+                self.fixed_axes = Some(fixed_axes.to_vec());
+                Ok(self)
+            }
+            _ => Err(AtomCollectionBuildingError::InconsistentSize {
+                curr: fixed_axes.len(),
+                expected: self.size,
+            }),
+        }
+    }
+    /// Supply the `magnetic_moments` for an `AtomCollection`.
+    /// Optional, the spin of the element in `ELEMENT_TABLE` is used if not supplied.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the `magnetic_moments` has a different vector size
+    /// with the builder's given size.
+    pub fn with_magnetic_moments(
+        mut self,
+        magnetic_moments: &[f64],
+    ) -> Result<Self, AtomCollectionBuildingError> {
+        match magnetic_moments.len().cmp(&self.size) {
+            Ordering::Equal => {
+                self.magnetic_moments = Some(magnetic_moments.to_vec());
+                Ok(self)
+            }
+            _ => Err(AtomCollectionBuildingError::InconsistentSize {
+                curr: magnetic_moments.len(),
+                expected: self.size,
+            }),
+        }
+    }
+    /// Supply the `unknown_attributes` for an `AtomCollection`.
+    /// Optional, no atom has unrecognised `msi` attributes if not supplied.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the `unknown_attributes` has a different vector size
+    /// with the builder's given size.
+    pub fn with_unknown_attributes(
+        mut self,
+        unknown_attributes: &[Vec<MsiAttribute>],
+    ) -> Result<Self, AtomCollectionBuildingError> {
+        match unknown_attributes.len().cmp(&self.size) {
+            Ordering::Equal => {
+                self.unknown_attributes = Some(unknown_attributes.to_vec());
+                Ok(self)
+            }
+            _ => Err(AtomCollectionBuildingError::InconsistentSize {
+                curr: unknown_attributes.len(),
+                expected: self.size,
+            }),
+        }
+    }
     pub fn finish(self) -> Result<AtomCollectionBuilder<T, Ready>, AtomCollectionBuildingError> {
         if self.atomic_nums.is_none() {
             return Err(AtomCollectionBuildingError::MissingField {
@@ -191,9 +287,13 @@ impl<T: ModelInfo, S: BuildState> AtomCollectionBuilder<T, S> {
         let Self {
             element_symbols,
             atomic_nums,
+            species_labels,
             xyz_coords,
             fractional_xyz,
             atom_ids,
+            fixed_axes,
+            magnetic_moments,
+            unknown_attributes,
             size,
             format_type,
             state: _,
@@ -201,9 +301,13 @@ impl<T: ModelInfo, S: BuildState> AtomCollectionBuilder<T, S> {
         Ok(AtomCollectionBuilder {
             element_symbols,
             atomic_nums,
+            species_labels,
             xyz_coords,
             fractional_xyz,
             atom_ids,
+            fixed_axes,
+            magnetic_moments,
+            unknown_attributes,
             size,
             format_type,
             state: PhantomData,
@@ -213,12 +317,27 @@ impl<T: ModelInfo, S: BuildState> AtomCollectionBuilder<T, S> {
 
 impl<T: ModelInfo> AtomCollectionBuilder<T, Ready> {
     pub fn build(self) -> AtomCollection<T> {
+        let element_symbols = self.element_symbols.unwrap();
+        let magnetic_moments = self.magnetic_moments.unwrap_or_else(|| {
+            element_symbols
+                .iter()
+                .map(|symbol| default_magnetic_moment(symbol))
+                .collect()
+        });
         AtomCollection {
-            element_symbols: self.element_symbols.unwrap(),
+            element_symbols,
             atomic_nums: self.atomic_nums.unwrap(),
+            species_labels: self.species_labels.unwrap_or_else(|| vec![None; self.size]),
             xyz_coords: self.xyz_coords.unwrap(),
             fractional_xyz: self.fractional_xyz.unwrap(),
             atom_ids: self.atom_ids.unwrap(),
+            fixed_axes: self
+                .fixed_axes
+                .unwrap_or_else(|| vec![[false; 3]; self.size]),
+            magnetic_moments,
+            unknown_attributes: self
+                .unknown_attributes
+                .unwrap_or_else(|| vec![Vec::new(); self.size]),
             size: self.size,
             format_type: T::default(),
         }
@@ -1,6 +1,11 @@
-use crate::{error::InvalidIndex, model_type::ModelInfo, CellModel, MsiModel, Transformation};
+use crate::{
+    error::InvalidIndex,
+    model_type::{msi::MsiAttribute, ModelInfo},
+    CellModel, MsiModel, Transformation,
+};
 use std::{cmp::Ordering, ops::Add};
 
+use cpt::{data::ELEMENT_TABLE, element::LookupElement};
 use na::Point3;
 
 mod atom_builder;
@@ -16,6 +21,8 @@ pub struct Atom<T: ModelInfo> {
     element_symbol: String,
     /// The atomic number of the element in periodic table.
     atomic_number: u8,
+    /// The label of the species, e.g. `up` in `Fe:up`. `None` if the species is the element.
+    species_label: Option<String>,
     /// The cartesian coordinate of the atom.
     xyz: Point3<f64>,
     /// The fractional coordinate of the atom in a lattice.
@@ -23,6 +30,12 @@ pub struct Atom<T: ModelInfo> {
     fractional_xyz: Option<Point3<f64>>,
     /// The id of the atom in the parsed model.
     atom_id: u32,
+    /// Whether the motion along x, y and z is fixed.
+    fixed_axes: [bool; 3],
+    /// The initial magnetic moment, in the unit of the spin of an electron.
+    magnetic_moment: f64,
+    /// Attributes in `msi` not recognised by the parser, written back on export.
+    unknown_attributes: Vec<MsiAttribute>,
     /// Format type
     format_type: T,
 }
@@ -30,9 +43,13 @@ pub struct Atom<T: ModelInfo> {
 pub struct AtomView<'a, T: ModelInfo> {
     element_symbol: &'a str,
     atomic_number: &'a u8,
+    species_label: Option<&'a str>,
     xyz: &'a Point3<f64>,
     fractional_xyz: Option<&'a Point3<f64>>,
     atom_id: &'a u32,
+    fixed_axes: &'a [bool; 3],
+    magnetic_moment: &'a f64,
+    unknown_attributes: &'a [MsiAttribute],
     format_type: T,
 }
 
@@ -49,6 +66,10 @@ impl<'a, T: ModelInfo> AtomView<'a, T> {
         self.atomic_number
     }
 
+    pub fn species_label(&self) -> Option<&str> {
+        self.species_label
+    }
+
     pub fn fractional_xyz(&self) -> Option<&Point3<f64>> {
         self.fractional_xyz
     }
@@ -56,6 +77,18 @@ impl<'a, T: ModelInfo> AtomView<'a, T> {
     pub fn atom_id(&self) -> &u32 {
         self.atom_id
     }
+
+    pub fn fixed_axes(&self) -> &[bool; 3] {
+        self.fixed_axes
+    }
+
+    pub fn magnetic_moment(&self) -> &f64 {
+        self.magnetic_moment
+    }
+
+    pub fn unknown_attributes(&self) -> &[MsiAttribute] {
+        self.unknown_attributes
+    }
 }
 
 impl<'a, T: ModelInfo> From<AtomView<'a, T>> for Atom<T> {
@@ -63,22 +96,35 @@ impl<'a, T: ModelInfo> From<AtomView<'a, T>> for Atom<T> {
         Self {
             element_symbol: src.element_symbol().into(),
             atomic_number: *src.atomic_number(),
+            species_label: src.species_label().map(String::from),
             xyz: src.xyz().to_owned(),
             fractional_xyz: src.fractional_xyz().copied(),
             atom_id: *src.atom_id(),
+            fixed_axes: *src.fixed_axes(),
+            magnetic_moment: *src.magnetic_moment(),
+            unknown_attributes: src.unknown_attributes().to_vec(),
             format_type: T::default(),
         }
     }
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Struct of `Atom` as data-driven design.
 pub struct AtomCollection<T: ModelInfo> {
     element_symbols: Vec<String>,
     atomic_nums: Vec<u8>,
+    /// The labels of species, e.g. `up` in `Fe:up`, `None` for atoms of the bare element.
+    species_labels: Vec<Option<String>>,
     xyz_coords: Vec<Point3<f64>>,
     fractional_xyz: Vec<Option<Point3<f64>>>,
     atom_ids: Vec<u32>,
+    /// Whether the motion along x, y and z is fixed, for each atom.
+    fixed_axes: Vec<[bool; 3]>,
+    /// The signed initial magnetic moment of each atom, written as `SPIN=` in `cell`.
+    magnetic_moments: Vec<f64>,
+    /// Attributes in `msi` not recognised by the parser, for each atom.
+    unknown_attributes: Vec<Vec<MsiAttribute>>,
     size: usize,
     format_type: T,
 }
@@ -98,6 +144,17 @@ impl<T: ModelInfo> AtomCollection<T> {
         *self.atomic_nums.get_mut(index).ok_or(InvalidIndex)? = new_elm_id;
         Ok(())
     }
+    /// Update the `species_label` at the given index.
+    /// # Errors
+    /// This function will return an error if the index is out of bounds.
+    pub fn update_species_label_at(
+        &mut self,
+        index: usize,
+        new_label: Option<&str>,
+    ) -> Result<(), InvalidIndex> {
+        *self.species_labels.get_mut(index).ok_or(InvalidIndex)? = new_label.map(String::from);
+        Ok(())
+    }
     /// Update the `xyz` at the given index.
     /// # Errors
     /// This function will return an error if the index is out of bounds.
@@ -131,6 +188,39 @@ impl<T: ModelInfo> AtomCollection<T> {
         *self.atom_ids.get_mut(index).ok_or(InvalidIndex)? = new_atom_id;
         Ok(())
     }
+    /// Update the `fixed_axes` at the given index.
+    /// # Errors
+    /// This function will return an error if the index is out of bounds.
+    pub fn update_fixed_axes_at(
+        &mut self,
+        index: usize,
+        new_fixed_axes: [bool; 3],
+    ) -> Result<(), InvalidIndex> {
+        *self.fixed_axes.get_mut(index).ok_or(InvalidIndex)? = new_fixed_axes;
+        Ok(())
+    }
+    /// Update the `magnetic_moment` at the given index.
+    /// # Errors
+    /// This function will return an error if the index is out of bounds.
+    pub fn update_magnetic_moment_at(
+        &mut self,
+        index: usize,
+        new_magnetic_moment: f64,
+    ) -> Result<(), InvalidIndex> {
+        *self.magnetic_moments.get_mut(index).ok_or(InvalidIndex)? = new_magnetic_moment;
+        Ok(())
+    }
+    /// Update the `unknown_attributes` at the given index.
+    /// # Errors
+    /// This function will return an error if the index is out of bounds.
+    pub fn update_unknown_attributes_at(
+        &mut self,
+        index: usize,
+        new_attributes: Vec<MsiAttribute>,
+    ) -> Result<(), InvalidIndex> {
+        *self.unknown_attributes.get_mut(index).ok_or(InvalidIndex)? = new_attributes;
+        Ok(())
+    }
     /// Update the whole atom at the given index.
     /// # Errors
     /// This function will return an error if the index is out of bounds.
@@ -138,16 +228,24 @@ impl<T: ModelInfo> AtomCollection<T> {
         let Atom {
             element_symbol,
             atomic_number: element_id,
+            species_label,
             xyz,
             fractional_xyz,
             atom_id,
+            fixed_axes,
+            magnetic_moment,
+            unknown_attributes,
             format_type: _,
         } = new_atom;
         self.update_symbol_at(index, &element_symbol)?;
         self.update_elm_id_at(index, element_id)?;
+        self.update_species_label_at(index, species_label.as_deref())?;
         self.update_xyz_at(index, xyz)?;
         self.update_frac_xyz_at(index, fractional_xyz)?;
         self.update_atom_id_at(index, atom_id)?;
+        self.update_fixed_axes_at(index, fixed_axes)?;
+        self.update_magnetic_moment_at(index, magnetic_moment)?;
+        self.update_unknown_attributes_at(index, unknown_attributes)?;
         Ok(())
     }
 
@@ -159,6 +257,19 @@ impl<T: ModelInfo> AtomCollection<T> {
         self.atomic_nums.as_ref()
     }
 
+    pub fn species_labels(&self) -> &[Option<String>] {
+        self.species_labels.as_ref()
+    }
+    /// The species of each atom, the element symbol followed by the label if any,
+    /// e.g. `Fe:up`.
+    pub fn species(&self) -> Vec<String> {
+        self.element_symbols
+            .iter()
+            .zip(self.species_labels.iter())
+            .map(|(symbol, label)| species_name(symbol, label.as_deref()))
+            .collect()
+    }
+
     pub fn xyz_coords(&self) -> &[Point3<f64>] {
         self.xyz_coords.as_ref()
     }
@@ -179,29 +290,76 @@ impl<T: ModelInfo> AtomCollection<T> {
         self.atom_ids.as_ref()
     }
 
+    pub fn fixed_axes(&self) -> &[[bool; 3]] {
+        self.fixed_axes.as_ref()
+    }
+
+    pub fn fixed_axes_mut(&mut self) -> &mut [[bool; 3]] {
+        self.fixed_axes.as_mut()
+    }
+
+    pub fn magnetic_moments(&self) -> &[f64] {
+        self.magnetic_moments.as_ref()
+    }
+
+    pub fn magnetic_moments_mut(&mut self) -> &mut [f64] {
+        self.magnetic_moments.as_mut()
+    }
+
+    pub fn unknown_attributes(&self) -> &[Vec<MsiAttribute>] {
+        self.unknown_attributes.as_ref()
+    }
+
     pub fn size(&self) -> usize {
         self.size
     }
 }
 
+impl<T: ModelInfo> AtomCollection<T> {
+    /// Copy the atoms into a collection of another format.
+    pub(crate) fn to_format<U: ModelInfo>(&self) -> AtomCollection<U> {
+        AtomCollection {
+            element_symbols: self.element_symbols.clone(),
+            atomic_nums: self.atomic_nums.clone(),
+            species_labels: self.species_labels.clone(),
+            xyz_coords: self.xyz_coords.clone(),
+            fractional_xyz: self.fractional_xyz.clone(),
+            atom_ids: self.atom_ids.clone(),
+            fixed_axes: self.fixed_axes.clone(),
+            magnetic_moments: self.magnetic_moments.clone(),
+            unknown_attributes: self.unknown_attributes.clone(),
+            size: self.size,
+            format_type: U::default(),
+        }
+    }
+}
+
 impl<T: ModelInfo> From<Vec<Atom<T>>> for AtomCollection<T> {
     fn from(src: Vec<Atom<T>>) -> Self {
         let atom_num = src.len();
         let mut output = AtomCollection {
             element_symbols: Vec::with_capacity(atom_num),
             atomic_nums: Vec::with_capacity(atom_num),
+            species_labels: Vec::with_capacity(atom_num),
             xyz_coords: Vec::with_capacity(atom_num),
             fractional_xyz: Vec::with_capacity(atom_num),
             atom_ids: Vec::with_capacity(atom_num),
+            fixed_axes: Vec::with_capacity(atom_num),
+            magnetic_moments: Vec::with_capacity(atom_num),
+            unknown_attributes: Vec::with_capacity(atom_num),
             size: atom_num,
             format_type: T::default(),
         };
         for atom in src.into_iter() {
             output.element_symbols.push(atom.element_symbol);
             output.atomic_nums.push(atom.atomic_number);
+            output.species_labels.push(atom.species_label);
             output.xyz_coords.push(atom.xyz);
             output.fractional_xyz.push(atom.fractional_xyz);
             output.atom_ids.push(atom.atom_id);
+            output.fixed_axes.push(atom.fixed_axes);
+            output.magnetic_moments.push(atom.magnetic_moment);
+            output.unknown_attributes.push(atom.unknown_attributes);
         }
         output
     }
@@ -238,9 +396,13 @@ impl<T: ModelInfo> Add for AtomCollection<T> {
         AtomCollection {
             element_symbols: vec![self.element_symbols, rhs.element_symbols].concat(),
             atomic_nums: vec![self.atomic_nums, rhs.atomic_nums].concat(),
+            species_labels: [self.species_labels, rhs.species_labels].concat(),
             xyz_coords: vec![self.xyz_coords, rhs.xyz_coords].concat(),
             fractional_xyz: vec![self.fractional_xyz, rhs.fractional_xyz].concat(),
             atom_ids: vec![self.atom_ids, rhs.atom_ids].concat(),
+            fixed_axes: [self.fixed_axes, rhs.fixed_axes].concat(),
+            magnetic_moments: [self.magnetic_moments, rhs.magnetic_moments].concat(),
+            unknown_attributes: [self.unknown_attributes, rhs.unknown_attributes].concat(),
             size: new_size,
             format_type: T::default(),
         }
@@ -251,14 +413,18 @@ impl<T> Atom<T>
 where
     T: ModelInfo,
 {
-    /// Creates a new [`Atom`].
+    /// Creates a new [`Atom`], with the magnetic moment of the element in `ELEMENT_TABLE`.
     pub fn new(element_symbol: String, atomic_number: u8, xyz: Point3<f64>, atom_id: u32) -> Self {
         Self {
+            magnetic_moment: default_magnetic_moment(&element_symbol),
             element_symbol,
             atomic_number,
+            species_label: None,
             xyz,
             fractional_xyz: None,
             atom_id,
+            fixed_axes: [false; 3],
+            unknown_attributes: Vec::new(),
             format_type: T::default(),
         }
     }
@@ -281,6 +447,15 @@ where
         self.atomic_number = atomic_number;
     }
 
+    /// Returns the species label of this [`Atom<Format>`], e.g. `up` in `Fe:up`.
+    pub fn species_label(&self) -> Option<&str> {
+        self.species_label.as_deref()
+    }
+    /// Sets the species label of this [`Atom<Format>`].
+    pub fn set_species_label(&mut self, species_label: Option<String>) {
+        self.species_label = species_label;
+    }
+
     /// Returns a reference to the xyz of this [`Atom<Format>`].
     pub fn xyz(&self) -> &Point3<f64> {
         &self.xyz
@@ -307,6 +482,51 @@ where
     pub fn set_fractional_xyz(&mut self, fractional_xyz: Option<Point3<f64>>) {
         self.fractional_xyz = fractional_xyz;
     }
+
+    pub fn fixed_axes(&self) -> [bool; 3] {
+        self.fixed_axes
+    }
+
+    pub fn set_fixed_axes(&mut self, fixed_axes: [bool; 3]) {
+        self.fixed_axes = fixed_axes;
+    }
+
+    pub fn magnetic_moment(&self) -> f64 {
+        self.magnetic_moment
+    }
+
+    pub fn set_magnetic_moment(&mut self, magnetic_moment: f64) {
+        self.magnetic_moment = magnetic_moment;
+    }
+
+    pub fn unknown_attributes(&self) -> &[MsiAttribute] {
+        self.unknown_attributes.as_ref()
+    }
+
+    pub fn set_unknown_attributes(&mut self, unknown_attributes: Vec<MsiAttribute>) {
+        self.unknown_attributes = unknown_attributes;
+    }
+}
+
+/// The name of a species, `Fe` or `Fe:up` with a label.
+pub(crate) fn species_name(symbol: &str, label: Option<&str>) -> String {
+    match label {
+        Some(label) => format!("{}:{}", symbol, label),
+        None => symbol.to_string(),
+    }
+}
+
+/// The element symbol of a species, `Fe` for `Fe:up`.
+pub(crate) fn species_element(species: &str) -> &str {
+    species.split(':').next().unwrap_or(species)
+}
+
+/// The spin of the element in `ELEMENT_TABLE`, zero for unknown symbols.
+pub(crate) fn default_magnetic_moment(symbol: &str) -> f64 {
+    ELEMENT_TABLE
+        .get_by_symbol(symbol)
+        .map(|elm| elm.spin() as f64)
+        .unwrap_or(0.0)
 }
 
 // impl Export for Vec<Atom> {
@@ -351,18 +571,26 @@ impl From<AtomCollection<MsiModel>> for AtomCollection<CellModel> {
         let AtomCollection {
             element_symbols,
             atomic_nums: element_ids,
+            species_labels,
             xyz_coords,
             fractional_xyz,
             atom_ids,
+            fixed_axes,
+            magnetic_moments,
+            unknown_attributes,
             size,
             format_type: _,
         } = src;
         Self {
             element_symbols,
             atomic_nums: element_ids,
+            species_labels,
             xyz_coords,
             fractional_xyz,
             atom_ids,
+            fixed_axes,
+            magnetic_moments,
+            unknown_attributes,
             size,
             format_type: CellModel::default(),
         }
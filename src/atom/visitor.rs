@@ -2,6 +2,7 @@ use std::{
     collections::HashSet,
     fs::File,
     io::{self, BufRead, BufReader},
+    path::Path,
 };
 
 use castep_periodic_table::{data::ELEMENT_TABLE, element::LookupElement};
@@ -38,7 +39,10 @@ pub trait VisitCollection<T: ModelInfo> {
     fn view_atom_by_id(&self, atom_id: u32) -> Result<AtomView<T>, InvalidIndex>;
     fn get_vector_ab(&self, a_id: u32, b_id: u32) -> Result<Vector3<f64>, InvalidIndex>;
     fn element_set(&self) -> Vec<String>;
-    fn spin_total(&self) -> u8;
+    /// The species in the model, labelled species listed after the bare element.
+    fn species_set(&self) -> Vec<String>;
+    /// The net spin of the initial magnetic moments, negative for a net spin down.
+    fn spin_total(&self) -> f64;
     fn get_final_cutoff_energy(&self, potentials_loc: &str) -> Result<f64, io::Error>;
 }
 
@@ -66,15 +70,27 @@ where
             .ok_or(InvalidIndex)?
             .as_str();
         let element_id = self.atomic_nums.get(index).ok_or(InvalidIndex)?;
+        let species_label = self
+            .species_labels
+            .get(index)
+            .ok_or(InvalidIndex)?
+            .as_deref();
         let xyz = self.xyz_coords.get(index).ok_or(InvalidIndex)?;
         let fractional_xyz = self.fractional_xyz.get(index).ok_or(InvalidIndex)?.as_ref();
         let atom_id = self.atom_ids.get(index).ok_or(InvalidIndex)?;
+        let fixed_axes = self.fixed_axes.get(index).ok_or(InvalidIndex)?;
+        let magnetic_moment = self.magnetic_moments.get(index).ok_or(InvalidIndex)?;
+        let unknown_attributes = self.unknown_attributes().get(index).ok_or(InvalidIndex)?;
         Ok(AtomView {
             element_symbol,
             atomic_number: element_id,
+            species_label,
             xyz,
             fractional_xyz,
             atom_id,
+            fixed_axes,
+            magnetic_moment,
+            unknown_attributes,
             format_type: T::default(),
         })
     }
@@ -116,55 +132,74 @@ where
             .collect::<Vec<String>>()
     }
 
-    fn spin_total(&self) -> u8 {
-        self.element_symbols()
+    fn species_set(&self) -> Vec<String> {
+        let mut species_list: Vec<(u8, String)> = self
+            .atomic_nums()
             .iter()
-            .map(|symbol| -> u8 { ELEMENT_TABLE.get_by_symbol(symbol).unwrap().spin })
-            .reduce(|total, next| total + next)
-            .unwrap()
+            .copied()
+            .zip(self.species())
+            .collect::<HashSet<(u8, String)>>()
+            .into_iter()
+            .collect();
+        species_list.sort_unstable();
+        species_list
+            .into_iter()
+            .map(|(_, species)| species)
+            .collect()
+    }
+
+    fn spin_total(&self) -> f64 {
+        self.magnetic_moments().iter().sum()
     }
 
     fn get_final_cutoff_energy(&self, potentials_loc: &str) -> Result<f64, io::Error> {
-        let mut energy: f64 = 0.0;
         self.element_set()
             .iter()
-            .try_for_each(|elm| -> Result<(), io::Error> {
+            .map(|elm| {
                 let potential_file = ELEMENT_TABLE.get_by_symbol(elm).unwrap().potential();
-                let potential_path = format!("{potentials_loc}/{potential_file}");
-                if let Ok(file) = File::open(&potential_path) {
-                    let file = BufReader::new(file);
-                    let fine_energy: u32 = file
-                        .lines()
-                        .find(|line| line.as_ref().unwrap().contains("FINE"))
-                        .map(|line| {
-                            let num_str = line.as_ref().unwrap().split_whitespace().next().unwrap();
-                            num_str.parse::<u32>().unwrap()
-                        })
-                        .unwrap();
-                    let round_bigger_tenth = |num: u32| -> f64 {
-                        match num % 10 {
-                            0 => num as f64,
-                            _ => ((num / 10 + 1) * 10) as f64,
-                        }
-                    };
-                    let ultra_fine_energy = round_bigger_tenth((fine_energy as f64 * 1.1) as u32);
-                    energy = if energy > ultra_fine_energy {
-                        energy
-                    } else {
-                        ultra_fine_energy
-                    };
-                    Ok(())
-                } else {
-                    panic!(
-                        "Error while reading potential file for element: {}, {}",
-                        elm, potential_path
-                    )
-                }
-            })?;
-        Ok(energy)
+                ultra_fine_energy(elm, &Path::new(potentials_loc).join(potential_file))
+            })
+            .try_fold(0.0, |energy, next| Ok(next?.max(energy)))
     }
 }
 
+/// The `FINE` energy in the potential file of the species, raised by 10% and
+/// rounded up to the next ten.
+/// # Errors
+/// This function will return the error of opening or reading the potential file,
+/// or an error of `io::ErrorKind::InvalidData` if it has no valid `FINE` line.
+fn ultra_fine_energy(species: &str, potential_path: &Path) -> Result<f64, io::Error> {
+    let file = BufReader::new(File::open(potential_path)?);
+    let mut fine_energy: Option<u32> = None;
+    for line in file.lines() {
+        let line = line?;
+        if line.contains("FINE") {
+            fine_energy = line
+                .split_whitespace()
+                .next()
+                .and_then(|num_str| num_str.parse::<u32>().ok());
+            break;
+        }
+    }
+    let fine_energy = fine_energy.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "No FINE energy in the potential file for {}: {}",
+                species,
+                potential_path.display()
+            ),
+        )
+    })?;
+    let round_bigger_tenth = |num: u32| -> f64 {
+        match num % 10 {
+            0 => num as f64,
+            _ => ((num / 10 + 1) * 10) as f64,
+        }
+    };
+    Ok(round_bigger_tenth((fine_energy as f64 * 1.1) as u32))
+}
+
 impl<T> VisitCollection<T> for LatticeModel<T>
 where
     T: ModelInfo,
@@ -195,11 +230,25 @@ where
         self.atoms().element_set()
     }
 
-    fn spin_total(&self) -> u8 {
+    fn species_set(&self) -> Vec<String> {
+        self.atoms().species_set()
+    }
+
+    fn spin_total(&self) -> f64 {
         self.atoms().spin_total()
     }
 
+    /// Uses the pseudopotentials assigned in `Settings<T>`.
+    /// Species with OTFG strings have no file to read and are skipped.
     fn get_final_cutoff_energy(&self, potentials_loc: &str) -> Result<f64, io::Error> {
-        self.atoms().get_final_cutoff_energy(potentials_loc)
+        self.species_set()
+            .iter()
+            .filter_map(|species| {
+                let potential = self.settings().species_potential(species);
+                potential
+                    .file()
+                    .map(|file| ultra_fine_energy(species, &Path::new(potentials_loc).join(file)))
+            })
+            .try_fold(0.0, |energy, next| Ok(next?.max(energy)))
     }
 }
@@ -1,18 +1,68 @@
 use crate::ModelInfo;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BondType {
+    #[default]
     Single,
     Double,
     Triple,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A bond between two atoms, referred by their atom ids.
 pub struct Bond((u32, u32));
 
-#[derive(Debug)]
+impl Bond {
+    pub fn new(atom_a: u32, atom_b: u32) -> Self {
+        Self((atom_a, atom_b))
+    }
+
+    pub fn atom_ids(&self) -> (u32, u32) {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Bonds<T: ModelInfo> {
     bonds: Vec<Bond>,
     bond_types: Vec<BondType>,
     format_type: T,
 }
+
+impl<T: ModelInfo> Bonds<T> {
+    pub fn new(bonds: Vec<Bond>, bond_types: Vec<BondType>) -> Self {
+        Self {
+            bonds,
+            bond_types,
+            format_type: T::default(),
+        }
+    }
+
+    pub fn push(&mut self, bond: Bond, bond_type: BondType) {
+        self.bonds.push(bond);
+        self.bond_types.push(bond_type);
+    }
+
+    pub fn bonds(&self) -> &[Bond] {
+        self.bonds.as_ref()
+    }
+
+    pub fn bond_types(&self) -> &[BondType] {
+        self.bond_types.as_ref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bonds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bonds.is_empty()
+    }
+    /// Copy the bonds into another format.
+    pub(crate) fn to_format<U: ModelInfo>(&self) -> Bonds<U> {
+        Bonds::new(self.bonds.clone(), self.bond_types.clone())
+    }
+}
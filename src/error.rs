@@ -34,3 +34,52 @@ impl Display for InvalidCoord {
 }
 
 impl Error for InvalidCoord {}
+
+#[derive(Debug, PartialEq, Eq)]
+/// Error type of invalid `CELL_CONSTRAINTS`.
+pub enum CellConstraintsError {
+    /// A length and an angle are tied by the same index.
+    TiedLengthAndAngle(u8),
+    /// The constraints relax the cell while `FIX_ALL_CELL` is true.
+    ConflictWithFixAllCell,
+}
+
+impl Display for CellConstraintsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CellConstraintsError::TiedLengthAndAngle(index) => {
+                write!(f, "A length and an angle are tied by index {}", index)
+            }
+            CellConstraintsError::ConflictWithFixAllCell => {
+                write!(
+                    f,
+                    "Cell constraints relax the cell while FIX_ALL_CELL is true"
+                )
+            }
+        }
+    }
+}
+
+impl Error for CellConstraintsError {}
+
+#[derive(Debug, PartialEq, Eq)]
+/// Error type when writing a model as the input of `pw.x`.
+pub enum PwInputError {
+    /// The model has no lattice vectors.
+    NotPeriodic,
+    /// The lattice vectors are linearly dependent.
+    SingularLattice,
+}
+
+impl Display for PwInputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PwInputError::NotPeriodic => write!(f, "The model has no lattice vectors"),
+            PwInputError::SingularLattice => {
+                write!(f, "The lattice vectors are not independent!")
+            }
+        }
+    }
+}
+
+impl Error for PwInputError {}
@@ -1,18 +1,24 @@
 use std::ops::Add;
 
-use na::Matrix3;
+use na::{Matrix3, UnitQuaternion, Vector, Vector3};
 
 use crate::{
     atom::AtomCollection,
+    bond::Bonds,
     model_type::{ModelInfo, Settings},
     Transformation,
 };
 
+pub mod symmetry;
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LatticeModel<T: ModelInfo> {
     lattice_vectors: Option<LatticeVectors<T>>,
     atoms: AtomCollection<T>,
     settings: Settings<T>,
+    /// Bonds between atoms, empty if the format does not record them.
+    bonds: Bonds<T>,
 }
 
 impl<T> LatticeModel<T>
@@ -28,6 +34,7 @@ where
             lattice_vectors,
             atoms,
             settings,
+            bonds: Bonds::default(),
         }
     }
 
@@ -50,6 +57,34 @@ where
     pub fn settings(&self) -> &Settings<T> {
         &self.settings
     }
+
+    pub fn settings_mut(&mut self) -> &mut Settings<T> {
+        &mut self.settings
+    }
+
+    pub fn bonds(&self) -> &Bonds<T> {
+        &self.bonds
+    }
+
+    pub fn set_bonds(&mut self, bonds: Bonds<T>) {
+        self.bonds = bonds;
+    }
+
+    /// Rotate the atoms and lattice vectors so that vector a is along the x axis.
+    /// Does nothing for a model without lattice vectors.
+    pub(crate) fn rotate_a_to_x(&mut self) {
+        let Some(lattice_vectors) = self.lattice_vectors() else {
+            return;
+        };
+        let x_axis: Vector3<f64> = Vector::x();
+        let a_vec = lattice_vectors.vectors().column(0);
+        let a_to_x_angle = a_vec.angle(&x_axis);
+        if a_to_x_angle != 0.0 {
+            let rot_axis = a_vec.cross(&x_axis).normalize();
+            let rot_quatd: UnitQuaternion<f64> = UnitQuaternion::new(rot_axis * a_to_x_angle);
+            self.rotate(&rot_quatd);
+        }
+    }
 }
 
 impl<T: ModelInfo> AsRef<LatticeModel<T>> for LatticeModel<T> {
@@ -65,6 +100,7 @@ impl<T: ModelInfo> AsMut<LatticeModel<T>> for LatticeModel<T> {
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LatticeVectors<T: ModelInfo> {
     vectors: Matrix3<f64>,
     model_type: T,
@@ -93,6 +129,43 @@ where
         }
     }
 
+    /// Build the lattice vectors from the lengths (Å) and angles (degrees).
+    /// Vector a is placed along the x axis, and vector b is in the xy plane.
+    pub fn from_lattice_params(lengths: [f64; 3], angles: [f64; 3]) -> Self {
+        let [len_a, len_b, len_c] = lengths;
+        let [alpha, beta, gamma] = angles.map(|deg| deg.to_radians());
+        let c_x = len_c * beta.cos();
+        let c_y = len_c * (alpha.cos() - beta.cos() * gamma.cos()) / gamma.sin();
+        let c_z = (len_c.powi(2) - c_x.powi(2) - c_y.powi(2)).sqrt();
+        let vectors = Matrix3::new(
+            len_a,
+            len_b * gamma.cos(),
+            c_x,
+            0.0,
+            len_b * gamma.sin(),
+            c_y,
+            0.0,
+            0.0,
+            c_z,
+        );
+        Self::new(vectors)
+    }
+
+    /// The lengths (Å) and angles (degrees) of the lattice vectors.
+    pub fn lattice_params(&self) -> ([f64; 3], [f64; 3]) {
+        let vec_a = self.vectors.column(0);
+        let vec_b = self.vectors.column(1);
+        let vec_c = self.vectors.column(2);
+        (
+            [vec_a.norm(), vec_b.norm(), vec_c.norm()],
+            [
+                vec_b.angle(&vec_c).to_degrees(),
+                vec_a.angle(&vec_c).to_degrees(),
+                vec_a.angle(&vec_b).to_degrees(),
+            ],
+        )
+    }
+
     pub fn fractional_coord_matrix(&self) -> Matrix3<f64> {
         let lattice_vectors = self.vectors();
         let vec_a = lattice_vectors.column(0);
@@ -156,15 +229,23 @@ where
 
     fn add(self, rhs: Self) -> Self::Output {
         let new_atoms = self.atoms + rhs.atoms;
+        let mut bonds = self.bonds;
+        rhs.bonds
+            .bonds()
+            .iter()
+            .zip(rhs.bonds.bond_types())
+            .for_each(|(bond, bond_type)| bonds.push(*bond, *bond_type));
         let Self {
             lattice_vectors,
             atoms: _,
             settings,
+            bonds: _,
         } = self;
         Self {
             lattice_vectors,
             atoms: new_atoms,
             settings,
+            bonds,
         }
     }
 }
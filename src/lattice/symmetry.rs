@@ -0,0 +1,221 @@
+use std::{fmt::Display, str::FromStr};
+
+use na::{Matrix3, Point3, Vector3};
+use nom::{
+    branch::alt,
+    character::complete::{char, one_of, space0},
+    combinator::{all_consuming, map, opt},
+    multi::many1,
+    sequence::{delimited, pair, preceded, tuple},
+    IResult,
+};
+
+use crate::parser::{decimal, float};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A symmetry operation acting on fractional coordinates, `r' = W r + w`.
+pub struct SymmetryOperation {
+    rotation: Matrix3<f64>,
+    translation: Vector3<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The operation string can not be parsed, e.g. `x, y`.
+pub struct InvalidSymmetryOperation(pub String);
+
+impl Display for InvalidSymmetryOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid symmetry operation: \"{}\"", self.0)
+    }
+}
+
+impl std::error::Error for InvalidSymmetryOperation {}
+
+/// A term in a component: `x`, `2y`, `1/2` or `0.25`.
+enum Term {
+    Axis(usize, f64),
+    Shift(f64),
+}
+
+fn axis(input: &str) -> IResult<&str, usize> {
+    map(one_of("xyzXYZ"), |c: char| match c.to_ascii_lowercase() {
+        'x' => 0,
+        'y' => 1,
+        _ => 2,
+    })(input)
+}
+
+fn term(input: &str) -> IResult<&str, Term> {
+    let fraction = map(
+        tuple((decimal, preceded(char('/'), decimal))),
+        |(num, den): (&str, &str)| {
+            Term::Shift(num.parse::<f64>().unwrap() / den.parse::<f64>().unwrap())
+        },
+    );
+    let scaled_axis = map(pair(alt((float, decimal)), axis), |(factor, index)| {
+        Term::Axis(index, factor.parse().unwrap())
+    });
+    let shift = map(alt((float, decimal)), |n: &str| {
+        Term::Shift(n.parse().unwrap())
+    });
+    alt((
+        map(axis, |index| Term::Axis(index, 1.0)),
+        fraction,
+        scaled_axis,
+        shift,
+    ))(input)
+}
+
+/// One component of the operation, e.g. `-x+y+1/2`.
+fn component(input: &str) -> IResult<&str, (Vector3<f64>, f64)> {
+    let (rest, terms) = many1(delimited(
+        space0,
+        pair(opt(one_of("+-")), preceded(space0, term)),
+        space0,
+    ))(input)?;
+    let mut row = Vector3::zeros();
+    let mut shift = 0.0;
+    terms.into_iter().for_each(|(sign, term)| {
+        let sign = if sign == Some('-') { -1.0 } else { 1.0 };
+        match term {
+            Term::Axis(index, factor) => row[index] += sign * factor,
+            Term::Shift(value) => shift += sign * value,
+        }
+    });
+    Ok((rest, (row, shift)))
+}
+
+impl SymmetryOperation {
+    pub fn new(rotation: Matrix3<f64>, translation: Vector3<f64>) -> Self {
+        Self {
+            rotation,
+            translation,
+        }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(Matrix3::identity(), Vector3::zeros())
+    }
+
+    pub fn rotation(&self) -> &Matrix3<f64> {
+        &self.rotation
+    }
+
+    pub fn translation(&self) -> &Vector3<f64> {
+        &self.translation
+    }
+    /// Apply to fractional coordinates. The result is wrapped into `[0, 1)`.
+    pub fn apply(&self, frac_xyz: &Point3<f64>) -> Point3<f64> {
+        let new_xyz = self.rotation * frac_xyz + self.translation;
+        new_xyz.map(wrap_fractional)
+    }
+}
+
+/// Wrap a fractional coordinate into `[0, 1)`.
+pub fn wrap_fractional(value: f64) -> f64 {
+    let wrapped = value - value.floor();
+    // Values like 0.9999999999 are rounding errors of 1.0.
+    if (wrapped - 1.0).abs() < 1e-10 {
+        0.0
+    } else {
+        wrapped
+    }
+}
+
+/// Parse the `x, y+1/2, -z` notation used in `cif`.
+impl FromStr for SymmetryOperation {
+    type Err = InvalidSymmetryOperation;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidSymmetryOperation(s.into());
+        let components: Vec<(Vector3<f64>, f64)> = s
+            .split(',')
+            .map(|c| all_consuming(component)(c).map(|(_, parsed)| parsed))
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+        if components.len() != 3 {
+            return Err(invalid());
+        }
+        let rotation = Matrix3::from_rows(&[
+            components[0].0.transpose(),
+            components[1].0.transpose(),
+            components[2].0.transpose(),
+        ]);
+        let translation = Vector3::new(components[0].1, components[1].1, components[2].1);
+        Ok(Self::new(rotation, translation))
+    }
+}
+
+/// Write back in the `x, y+1/2, -z` notation.
+impl Display for SymmetryOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let components: Vec<String> = (0..3)
+            .map(|i| {
+                let mut component = String::new();
+                self.rotation
+                    .row(i)
+                    .iter()
+                    .zip(["x", "y", "z"])
+                    .filter(|(factor, _)| **factor != 0.0)
+                    .for_each(|(factor, axis)| {
+                        let sign = if *factor < 0.0 {
+                            "-"
+                        } else if component.is_empty() {
+                            ""
+                        } else {
+                            "+"
+                        };
+                        let magnitude = if factor.abs() == 1.0 {
+                            String::new()
+                        } else {
+                            format!("{}", factor.abs())
+                        };
+                        component.push_str(&format!("{sign}{magnitude}{axis}"));
+                    });
+                let shift = self.translation[i];
+                if shift != 0.0 {
+                    let sign = if shift < 0.0 { "-" } else { "+" };
+                    component.push_str(&format!("{sign}{}", fraction_str(shift.abs())));
+                }
+                if component.is_empty() {
+                    component.push('0');
+                }
+                component
+            })
+            .collect();
+        write!(f, "{}", components.join(","))
+    }
+}
+
+/// `0.5` as `1/2`, with denominators up to 12.
+fn fraction_str(value: f64) -> String {
+    (1..=12_u32)
+        .find_map(|den| {
+            let num = value * den as f64;
+            ((num - num.round()).abs() < 1e-8).then(|| match den {
+                1 => format!("{}", num.round()),
+                _ => format!("{}/{}", num.round(), den),
+            })
+        })
+        .unwrap_or_else(|| format!("{}", value))
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use na::Point3;
+
+    use super::SymmetryOperation;
+
+    #[test]
+    fn parse_symmetry_operations() {
+        let op = SymmetryOperation::from_str("-y+1/2, x-y, z+0.25").unwrap();
+        let new_xyz = op.apply(&Point3::new(0.1, 0.2, 0.9));
+        assert!((new_xyz - Point3::new(0.3, 0.9, 0.15)).norm() < 1e-10);
+        assert_eq!(format!("{}", op), "-y+1/2,x-y,z+1/4");
+        assert_eq!(SymmetryOperation::from_str(&format!("{}", op)).unwrap(), op);
+        assert!(SymmetryOperation::from_str("x, y").is_err());
+    }
+}
@@ -4,11 +4,13 @@ pub mod bond;
 pub mod builder_typestate;
 pub mod error;
 pub mod lattice;
+pub mod model_io;
 pub mod model_type;
 pub mod param_writer;
 pub mod parser;
 #[cfg(test)]
 mod test;
+pub mod units;
 
 extern crate castep_periodic_table as cpt;
 extern crate nalgebra as na;
@@ -17,8 +19,12 @@ use na::UnitQuaternion;
 
 pub use atom::Atom;
 pub use lattice::LatticeModel;
+pub use model_io::{load_model, load_model_as, save_model};
 pub use model_type::cell::CellModel;
+pub use model_type::extxyz::ExtXyzModel;
 pub use model_type::msi::MsiModel;
+pub use model_type::poscar::PoscarModel;
+pub use model_type::xsd::XsdModel;
 pub use model_type::ModelInfo;
 
 /// Transformation for atoms and lattices.
@@ -0,0 +1,42 @@
+use std::{error::Error, fmt::Display, io};
+
+use super::ModelFormat;
+
+#[derive(Debug)]
+/// Error type when loading or saving a model with format detection.
+pub enum ModelIoError {
+    Io(io::Error),
+    /// The format can not be told from the path or the content.
+    UnknownFormat(String),
+    /// The content can not be parsed in the detected format.
+    Parse {
+        format: ModelFormat,
+        source: Box<dyn Error>,
+    },
+    /// Conversions between formats go through periodic models.
+    NotPeriodic,
+}
+
+impl Display for ModelIoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelIoError::Io(e) => write!(f, "{}", e),
+            ModelIoError::UnknownFormat(path) => write!(f, "Unknown model format: {}", path),
+            ModelIoError::Parse { format, source } => {
+                write!(f, "Failed to parse as {}: {}", format, source)
+            }
+            ModelIoError::NotPeriodic => write!(
+                f,
+                "The model has no lattice vectors to be converted into another format."
+            ),
+        }
+    }
+}
+
+impl Error for ModelIoError {}
+
+impl From<io::Error> for ModelIoError {
+    fn from(e: io::Error) -> Self {
+        ModelIoError::Io(e)
+    }
+}
@@ -0,0 +1,341 @@
+use std::{fmt::Display, fs, path::Path, str::FromStr};
+
+use crate::{
+    lattice::LatticeModel,
+    model_type::{CifExport, DefaultExport},
+    parser::cif_parser::CifStructure,
+    CellModel, ExtXyzModel, ModelInfo, MsiModel, PoscarModel, XsdModel,
+};
+
+pub use self::error::ModelIoError;
+
+mod error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Structure file formats supported by `load_model` and `save_model`.
+pub enum ModelFormat {
+    Msi,
+    Cell,
+    ExtXyz,
+    Cif,
+    Poscar,
+    Xsd,
+}
+
+impl ModelFormat {
+    /// By the extension, case-insensitively. `POSCAR`/`CONTCAR` are recognised
+    /// by the file name as they usually have no extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase());
+        match extension.as_deref() {
+            Some("msi") => Some(Self::Msi),
+            Some("cell") => Some(Self::Cell),
+            Some("xyz") | Some("extxyz") => Some(Self::ExtXyz),
+            Some("cif") => Some(Self::Cif),
+            Some("vasp") | Some("poscar") => Some(Self::Poscar),
+            Some("xsd") => Some(Self::Xsd),
+            _ => {
+                let file_name = path.file_name()?.to_str()?.to_uppercase();
+                (file_name.starts_with("POSCAR") || file_name.starts_with("CONTCAR"))
+                    .then_some(Self::Poscar)
+            }
+        }
+    }
+    /// By the content, for files without a recognised extension.
+    pub fn sniff(content: &str) -> Option<Self> {
+        let trimmed = content.trim_start();
+        let lowercase = content.to_lowercase();
+        let mut lines = content.lines().map(str::trim);
+        let first_line = lines.next().unwrap_or_default();
+        let second_line = lines.next().unwrap_or_default();
+        let is_model_object = |line: &str| {
+            line.strip_prefix('(')
+                .and_then(|rest| rest.split_once(' '))
+                .map(|(num, object)| num.parse::<u32>().is_ok() && object.trim() == "Model")
+                .unwrap_or(false)
+        };
+        if trimmed.starts_with("<?xml") || trimmed.starts_with("<XSD") {
+            Some(Self::Xsd)
+        } else if trimmed.starts_with("# MSI")
+            || content.lines().map(str::trim).any(is_model_object)
+        {
+            Some(Self::Msi)
+        } else if lowercase.contains("%block lattice_") {
+            Some(Self::Cell)
+        } else if lowercase.contains("data_") && lowercase.contains("_cell_length_a") {
+            Some(Self::Cif)
+        } else if first_line.parse::<usize>().is_ok() {
+            Some(Self::ExtXyz)
+        } else if second_line.parse::<f64>().is_ok() {
+            Some(Self::Poscar)
+        } else {
+            None
+        }
+    }
+    /// The extension first, then the content.
+    pub fn detect<P: AsRef<Path>>(path: P, content: &str) -> Option<Self> {
+        Self::from_path(path).or_else(|| Self::sniff(content))
+    }
+}
+
+impl Display for ModelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ModelFormat::Msi => "msi",
+            ModelFormat::Cell => "cell",
+            ModelFormat::ExtXyz => "xyz",
+            ModelFormat::Cif => "cif",
+            ModelFormat::Poscar => "POSCAR",
+            ModelFormat::Xsd => "xsd",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A model loaded from any supported format. `cif` is read into `Cell`.
+pub enum AnyModel {
+    Msi(LatticeModel<MsiModel>),
+    Cell(LatticeModel<CellModel>),
+    ExtXyz(LatticeModel<ExtXyzModel>),
+    Poscar(LatticeModel<PoscarModel>),
+    Xsd(LatticeModel<XsdModel>),
+}
+
+impl AnyModel {
+    /// Parse the content in the given format.
+    pub fn parse(content: &str, format: ModelFormat) -> Result<Self, ModelIoError> {
+        let parse_error =
+            |source: Box<dyn std::error::Error>| ModelIoError::Parse { format, source };
+        match format {
+            ModelFormat::Msi => LatticeModel::<MsiModel>::from_str(content)
+                .map(Self::Msi)
+                .map_err(|e| parse_error(e.into())),
+            ModelFormat::Cell => LatticeModel::<CellModel>::from_str(content)
+                .map(Self::Cell)
+                .map_err(|e| parse_error(e.into())),
+            ModelFormat::ExtXyz => LatticeModel::<ExtXyzModel>::from_str(content)
+                .map(Self::ExtXyz)
+                .map_err(|e| parse_error(e.into())),
+            ModelFormat::Cif => CifStructure::from_str(content)
+                .map(|cif| Self::Cell(cif.into_model()))
+                .map_err(|e| parse_error(e.into())),
+            ModelFormat::Poscar => LatticeModel::<PoscarModel>::from_str(content)
+                .map(Self::Poscar)
+                .map_err(|e| parse_error(e.into())),
+            ModelFormat::Xsd => LatticeModel::<XsdModel>::from_str(content)
+                .map(Self::Xsd)
+                .map_err(|e| parse_error(e.into())),
+        }
+    }
+
+    fn is_periodic(&self) -> bool {
+        match self {
+            AnyModel::Msi(model) => model.lattice_vectors().is_some(),
+            AnyModel::Cell(model) => model.lattice_vectors().is_some(),
+            AnyModel::ExtXyz(model) => model.lattice_vectors().is_some(),
+            AnyModel::Poscar(model) => model.lattice_vectors().is_some(),
+            AnyModel::Xsd(model) => model.lattice_vectors().is_some(),
+        }
+    }
+    /// Convert into the target format with the existing `From` conversions.
+    /// `Msi` converts directly, keeping what the target shares with it (e.g. bonds in `Xsd`),
+    /// other formats go through `LatticeModel<CellModel>`.
+    /// The model needs lattice vectors.
+    pub fn into_model<T>(self) -> Result<LatticeModel<T>, ModelIoError>
+    where
+        T: ModelInfo,
+        LatticeModel<T>: From<LatticeModel<CellModel>> + From<LatticeModel<MsiModel>>,
+    {
+        if !self.is_periodic() {
+            return Err(ModelIoError::NotPeriodic);
+        }
+        let model = match self {
+            AnyModel::Msi(model) => model.into(),
+            AnyModel::Cell(model) => model.into(),
+            AnyModel::ExtXyz(model) => LatticeModel::<CellModel>::from(model).into(),
+            AnyModel::Poscar(model) => LatticeModel::<CellModel>::from(model).into(),
+            AnyModel::Xsd(model) => LatticeModel::<CellModel>::from(model).into(),
+        };
+        Ok(model)
+    }
+    /// Export in the given format. A model already in that format is written as it is.
+    pub fn export(&self, format: ModelFormat) -> Result<String, ModelIoError> {
+        let exported = match (format, self) {
+            (ModelFormat::Msi, AnyModel::Msi(model)) => model.export(),
+            (ModelFormat::Msi, _) => self.clone().into_model::<MsiModel>()?.export(),
+            (ModelFormat::Cell, AnyModel::Cell(model)) => DefaultExport::<CellModel>::export(model),
+            (ModelFormat::Cell, _) => {
+                DefaultExport::<CellModel>::export(&self.clone().into_model::<CellModel>()?)
+            }
+            (ModelFormat::Cif, _) if !self.is_periodic() => return Err(ModelIoError::NotPeriodic),
+            (ModelFormat::Cif, AnyModel::Cell(model)) => {
+                model.export_cif().ok_or(ModelIoError::NotPeriodic)?
+            }
+            (ModelFormat::Cif, _) => self
+                .clone()
+                .into_model::<CellModel>()?
+                .export_cif()
+                .ok_or(ModelIoError::NotPeriodic)?,
+            (ModelFormat::ExtXyz, AnyModel::ExtXyz(model)) => model.export(),
+            (ModelFormat::ExtXyz, _) => self.clone().into_model::<ExtXyzModel>()?.export(),
+            (ModelFormat::Poscar, AnyModel::Poscar(model)) => model.export(),
+            (ModelFormat::Poscar, _) => self.clone().into_model::<PoscarModel>()?.export(),
+            (ModelFormat::Xsd, AnyModel::Xsd(model)) => model.export(),
+            (ModelFormat::Xsd, _) => self.clone().into_model::<XsdModel>()?.export(),
+        };
+        Ok(exported)
+    }
+}
+
+impl From<LatticeModel<MsiModel>> for AnyModel {
+    fn from(model: LatticeModel<MsiModel>) -> Self {
+        AnyModel::Msi(model)
+    }
+}
+
+impl From<LatticeModel<CellModel>> for AnyModel {
+    fn from(model: LatticeModel<CellModel>) -> Self {
+        AnyModel::Cell(model)
+    }
+}
+
+impl From<LatticeModel<ExtXyzModel>> for AnyModel {
+    fn from(model: LatticeModel<ExtXyzModel>) -> Self {
+        AnyModel::ExtXyz(model)
+    }
+}
+
+impl From<LatticeModel<PoscarModel>> for AnyModel {
+    fn from(model: LatticeModel<PoscarModel>) -> Self {
+        AnyModel::Poscar(model)
+    }
+}
+
+impl From<LatticeModel<XsdModel>> for AnyModel {
+    fn from(model: LatticeModel<XsdModel>) -> Self {
+        AnyModel::Xsd(model)
+    }
+}
+
+/// Load a structure file, detecting the format by the extension, or by the content
+/// when the extension is not recognised.
+/// # Example:
+/// ```no_run
+/// use castep_model_core::{load_model, model_io::AnyModel};
+///
+/// match load_model("SAC_GDY_V.msi").unwrap() {
+///     AnyModel::Msi(msi_model) => println!("{:?}", msi_model.settings()),
+///     _ => (),
+/// }
+/// ```
+pub fn load_model<P: AsRef<Path>>(path: P) -> Result<AnyModel, ModelIoError> {
+    let content = fs::read_to_string(&path)?;
+    let format = ModelFormat::detect(&path, &content)
+        .ok_or_else(|| ModelIoError::UnknownFormat(path.as_ref().display().to_string()))?;
+    AnyModel::parse(&content, format)
+}
+
+/// Load a structure file and convert it into `LatticeModel<T>`.
+pub fn load_model_as<T, P>(path: P) -> Result<LatticeModel<T>, ModelIoError>
+where
+    T: ModelInfo,
+    P: AsRef<Path>,
+    LatticeModel<T>: From<LatticeModel<CellModel>> + From<LatticeModel<MsiModel>>,
+{
+    load_model(path)?.into_model()
+}
+
+/// Save the model in the format given by the extension of `path`.
+pub fn save_model<M, P>(model: M, path: P) -> Result<(), ModelIoError>
+where
+    M: Into<AnyModel>,
+    P: AsRef<Path>,
+{
+    let format = ModelFormat::from_path(&path)
+        .ok_or_else(|| ModelIoError::UnknownFormat(path.as_ref().display().to_string()))?;
+    let content = model.into().export(format)?;
+    fs::write(path, content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::env::temp_dir;
+
+    use crate::{load_model, load_model_as, save_model, CellModel, ExtXyzModel, LatticeModel};
+
+    use super::{AnyModel, ModelFormat, ModelIoError};
+
+    const XYZ: &str = r#"2
+Lattice="5.0 0.0 0.0 0.0 5.0 0.0 0.0 0.0 5.0" Properties=species:S:1:pos:R:3 pbc="T T T"
+Fe 0.0 0.0 0.0
+O  2.5 2.5 2.5
+"#;
+
+    #[test]
+    fn detect_formats() {
+        assert_eq!(
+            ModelFormat::from_path("a/CONTCAR"),
+            Some(ModelFormat::Poscar)
+        );
+        assert_eq!(ModelFormat::from_path("a/b.CIF"), Some(ModelFormat::Cif));
+        assert_eq!(ModelFormat::sniff(XYZ), Some(ModelFormat::ExtXyz));
+        assert_eq!(
+            ModelFormat::sniff("# MSI CERIUS2 DataModel File Version 4 0\n(1 Model\n)"),
+            Some(ModelFormat::Msi)
+        );
+        assert_eq!(
+            ModelFormat::sniff("%BLOCK LATTICE_CART\n%ENDBLOCK LATTICE_CART\n"),
+            Some(ModelFormat::Cell)
+        );
+        assert_eq!(
+            ModelFormat::detect("model.txt", "data_test\n_cell_length_a 5.0\n"),
+            Some(ModelFormat::Cif)
+        );
+    }
+
+    #[test]
+    fn save_and_load() {
+        let xyz_model: LatticeModel<ExtXyzModel> = XYZ.parse().unwrap();
+        let dir = temp_dir();
+        let cell_path = dir.join("model_io_save_and_load.cell");
+        let poscar_path = dir.join("POSCAR_model_io_save_and_load");
+        save_model(xyz_model.clone(), &cell_path).unwrap();
+        save_model(xyz_model, &poscar_path).unwrap();
+        let cell_model: LatticeModel<CellModel> = load_model_as(&cell_path).unwrap();
+        assert_eq!(cell_model.atoms().element_symbols()[1], "O");
+        match load_model(&poscar_path).unwrap() {
+            AnyModel::Poscar(model) => {
+                // Species are grouped in the order of atomic numbers.
+                assert_eq!(model.atoms().element_symbols()[0], "O");
+                assert!((model.atoms().xyz_coords()[0].x - 2.5).abs() < 1e-10);
+            }
+            other => panic!("Unexpected model: {:?}", other),
+        }
+        std::fs::remove_file(cell_path).unwrap();
+        std::fs::remove_file(poscar_path).unwrap();
+    }
+
+    #[test]
+    fn save_not_periodic() {
+        let xyz_model: LatticeModel<ExtXyzModel> =
+            "2\nProperties=species:S:1:pos:R:3\nFe 0.0 0.0 0.0\nO  2.5 2.5 2.5\n"
+                .parse()
+                .unwrap();
+        let cell_model = AnyModel::Cell(LatticeModel::<CellModel>::from(xyz_model));
+        [ModelFormat::Cif, ModelFormat::Poscar, ModelFormat::Msi]
+            .into_iter()
+            .for_each(|format| {
+                assert!(matches!(
+                    cell_model.export(format),
+                    Err(ModelIoError::NotPeriodic)
+                ))
+            });
+        assert!(cell_model.export(ModelFormat::Cell).is_ok());
+    }
+}
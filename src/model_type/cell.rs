@@ -1,19 +1,20 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
 use crate::{
     atom::{visitor::VisitCollection, AtomCollection},
     lattice::{LatticeModel, LatticeVectors},
     param_writer::ms_aux_files::{KptAux, TrjAux},
-    Transformation,
+    units::BOHR_TO_ANG,
 };
 
-use cpt::{data::ELEMENT_TABLE, element::LookupElement};
-use na::{UnitQuaternion, Vector, Vector3};
 use nalgebra::Point3;
 
-use super::{msi::MsiModel, BandStructureExport, DefaultExport, ModelInfo, Settings};
+use super::{
+    hubbard::HubbardValue, msi::MsiModel, BandStructureExport, DefaultExport, ModelInfo, Settings,
+};
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A unit struct to mark `cell`format.
 pub struct CellModel;
 
@@ -28,6 +29,141 @@ impl CellModel {
             block_name, content, block_name
         )
     }
+    /// `HUBBARD_U` or `HUBBARD_ALPHA` in eV, with a line for each species or ion
+    /// in the order they are first set. Empty without values.
+    fn hubbard_block(block_name: &str, hubbard_values: &[HubbardValue]) -> String {
+        if hubbard_values.is_empty() {
+            return String::new();
+        }
+        let mut sites: Vec<(&str, Option<u32>)> = Vec::new();
+        hubbard_values.iter().for_each(|v| {
+            if !sites.contains(&(v.species(), v.ion())) {
+                sites.push((v.species(), v.ion()));
+            }
+        });
+        let lines: Vec<String> = sites
+            .iter()
+            .map(|(species, ion)| {
+                let values: Vec<String> = hubbard_values
+                    .iter()
+                    .filter(|v| v.species() == *species && v.ion() == *ion)
+                    .map(|v| format!("  {}:{:17.10}", v.orbital(), v.value()))
+                    .collect();
+                let ion_str = ion.map(|ion| format!(" {:>3}", ion)).unwrap_or_default();
+                format!("{:>8}{}{}\n", species, ion_str, values.concat())
+            })
+            .collect();
+        CellModel::write_block((block_name.to_string(), format!("eV\n{}", lines.concat())))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// Block to write the lattice in `.cell`.
+pub enum LatticeBlock {
+    #[default]
+    Cart,
+    Abc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// Block to write the atoms in `.cell`.
+pub enum PositionsBlock {
+    #[default]
+    Frac,
+    Abs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Length unit written as the first line of `LATTICE_*` and `POSITIONS_ABS`.
+pub enum LengthUnit {
+    Ang,
+    Bohr,
+}
+
+impl LengthUnit {
+    /// Factor to convert a length in Å into this unit.
+    fn per_ang(&self) -> f64 {
+        match self {
+            Self::Ang => 1.0,
+            Self::Bohr => 1.0 / BOHR_TO_ANG,
+        }
+    }
+}
+
+impl Display for LengthUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ang => write!(f, "ang"),
+            Self::Bohr => write!(f, "bohr"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Options for exporting `LatticeModel<CellModel>` as `.cell`.
+/// The default writes `LATTICE_CART` and `POSITIONS_FRAC` in Å without a unit line,
+/// with 18 decimals for the lattice and 16 for the positions.
+/// Without a lattice, or with a singular one, the atoms are written in
+/// `POSITIONS_ABS` from `xyz_coords` whatever the blocks chosen, and the lattice
+/// block is left out when there is no lattice.
+pub struct CellExportOptions {
+    lattice_block: LatticeBlock,
+    positions_block: PositionsBlock,
+    length_unit: Option<LengthUnit>,
+    precision: Option<usize>,
+}
+
+impl CellExportOptions {
+    pub fn with_lattice_block(self, lattice_block: LatticeBlock) -> Self {
+        Self {
+            lattice_block,
+            ..self
+        }
+    }
+
+    pub fn with_positions_block(self, positions_block: PositionsBlock) -> Self {
+        Self {
+            positions_block,
+            ..self
+        }
+    }
+    /// Write the unit line, and convert the lengths into `length_unit`.
+    pub fn with_length_unit(self, length_unit: LengthUnit) -> Self {
+        Self {
+            length_unit: Some(length_unit),
+            ..self
+        }
+    }
+    /// Number of decimals for the lattice and the positions.
+    pub fn with_precision(self, precision: usize) -> Self {
+        Self {
+            precision: Some(precision),
+            ..self
+        }
+    }
+
+    pub fn lattice_block(&self) -> LatticeBlock {
+        self.lattice_block
+    }
+
+    pub fn positions_block(&self) -> PositionsBlock {
+        self.positions_block
+    }
+
+    pub fn length_unit(&self) -> Option<LengthUnit> {
+        self.length_unit
+    }
+
+    pub fn precision(&self) -> Option<usize> {
+        self.precision
+    }
+    /// The unit line, if any, and the factor to convert from Å.
+    fn unit_line(&self) -> (String, f64) {
+        match self.length_unit {
+            Some(unit) => (format!("{}\n", unit), unit.per_ang()),
+            None => (String::new(), 1.0),
+        }
+    }
 }
 
 /// Transition from `LatticeModel<MsiFormat>` to `LatticeModel<CellFormat>`
@@ -36,19 +172,7 @@ where
     T: AsRef<LatticeModel<MsiModel>> + AsMut<LatticeModel<MsiModel>>,
 {
     fn from(mut msi_model: T) -> Self {
-        let x_axis: Vector3<f64> = Vector::x();
-        let a_vec = msi_model
-            .as_ref()
-            .lattice_vectors()
-            .unwrap()
-            .vectors()
-            .column(0);
-        let a_to_x_angle = a_vec.angle(&x_axis);
-        if a_to_x_angle != 0.0 {
-            let rot_axis = a_vec.cross(&x_axis).normalize();
-            let rot_quatd: UnitQuaternion<f64> = UnitQuaternion::new(rot_axis * a_to_x_angle);
-            msi_model.as_mut().rotate(&rot_quatd);
-        }
+        msi_model.as_mut().rotate_a_to_x();
         let new_lat_vec = LatticeVectors::new(
             msi_model
                 .as_ref()
@@ -75,16 +199,92 @@ where
             .for_each(|(i, f_xyz)| {
                 *f_xyz = Some(*frac_coords.get(i).unwrap());
             });
-        Self::new(Some(new_lat_vec), cell_atoms, Settings::default())
+        let mut settings = Settings::default();
+        settings.set_hubbard_u(msi_model.as_ref().settings().hubbard_u().to_vec());
+        settings.set_hubbard_alpha(msi_model.as_ref().settings().hubbard_alpha().to_vec());
+        Self::new(Some(new_lat_vec), cell_atoms, settings)
     }
 }
 
 /// Methods only for `LatticeModel<CellFormat>`
 impl LatticeModel<CellModel> {
-    /// Formatted *fractional coordinates*
-    fn positions_str(&self) -> String {
-        let coords = format!("{}", self.atoms());
-        CellModel::write_block(("POSITIONS_FRAC".to_string(), coords))
+    /// `POSITIONS_FRAC`, with the fractional coordinates computed from the cartesian ones
+    /// if missing, or `POSITIONS_ABS` written from `xyz_coords`.
+    /// With `LATTICE_ABC`, the absolute positions follow the lattice re-oriented
+    /// by `castep`, with a along x and b in the xy plane.
+    /// Falls back to `POSITIONS_ABS` as in `LATTICE_CART` when the lattice is
+    /// missing or singular, and the fractional coordinates cannot be computed.
+    fn positions_str(&self, options: &CellExportOptions) -> String {
+        let precision = options.precision().unwrap_or(16);
+        let atoms = self.atoms();
+        let lattice_vectors = self.lattice_vectors();
+        let to_frac = lattice_vectors.and_then(|lattice| lattice.vectors().try_inverse());
+        let frac_coords: Option<Vec<Point3<f64>>> = atoms
+            .fractional_xyz()
+            .iter()
+            .zip(atoms.xyz_coords())
+            .map(|(frac, xyz)| frac.or_else(|| to_frac.map(|to_frac| to_frac * xyz)))
+            .collect();
+        match (options.positions_block(), frac_coords) {
+            (PositionsBlock::Frac, Some(frac_coords)) => {
+                let coords = atoms.positions_lines(&frac_coords, precision);
+                CellModel::write_block(("POSITIONS_FRAC".to_string(), coords))
+            }
+            _ => {
+                let (unit_line, factor) = options.unit_line();
+                let abs_coords: Vec<Point3<f64>> =
+                    match (options.lattice_block(), lattice_vectors, to_frac) {
+                        (LatticeBlock::Abc, Some(lattice_vectors), Some(to_frac)) => {
+                            let (lengths, angles) = lattice_vectors.lattice_params();
+                            let abc_vectors =
+                                LatticeVectors::<CellModel>::from_lattice_params(lengths, angles);
+                            atoms
+                                .xyz_coords()
+                                .iter()
+                                .map(|xyz| abc_vectors.vectors() * (to_frac * xyz))
+                                .collect()
+                        }
+                        _ => atoms.xyz_coords().to_vec(),
+                    };
+                let abs_coords: Vec<Point3<f64>> =
+                    abs_coords.iter().map(|xyz| xyz * factor).collect();
+                let coords = atoms.positions_lines(&abs_coords, precision);
+                CellModel::write_block((
+                    "POSITIONS_ABS".to_string(),
+                    format!("{}{}", unit_line, coords),
+                ))
+            }
+        }
+    }
+    /// The text of `.cell`, with `BS_KPOINTS_LIST` for `BandStructure` calculation.
+    fn cell_text(&self, options: &CellExportOptions, band_structure: bool) -> String {
+        let mut cell_text = vec![
+            self.lattice_vectors()
+                .map(|lattice_vectors| lattice_vectors.block_str(options))
+                .unwrap_or_default(),
+            self.positions_str(options),
+        ];
+        if band_structure {
+            cell_text.push(self.bs_kpoints_list_str());
+        }
+        cell_text.extend([
+            self.kpoints_list_str(),
+            self.misc_options(),
+            self.species_mass(),
+            self.species_pot_str(),
+            self.species_lcao_str(),
+            self.hubbard_u_str(),
+            self.hubbard_alpha_str(),
+        ]);
+        cell_text.concat()
+    }
+    /// Export as `.cell` with the given options.
+    pub fn export_with_options(&self, options: &CellExportOptions) -> String {
+        self.cell_text(options, false)
+    }
+    /// Export as `.cell` for `BandStructure` calculation with the given options.
+    pub fn export_bs_with_options(&self, options: &CellExportOptions) -> String {
+        self.cell_text(options, true)
     }
     /**
     This data block contains a list of k-points at which the Brillouin zone will be sampled during a self consistent calculation to find the electronic ground state, along with the associated weights
@@ -126,17 +326,66 @@ impl LatticeModel<CellModel> {
             .collect();
         CellModel::write_block(("BS_KPOINTS_LIST".to_string(), kpoints_list.concat()))
     }
-    /// No constraints. Future: adapt to settings
+    /**
+    One row for each fixed axis of the atoms.
+    # Format:
+    ```text
+    %BLOCK IONIC_CONSTRAINTS
+        1  Fe     1    1.0000000000    0.0000000000    0.0000000000
+        2  Fe     1    0.0000000000    1.0000000000    0.0000000000
+    %ENDBLOCK IONIC_CONSTRAINTS
+    ```
+    The entries are the index of the constraint, the species, the index of the ion
+    within its species in the order of the positions block, and the cartesian
+    direction along which the ion is fixed. Labelled species, e.g. `Fe:up`, count
+    their ions separately.
+    */
     fn ionic_constraints(&self) -> String {
-        CellModel::write_block(("IONIC_CONSTRAINTS".to_string(), "".to_string()))
+        let atoms = self.atoms();
+        let species = atoms.species();
+        let mut species_counts: HashMap<&str, u32> = HashMap::new();
+        let mut rows: Vec<String> = Vec::new();
+        species
+            .iter()
+            .zip(atoms.fixed_axes())
+            .for_each(|(symbol, fixed_axes)| {
+                let ion_index = species_counts.entry(symbol.as_str()).or_insert(0);
+                *ion_index += 1;
+                fixed_axes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, fixed)| **fixed)
+                    .for_each(|(axis, _)| {
+                        let mut direction = [0.0; 3];
+                        direction[axis] = 1.0;
+                        rows.push(format!(
+                            "{:>6} {:>4}{:>6}{:16.10}{:16.10}{:16.10}\n",
+                            rows.len() + 1,
+                            symbol,
+                            ion_index,
+                            direction[0],
+                            direction[1],
+                            direction[2]
+                        ));
+                    });
+            });
+        CellModel::write_block(("IONIC_CONSTRAINTS".to_string(), rows.concat()))
     }
-    /// Miscellaneous parameters
+    /// Miscellaneous parameters.
+    /// `CELL_CONSTRAINTS` is written only when `FIX_ALL_CELL` is false.
     fn misc_options(&self) -> String {
+        let cell_constraints = match self.settings().cell_constraints() {
+            Some(constraints) if !self.settings().fix_all_cell() => {
+                CellModel::write_block(("CELL_CONSTRAINTS".to_string(), constraints.to_string()))
+            }
+            _ => String::new(),
+        };
         let fix = format!(
-            "FIX_ALL_CELL : {}\n\nFIX_COM : {}\n{}",
+            "FIX_ALL_CELL : {}\n\nFIX_COM : {}\n{}{}",
             self.settings().fix_all_cell(),
             self.settings().fix_com(),
-            self.ionic_constraints()
+            self.ionic_constraints(),
+            cell_constraints
         );
         let [ex, ey, ez] = self.settings().external_efield();
         let external_efield = CellModel::write_block((
@@ -173,12 +422,12 @@ impl LatticeModel<CellModel> {
     ```
     */
     fn species_mass(&self) -> String {
-        let element_list = self.element_set();
-        let mass_strings: Vec<String> = element_list
+        let species_list = self.species_set();
+        let mass_strings: Vec<String> = species_list
             .iter()
-            .map(|elm| -> String {
-                let mass: f64 = ELEMENT_TABLE.get_by_symbol(elm).unwrap().mass();
-                format!("{:>8}{:17.10}\n", elm, mass)
+            .map(|species| -> String {
+                let mass: f64 = self.settings().species_mass(species);
+                format!("{:>8}{:17.10}\n", species, mass)
             })
             .collect();
         CellModel::write_block(("SPECIES_MASS".to_string(), mass_strings.concat()))
@@ -196,12 +445,15 @@ impl LatticeModel<CellModel> {
     ```
     */
     fn species_pot_str(&self) -> String {
-        let element_list = self.element_set();
-        let pot_strings: Vec<String> = element_list
+        let species_list = self.species_set();
+        let pot_strings: Vec<String> = species_list
             .iter()
-            .map(|elm| {
-                let pot_file = ELEMENT_TABLE.get_by_symbol(elm).unwrap().potential();
-                format!("{:>8}  {}\n", elm, pot_file)
+            .map(|species| {
+                format!(
+                    "{:>8}  {}\n",
+                    species,
+                    self.settings().species_potential(species)
+                )
             })
             .collect();
         CellModel::write_block(("SPECIES_POT".to_string(), pot_strings.concat()))
@@ -219,16 +471,37 @@ impl LatticeModel<CellModel> {
     ```
     */
     fn species_lcao_str(&self) -> String {
-        let element_list = self.element_set();
-        let lcao_strings: Vec<String> = element_list
+        let species_list = self.species_set();
+        let lcao_strings: Vec<String> = species_list
             .iter()
-            .map(|elm| {
-                let lcao_state = ELEMENT_TABLE.get_by_symbol(elm).unwrap().lcao();
-                format!("{:>8}{:9}\n", elm, lcao_state)
+            .map(|species| {
+                let lcao_state = self.settings().species_lcao_state(species);
+                format!("{:>8}{:9}\n", species, lcao_state)
             })
             .collect();
         CellModel::write_block(("SPECIES_LCAO_STATES".to_string(), lcao_strings.concat()))
     }
+    /**
+    The U values of DFT+U, with the orbitals of a species, or of an ion of
+    the species, on the same line. Empty if no U is set.
+    # Example:
+    ```text
+    %BLOCK HUBBARD_U
+    eV
+          Fe  d:     5.3000000000
+        Ni:1  d:     6.2000000000  p:     1.0000000000
+          Co   2  d:     3.3200000000
+    %ENDBLOCK HUBBARD_U
+    ```
+    */
+    fn hubbard_u_str(&self) -> String {
+        CellModel::hubbard_block("HUBBARD_U", self.settings().hubbard_u())
+    }
+    /// The alpha values of DFT+U, in the same form as `HUBBARD_U`.
+    /// Empty if no alpha is set.
+    fn hubbard_alpha_str(&self) -> String {
+        CellModel::hubbard_block("HUBBARD_ALPHA", self.settings().hubbard_alpha())
+    }
     /// Build `KptAux` struct
     pub fn build_kptaux(&self) -> KptAux {
         KptAux::new(
@@ -244,41 +517,94 @@ impl LatticeModel<CellModel> {
     }
 }
 
-impl Display for AtomCollection<CellModel> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl AtomCollection<CellModel> {
+    /// Lines of species and coordinates, with `SPIN=` for atoms with a
+    /// nonzero magnetic moment. The coordinates are separated by a space, so
+    /// that they do not run together when wider than the field.
+    fn positions_lines(&self, coords: &[Point3<f64>], precision: usize) -> String {
+        let width = precision + 4;
         let all_positions_str: Vec<String> = self
-            .element_symbols()
+            .species()
             .iter()
-            .zip(self.fractional_xyz().iter())
-            .map(|(symbol, frac_xyz)| -> String {
-                let spin = ELEMENT_TABLE.get_by_symbol(symbol).unwrap().spin();
-                let spin_str = if spin > 0 {
+            .zip(coords)
+            .zip(self.magnetic_moments())
+            .map(|((symbol, xyz), spin)| -> String {
+                let spin_str = if *spin != 0.0 {
                     format!(" SPIN={:14.10}", spin)
                 } else {
                     "".into()
                 };
-                let frac_xyz = frac_xyz.unwrap();
                 format!(
-                    "{:>3}{:20.16}{:20.16}{:20.16}{spin_str}",
-                    symbol, frac_xyz.x, frac_xyz.y, frac_xyz.z
+                    "{:>3} {:width$.precision$} {:width$.precision$} {:width$.precision$}{spin_str}\n",
+                    symbol, xyz.x, xyz.y, xyz.z
                 )
             })
             .collect();
-        let joined_positions_str = all_positions_str.join("\n");
-        write!(f, "{}\n", joined_positions_str)
+        all_positions_str.concat()
     }
 }
 
-impl Display for LatticeVectors<CellModel> {
+/// The lines of `POSITIONS_FRAC`.
+/// # Panics
+/// Panics if any atom has no fractional coordinates.
+impl Display for AtomCollection<CellModel> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let formatted_vector: Vec<String> = self
-            .vectors()
-            .column_iter()
-            .map(|col| format!("{:24.18}{:24.18}{:24.18}\n", col.x, col.y, col.z))
+        let frac_coords: Vec<Point3<f64>> = self
+            .fractional_xyz()
+            .iter()
+            .map(|frac_xyz| frac_xyz.unwrap())
             .collect();
-        let formatted_vector = formatted_vector.concat();
-        let output = CellModel::write_block(("LATTICE_CART".to_string(), formatted_vector));
-        write!(f, "{}", &output)
+        write!(f, "{}", self.positions_lines(&frac_coords, 16))
+    }
+}
+
+impl LatticeVectors<CellModel> {
+    /// `LATTICE_CART` or `LATTICE_ABC` block with the given options.
+    fn block_str(&self, options: &CellExportOptions) -> String {
+        let precision = options.precision().unwrap_or(18);
+        let width = precision + 6;
+        let (unit_line, factor) = options.unit_line();
+        match options.lattice_block() {
+            LatticeBlock::Cart => {
+                let formatted_vector: Vec<String> = self
+                    .vectors()
+                    .column_iter()
+                    .map(|col| {
+                        let col = col * factor;
+                        format!(
+                            "{:width$.precision$}{:width$.precision$}{:width$.precision$}\n",
+                            col.x, col.y, col.z
+                        )
+                    })
+                    .collect();
+                CellModel::write_block((
+                    "LATTICE_CART".to_string(),
+                    format!("{}{}", unit_line, formatted_vector.concat()),
+                ))
+            }
+            LatticeBlock::Abc => {
+                let ([a, b, c], [alpha, beta, gamma]) = self.lattice_params();
+                CellModel::write_block((
+                    "LATTICE_ABC".to_string(),
+                    format!(
+                        "{}{:width$.precision$}{:width$.precision$}{:width$.precision$}\n{:width$.precision$}{:width$.precision$}{:width$.precision$}\n",
+                        unit_line,
+                        a * factor,
+                        b * factor,
+                        c * factor,
+                        alpha,
+                        beta,
+                        gamma
+                    ),
+                ))
+            }
+        }
+    }
+}
+
+impl Display for LatticeVectors<CellModel> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.block_str(&CellExportOptions::default()))
     }
 }
 
@@ -287,17 +613,8 @@ where
     T: AsRef<LatticeModel<CellModel>>,
 {
     fn export(&self) -> String {
-        let lattice_vector_string = format!("{}", self.as_ref().lattice_vectors().unwrap());
-        let cell_text = vec![
-            lattice_vector_string,
-            self.as_ref().positions_str(),
-            self.as_ref().kpoints_list_str(),
-            self.as_ref().misc_options(),
-            self.as_ref().species_mass(),
-            self.as_ref().species_pot_str(),
-            self.as_ref().species_lcao_str(),
-        ];
-        cell_text.concat()
+        self.as_ref()
+            .export_with_options(&CellExportOptions::default())
     }
 }
 
@@ -306,17 +623,282 @@ where
     T: AsRef<LatticeModel<CellModel>>,
 {
     fn export(&self) -> String {
-        let lattice_vector_string = format!("{}", self.as_ref().lattice_vectors().unwrap());
-        let cell_text = vec![
-            lattice_vector_string,
-            self.as_ref().positions_str(),
-            self.as_ref().bs_kpoints_list_str(),
-            self.as_ref().kpoints_list_str(),
-            self.as_ref().misc_options(),
-            self.as_ref().species_mass(),
-            self.as_ref().species_pot_str(),
-            self.as_ref().species_lcao_str(),
-        ];
-        cell_text.concat()
+        self.as_ref()
+            .export_bs_with_options(&CellExportOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{
+        atom::visitor::VisitCollection,
+        error::CellConstraintsError,
+        lattice::LatticeVectors,
+        model_type::{
+            cell_constraints::CellConstraints, hubbard::HubbardOrbital,
+            pseudopotential::Pseudopotential, DefaultExport,
+        },
+        units::BOHR_TO_ANG,
+        CellModel, LatticeModel, MsiModel,
+    };
+
+    use super::{CellExportOptions, LatticeBlock, LengthUnit, PositionsBlock};
+
+    /// A hexagonal cell with a along x and b in the xy plane, as `LATTICE_ABC` is read.
+    const HEXAGONAL: &str = r#"%BLOCK LATTICE_CART
+   4.0   0.0                  0.0
+  -2.0   3.4641016151377544   0.0
+   0.0   0.0                  6.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+  Fe   0.0                  0.0                  0.0
+   O   0.3333333333333333   0.6666666666666667   0.25
+%ENDBLOCK POSITIONS_FRAC
+"#;
+
+    /// A 100 Å box with atoms far outside of it.
+    const LARGE_BOX: &str = r#"%BLOCK LATTICE_CART
+  100.0    0.0    0.0
+    0.0  100.0    0.0
+    0.0    0.0  100.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_ABS
+  C  -1234.5    150.25   -10.0
+  O    999.75  -250.0    100.0
+%ENDBLOCK POSITIONS_ABS
+"#;
+
+    /// Iron oxide in a cubic cell, for the blocks written from the settings.
+    const FE_O: &str = r#"%BLOCK LATTICE_CART
+   4.0   0.0   0.0
+   0.0   4.0   0.0
+   0.0   0.0   4.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+  Fe   0.0   0.0   0.0
+   O   0.5   0.5   0.5
+%ENDBLOCK POSITIONS_FRAC
+"#;
+
+    fn assert_same_positions(a: &LatticeModel<CellModel>, b: &LatticeModel<CellModel>) {
+        a.atoms()
+            .xyz_coords()
+            .iter()
+            .zip(b.atoms().xyz_coords())
+            .for_each(|(a, b)| assert!((a - b).norm() < 1e-8));
+    }
+
+    #[test]
+    fn export_with_options() {
+        let mut model = LatticeModel::<CellModel>::from_str(HEXAGONAL).unwrap();
+        let default_exported = model.export_with_options(&CellExportOptions::default());
+        assert!(default_exported.starts_with(&format!("%BLOCK LATTICE_CART\n{:24.18}", 4.0)));
+        assert!(default_exported.contains(&format!(
+            "%BLOCK POSITIONS_FRAC\n Fe {:20.16} {:20.16} {:20.16}\n",
+            0.0, 0.0, 0.0
+        )));
+        model
+            .atoms_mut()
+            .fractional_xyz_mut()
+            .iter_mut()
+            .for_each(|frac| *frac = None);
+        let options = CellExportOptions::default()
+            .with_lattice_block(LatticeBlock::Abc)
+            .with_positions_block(PositionsBlock::Abs)
+            .with_length_unit(LengthUnit::Bohr)
+            .with_precision(10);
+        let exported = model.export_with_options(&options);
+        assert!(exported.contains(&format!(
+            "%BLOCK LATTICE_ABC\nbohr\n{:16.10}{:16.10}{:16.10}\n",
+            4.0 / BOHR_TO_ANG,
+            4.0 / BOHR_TO_ANG,
+            6.0 / BOHR_TO_ANG
+        )));
+        assert!(exported.contains(&format!(
+            "%BLOCK POSITIONS_ABS\nbohr\n Fe {:14.10} {:14.10} {:14.10}\n",
+            0.0, 0.0, 0.0
+        )));
+        let parsed_back = LatticeModel::<CellModel>::from_str(&exported).unwrap();
+        assert_same_positions(&model, &parsed_back);
+        let frac_exported = model.export_with_options(&CellExportOptions::default());
+        assert!(frac_exported.contains("%BLOCK POSITIONS_FRAC\n"));
+    }
+
+    #[test]
+    fn species_pot_str() {
+        let mut model = LatticeModel::<CellModel>::from_str(FE_O).unwrap();
+        assert!(model.export().contains("      Fe  Fe_00PBE.uspcc\n"));
+        model.settings_mut().set_species_potential(
+            "Fe",
+            Pseudopotential::Otfg("3|2.1|10|12|13|30U:40:31:32(qc=5.5)".into()),
+        );
+        model.settings_mut().set_species_potential(
+            "O",
+            Pseudopotential::File("/opt/potentials/O_custom.usp".into()),
+        );
+        assert!(model.export().contains(
+            "%BLOCK SPECIES_POT\n       O  O_custom.usp\n      Fe  3|2.1|10|12|13|30U:40:31:32(qc=5.5)\n"
+        ));
+    }
+
+    #[test]
+    fn cell_constraints() {
+        let mut model = LatticeModel::<CellModel>::from_str(FE_O).unwrap();
+        assert!(model.export().contains("FIX_ALL_CELL : true\n"));
+        let slab = CellConstraints::new([1, 1, 0], [0, 0, 0]).unwrap();
+        let settings = model.settings_mut();
+        assert_eq!(
+            settings.set_cell_constraints(Some(slab)),
+            Err(CellConstraintsError::ConflictWithFixAllCell)
+        );
+        settings.set_fix_all_cell(false).unwrap();
+        settings.set_cell_constraints(Some(slab)).unwrap();
+        let exported = model.export();
+        assert!(exported.contains("FIX_ALL_CELL : false\n"));
+        assert!(exported.contains(
+            "%BLOCK CELL_CONSTRAINTS\n   1   1   0\n   0   0   0\n%ENDBLOCK CELL_CONSTRAINTS\n"
+        ));
+        let settings = model.settings_mut();
+        assert_eq!(
+            settings.set_fix_all_cell(true),
+            Err(CellConstraintsError::ConflictWithFixAllCell)
+        );
+        assert!(!settings.fix_all_cell());
+        settings.set_cell_constraints(None).unwrap();
+        settings.set_fix_all_cell(true).unwrap();
+        assert!(!model.export().contains("CELL_CONSTRAINTS"));
+        assert_eq!(
+            CellConstraints::new([1, 1, 0], [0, 0, 1]),
+            Err(CellConstraintsError::TiedLengthAndAngle(1))
+        );
+    }
+
+    #[test]
+    fn labelled_species() {
+        let mut model = LatticeModel::<CellModel>::from_str(FE_O).unwrap();
+        model
+            .atoms_mut()
+            .update_species_label_at(0, Some("up"))
+            .unwrap();
+        model
+            .atoms_mut()
+            .update_fixed_axes_at(0, [true; 3])
+            .unwrap();
+        model.settings_mut().set_species_mass("O", 17.999);
+        model.settings_mut().set_species_lcao_state("Fe:up", 4);
+        let exported = model.export();
+        assert!(exported.contains(&format!(
+            "\nFe:up {:20.16} {:20.16} {:20.16}\n",
+            0.0, 0.0, 0.0
+        )));
+        assert!(exported.contains("       O    17.9990000000\n"));
+        assert!(exported.contains("   Fe:up        4\n"));
+        assert!(exported.contains("   Fe:up  Fe_00PBE.uspcc\n"));
+        assert!(exported.contains(&format!(
+            "{:>6} {:>4}{:>6}{:16.10}{:16.10}{:16.10}\n",
+            3, "Fe:up", 1, 0.0, 0.0, 1.0
+        )));
+        let parsed_back = LatticeModel::<CellModel>::from_str(&exported).unwrap();
+        assert_eq!(parsed_back.atoms().species(), model.atoms().species());
+        assert_eq!(parsed_back.settings().species_mass("O"), 17.999);
+        assert_eq!(parsed_back.atoms().fixed_axes(), model.atoms().fixed_axes());
+    }
+
+    #[test]
+    fn magnetic_moments() {
+        let mut model = LatticeModel::<CellModel>::from_str(FE_O).unwrap();
+        assert!(!model.export().contains("SPIN="));
+        model
+            .atoms_mut()
+            .update_magnetic_moment_at(0, -3.0)
+            .unwrap();
+        assert_eq!(model.spin_total(), -3.0);
+        let exported = model.export();
+        assert!(exported.contains(&format!(
+            " Fe {:20.16} {:20.16} {:20.16} SPIN={:14.10}\n",
+            0.0, 0.0, 0.0, -3.0
+        )));
+        assert_eq!(exported.matches("SPIN=").count(), 1);
+        let parsed_back = LatticeModel::<CellModel>::from_str(&exported).unwrap();
+        assert_eq!(parsed_back.atoms().magnetic_moments(), &[-3.0, 0.0]);
+        let msi_model: LatticeModel<MsiModel> = parsed_back.into();
+        assert_eq!(msi_model.atoms().magnetic_moments(), &[-3.0, 0.0]);
+    }
+
+    #[test]
+    fn hubbard_blocks() {
+        let mut model = LatticeModel::<CellModel>::from_str(FE_O).unwrap();
+        assert!(!model.export().contains("HUBBARD"));
+        model.set_default_hubbard_u();
+        let settings = model.settings_mut();
+        settings.set_species_hubbard_u("O", HubbardOrbital::P, 1.5);
+        settings.set_ion_hubbard_u("Fe", 1, HubbardOrbital::D, 4.0);
+        settings.set_species_hubbard_alpha("Fe", HubbardOrbital::D, 0.1);
+        let exported = model.export();
+        assert!(exported.contains(
+            "%BLOCK HUBBARD_U\neV\n      Fe  d:     5.3000000000\n       O  p:     1.5000000000\n      Fe   1  d:     4.0000000000\n%ENDBLOCK HUBBARD_U\n"
+        ));
+        assert!(exported.contains(
+            "%BLOCK HUBBARD_ALPHA\neV\n      Fe  d:     0.1000000000\n%ENDBLOCK HUBBARD_ALPHA\n"
+        ));
+        let parsed_back = LatticeModel::<CellModel>::from_str(&exported).unwrap();
+        assert_eq!(
+            parsed_back.settings().hubbard_u(),
+            model.settings().hubbard_u()
+        );
+        let msi_model: LatticeModel<MsiModel> = parsed_back.into();
+        let cell_model: LatticeModel<CellModel> = msi_model.into();
+        assert_eq!(
+            cell_model.settings().hubbard_u(),
+            model.settings().hubbard_u()
+        );
+        assert_eq!(
+            cell_model.settings().hubbard_alpha(),
+            model.settings().hubbard_alpha()
+        );
+    }
+
+    #[test]
+    fn large_coordinates_round_trip() {
+        let model = LatticeModel::<CellModel>::from_str(LARGE_BOX).unwrap();
+        [PositionsBlock::Frac, PositionsBlock::Abs]
+            .into_iter()
+            .for_each(|positions_block| {
+                let options = CellExportOptions::default().with_positions_block(positions_block);
+                let exported = model.export_with_options(&options);
+                let parsed_back = LatticeModel::<CellModel>::from_str(&exported).unwrap();
+                assert_same_positions(&model, &parsed_back);
+            });
+    }
+
+    #[test]
+    fn export_without_inverse_lattice() {
+        let model = LatticeModel::<CellModel>::from_str(LARGE_BOX).unwrap();
+        let mut no_lattice = model.clone();
+        *no_lattice.lattice_vectors_mut() = None;
+        no_lattice
+            .atoms_mut()
+            .fractional_xyz_mut()
+            .iter_mut()
+            .for_each(|frac| *frac = None);
+        let exported = DefaultExport::<CellModel>::export(&no_lattice);
+        assert!(!exported.contains("LATTICE_CART"));
+        assert!(exported.contains("%BLOCK POSITIONS_ABS\n"));
+        let mut singular = no_lattice.clone();
+        *singular.lattice_vectors_mut() = model.lattice_vectors().map(|lattice_vectors| {
+            let mut vectors = *lattice_vectors.vectors();
+            vectors.set_column(2, &vectors.column(0).clone_owned());
+            LatticeVectors::new(vectors)
+        });
+        let options = CellExportOptions::default().with_lattice_block(LatticeBlock::Abc);
+        let exported = singular.export_with_options(&options);
+        assert!(exported.contains("%BLOCK LATTICE_ABC\n"));
+        assert!(exported.contains("%BLOCK POSITIONS_ABS\n"));
+        assert!(exported.contains(&format!(" {:20.16}", -1234.5)));
     }
 }
@@ -0,0 +1,64 @@
+use std::fmt::Display;
+
+use crate::error::CellConstraintsError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The `CELL_CONSTRAINTS` of a variable-cell geometry optimization.
+/// Each of the lengths a, b, c and the angles alpha, beta, gamma has an index:
+/// 0 fixes it, and the same nonzero index ties the values together.
+/// E.g. to relax a and b of a slab but fix c, with a = b:
+/// ```text
+///    1   1   0
+///    0   0   0
+/// ```
+pub struct CellConstraints {
+    lengths: [u8; 3],
+    angles: [u8; 3],
+}
+
+impl CellConstraints {
+    /// # Errors
+    /// This function will return an error if a length and an angle share a nonzero index.
+    pub fn new(lengths: [u8; 3], angles: [u8; 3]) -> Result<Self, CellConstraintsError> {
+        match lengths
+            .iter()
+            .find(|index| **index != 0 && angles.contains(index))
+        {
+            Some(index) => Err(CellConstraintsError::TiedLengthAndAngle(*index)),
+            None => Ok(Self { lengths, angles }),
+        }
+    }
+    /// All lengths and angles are free to change independently.
+    pub fn relax_all() -> Self {
+        Self {
+            lengths: [1, 2, 3],
+            angles: [4, 5, 6],
+        }
+    }
+
+    pub fn lengths(&self) -> [u8; 3] {
+        self.lengths
+    }
+
+    pub fn angles(&self) -> [u8; 3] {
+        self.angles
+    }
+    /// Whether all lengths and angles are fixed, same as `FIX_ALL_CELL : true`.
+    pub fn is_all_fixed(&self) -> bool {
+        self.lengths
+            .iter()
+            .chain(self.angles.iter())
+            .all(|i| *i == 0)
+    }
+}
+
+/// The content of `CELL_CONSTRAINTS`.
+impl Display for CellConstraints {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b, c] = self.lengths;
+        let [alpha, beta, gamma] = self.angles;
+        writeln!(f, "{:>4}{:>4}{:>4}", a, b, c)?;
+        writeln!(f, "{:>4}{:>4}{:>4}", alpha, beta, gamma)
+    }
+}
@@ -0,0 +1,165 @@
+use na::Point3;
+
+use crate::{
+    lattice::{symmetry::SymmetryOperation, LatticeModel},
+    CellModel,
+};
+
+use super::CifExport;
+
+/// The space groups meaning no symmetry, including the `msi` default `"1 1"`.
+fn is_p1(space_group: &str) -> bool {
+    matches!(
+        space_group.replace(' ', "").to_uppercase().as_str(),
+        "" | "11" | "P1"
+    )
+}
+
+/// Methods for writing `LatticeModel<CellModel>` as `cif`
+impl LatticeModel<CellModel> {
+    /// Cell parameters, `None` without lattice vectors.
+    fn cif_cell_str(&self) -> Option<String> {
+        let ([a, b, c], [alpha, beta, gamma]) = self.lattice_vectors()?.lattice_params();
+        Some(format!(
+            r#"_cell_length_a                    {:.6}
+_cell_length_b                    {:.6}
+_cell_length_c                    {:.6}
+_cell_angle_alpha                 {:.6}
+_cell_angle_beta                  {:.6}
+_cell_angle_gamma                 {:.6}
+"#,
+            a, b, c, alpha, beta, gamma
+        ))
+    }
+    /// Fractional coordinates of all atoms, computed from the cartesian ones if missing.
+    /// `None` without invertible lattice vectors.
+    fn cif_fractional_xyz(&self) -> Option<Vec<Point3<f64>>> {
+        let to_frac = self.lattice_vectors()?.vectors().try_inverse()?;
+        let frac_xyz = self
+            .atoms()
+            .fractional_xyz()
+            .iter()
+            .zip(self.atoms().xyz_coords())
+            .map(|(frac, xyz)| frac.unwrap_or_else(|| to_frac * xyz))
+            .collect();
+        Some(frac_xyz)
+    }
+    /// Symmetry operations and the atom sites at the given indices.
+    /// Labels are numbered per element, e.g. `Fe1`, `Fe2`, `O1`.
+    fn cif_body(
+        &self,
+        space_group: &str,
+        operations: &[SymmetryOperation],
+        site_indices: &[usize],
+        frac_xyz: &[Point3<f64>],
+    ) -> Option<String> {
+        let symmetry_lines: Vec<String> = operations
+            .iter()
+            .map(|op| format!("  '{}'\n", op))
+            .collect();
+        let mut element_counts: Vec<(&str, u32)> = Vec::new();
+        let site_lines: Vec<String> = site_indices
+            .iter()
+            .map(|&i| {
+                let symbol = self.atoms().element_symbols()[i].as_str();
+                let count = match element_counts.iter_mut().find(|(elm, _)| *elm == symbol) {
+                    Some((_, count)) => {
+                        *count += 1;
+                        *count
+                    }
+                    None => {
+                        element_counts.push((symbol, 1));
+                        1
+                    }
+                };
+                let xyz = frac_xyz[i];
+                format!(
+                    "{:<6}{:>3}{:14.8}{:14.8}{:14.8}  1.0\n",
+                    format!("{}{}", symbol, count),
+                    symbol,
+                    xyz.x,
+                    xyz.y,
+                    xyz.z
+                )
+            })
+            .collect();
+        let cell_str = self.cif_cell_str()?;
+        Some(format!(
+            r#"data_model
+_symmetry_space_group_name_H-M    '{}'
+{}
+loop_
+_symmetry_equiv_pos_as_xyz
+{}
+loop_
+_atom_site_label
+_atom_site_type_symbol
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+_atom_site_occupancy
+{}"#,
+            space_group,
+            cell_str,
+            symmetry_lines.concat(),
+            site_lines.concat()
+        ))
+    }
+}
+
+impl<T> CifExport<CellModel> for T
+where
+    T: AsRef<LatticeModel<CellModel>>,
+{
+    fn export_cif(&self) -> Option<String> {
+        let model = self.as_ref();
+        let frac_xyz = model.cif_fractional_xyz()?;
+        let site_indices: Vec<usize> = (0..model.atoms().size()).collect();
+        model.cif_body(
+            "P 1",
+            &[SymmetryOperation::identity()],
+            &site_indices,
+            &frac_xyz,
+        )
+    }
+
+    fn export_symmetrized_cif(&self, tolerance: f64) -> Option<String> {
+        let model = self.as_ref();
+        let space_group = model.settings().space_group();
+        let operations = model.settings().symmetry_operations();
+        if is_p1(space_group) || operations.is_empty() {
+            return None;
+        }
+        let frac_xyz = model.cif_fractional_xyz()?;
+        let to_cart = model.lattice_vectors()?.vectors();
+        let symbols = model.atoms().element_symbols();
+        let is_same_site = |i: usize, image: &Point3<f64>, j: usize| {
+            // Minimum image convention.
+            let diff = (image - frac_xyz[j]).map(|d| d - d.round());
+            symbols[i] == symbols[j] && (to_cart * diff).norm() < tolerance
+        };
+        // Keep the atoms which are not images of an atom kept before.
+        let mut site_indices: Vec<usize> = Vec::new();
+        (0..frac_xyz.len()).for_each(|i| {
+            let is_image = site_indices.iter().any(|&k| {
+                operations
+                    .iter()
+                    .any(|op| is_same_site(k, &op.apply(&frac_xyz[k]), i))
+            });
+            if !is_image {
+                site_indices.push(i);
+            }
+        });
+        // Every image of the asymmetric unit must be an atom in the model.
+        let is_symmetric = site_indices.iter().all(|&k| {
+            operations.iter().all(|op| {
+                let image = op.apply(&frac_xyz[k]);
+                (0..frac_xyz.len()).any(|j| is_same_site(k, &image, j))
+            })
+        });
+        if !is_symmetric {
+            return None;
+        }
+        model.cif_body(space_group, operations, &site_indices, &frac_xyz)
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt::Display;
+
+use na::{UnitQuaternion, Vector, Vector3};
+
+use crate::{
+    atom::AtomCollection,
+    lattice::{LatticeModel, LatticeVectors},
+    Transformation,
+};
+
+use super::{cell::CellModel, msi::MsiModel, DefaultExport, ModelInfo, Settings};
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A unit struct to mark extended `xyz` format.
+pub struct ExtXyzModel;
+
+impl ModelInfo for ExtXyzModel {}
+
+/// Transition from `LatticeModel<CellModel>` to `LatticeModel<ExtXyzModel>`
+/// The cartesian coordinates are kept as they are.
+impl From<LatticeModel<CellModel>> for LatticeModel<ExtXyzModel> {
+    fn from(cell_model: LatticeModel<CellModel>) -> Self {
+        let lattice_vectors = cell_model
+            .lattice_vectors()
+            .map(|lat_vec| LatticeVectors::new(lat_vec.vectors().to_owned()));
+        Self::new(
+            lattice_vectors,
+            cell_model.atoms().to_format(),
+            Settings::default(),
+        )
+    }
+}
+
+/// Transition from `LatticeModel<ExtXyzModel>` to `LatticeModel<CellModel>`
+/// The fractional coordinates are computed from the lattice vectors, if present.
+impl From<LatticeModel<ExtXyzModel>> for LatticeModel<CellModel> {
+    fn from(xyz_model: LatticeModel<ExtXyzModel>) -> Self {
+        let lattice_vectors: Option<LatticeVectors<CellModel>> = xyz_model
+            .lattice_vectors()
+            .map(|lat_vec| LatticeVectors::new(lat_vec.vectors().to_owned()));
+        let mut cell_atoms: AtomCollection<CellModel> = xyz_model.atoms().to_format();
+        if let Some(to_frac) = lattice_vectors
+            .as_ref()
+            .and_then(|lat_vec| lat_vec.vectors().try_inverse())
+        {
+            let frac_coords: Vec<_> = cell_atoms
+                .xyz_coords()
+                .iter()
+                .map(|xyz| Some(to_frac * xyz))
+                .collect();
+            cell_atoms
+                .fractional_xyz_mut()
+                .iter_mut()
+                .zip(frac_coords)
+                .for_each(|(f_xyz, frac)| *f_xyz = frac);
+        }
+        Self::new(lattice_vectors, cell_atoms, Settings::default())
+    }
+}
+
+/// Transition from `LatticeModel<MsiModel>` to `LatticeModel<ExtXyzModel>`
+impl From<LatticeModel<MsiModel>> for LatticeModel<ExtXyzModel> {
+    fn from(msi_model: LatticeModel<MsiModel>) -> Self {
+        let lattice_vectors = msi_model
+            .lattice_vectors()
+            .map(|lat_vec| LatticeVectors::new(lat_vec.vectors().to_owned()));
+        Self::new(
+            lattice_vectors,
+            msi_model.atoms().to_format(),
+            Settings::default(),
+        )
+    }
+}
+
+/// Transition from `LatticeModel<ExtXyzModel>` to `LatticeModel<MsiModel>`
+/// Like the conversion from `LatticeModel<CellModel>`, the model is rotated
+/// to have vector b along the y axis.
+impl From<LatticeModel<ExtXyzModel>> for LatticeModel<MsiModel> {
+    fn from(xyz_model: LatticeModel<ExtXyzModel>) -> Self {
+        let lattice_vectors = xyz_model
+            .lattice_vectors()
+            .map(|lat_vec| LatticeVectors::new(lat_vec.vectors().to_owned()));
+        let mut msi_model = Self::new(
+            lattice_vectors,
+            xyz_model.atoms().to_format(),
+            Settings::default(),
+        );
+        if let Some(lat_vec) = xyz_model.lattice_vectors() {
+            let y_axis: Vector3<f64> = Vector::y();
+            let b_vec = lat_vec.vectors().column(1);
+            let b_to_y_angle = b_vec.angle(&y_axis);
+            if b_to_y_angle != 0.0 {
+                let rot_axis = b_vec.cross(&y_axis).normalize();
+                let rot_quatd: UnitQuaternion<f64> = UnitQuaternion::new(rot_axis * b_to_y_angle);
+                msi_model.rotate(&rot_quatd);
+            }
+        }
+        msi_model
+    }
+}
+
+/// The `Lattice="..."` entry in the comment line, vectors a, b and c in order.
+impl Display for LatticeVectors<ExtXyzModel> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let components: Vec<String> = self
+            .vectors()
+            .column_iter()
+            .flat_map(|col| [col.x, col.y, col.z])
+            .map(|v| format!("{:.10}", v))
+            .collect();
+        write!(f, "Lattice=\"{}\"", components.join(" "))
+    }
+}
+
+impl Display for AtomCollection<ExtXyzModel> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.element_symbols()
+            .iter()
+            .zip(self.xyz_coords().iter())
+            .try_for_each(|(symbol, xyz)| {
+                writeln!(
+                    f,
+                    "{:<3}{:20.10}{:20.10}{:20.10}",
+                    symbol, xyz.x, xyz.y, xyz.z
+                )
+            })
+    }
+}
+
+impl<T> DefaultExport<ExtXyzModel> for T
+where
+    T: AsRef<LatticeModel<ExtXyzModel>>,
+{
+    fn export(&self) -> String {
+        let model = self.as_ref();
+        let comment_line = match model.lattice_vectors() {
+            Some(lattice_vectors) => format!(
+                "{} Properties=species:S:1:pos:R:3 pbc=\"T T T\"",
+                lattice_vectors
+            ),
+            None => "Properties=species:S:1:pos:R:3 pbc=\"F F F\"".to_string(),
+        };
+        format!(
+            "{}\n{}\n{}",
+            model.atoms().size(),
+            comment_line,
+            model.atoms()
+        )
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt::Display;
+
+use crate::{
+    atom::{species_element, visitor::VisitCollection},
+    lattice::LatticeModel,
+};
+
+use super::ModelInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The angular momentum channel the Hubbard U or alpha acts on.
+pub enum HubbardOrbital {
+    P,
+    D,
+    F,
+}
+
+impl HubbardOrbital {
+    /// The orbital by its letter in `HUBBARD_U` or `HUBBARD_ALPHA`, case-insensitive.
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter.to_ascii_lowercase() {
+            'p' => Some(Self::P),
+            'd' => Some(Self::D),
+            'f' => Some(Self::F),
+            _ => None,
+        }
+    }
+}
+
+impl Display for HubbardOrbital {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::P => write!(f, "p"),
+            Self::D => write!(f, "d"),
+            Self::F => write!(f, "f"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A U or alpha value of DFT+U on an orbital of a species, in eV.
+/// The species may be an element symbol or a labelled species, e.g. `Fe:1`.
+/// With `ion`, the value only acts on that ion of the species, counted from 1
+/// in the order of the atoms of the species.
+pub struct HubbardValue {
+    species: String,
+    ion: Option<u32>,
+    orbital: HubbardOrbital,
+    value: f64,
+}
+
+impl HubbardValue {
+    /// A value on all the ions of the species.
+    pub fn new(species: &str, orbital: HubbardOrbital, value: f64) -> Self {
+        Self {
+            species: species.into(),
+            ion: None,
+            orbital,
+            value,
+        }
+    }
+    /// A value on a single ion of the species.
+    pub fn new_on_ion(species: &str, ion: u32, orbital: HubbardOrbital, value: f64) -> Self {
+        Self {
+            ion: Some(ion),
+            ..Self::new(species, orbital, value)
+        }
+    }
+
+    pub fn species(&self) -> &str {
+        self.species.as_ref()
+    }
+
+    pub fn ion(&self) -> Option<u32> {
+        self.ion
+    }
+
+    pub fn orbital(&self) -> HubbardOrbital {
+        self.orbital
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn set_value(&mut self, value: f64) {
+        self.value = value;
+    }
+}
+
+/// The value on the orbital of the species, or of one ion of it, `None` if not set.
+pub(crate) fn find_hubbard_value(
+    values: &[HubbardValue],
+    species: &str,
+    ion: Option<u32>,
+    orbital: HubbardOrbital,
+) -> Option<f64> {
+    values
+        .iter()
+        .find(|v| v.species() == species && v.ion() == ion && v.orbital() == orbital)
+        .map(|v| v.value())
+}
+
+/// Push the value, or replace the one on the same species, ion and orbital.
+pub(crate) fn insert_hubbard_value(values: &mut Vec<HubbardValue>, new_value: HubbardValue) {
+    match values.iter_mut().find(|v| {
+        v.species() == new_value.species()
+            && v.ion() == new_value.ion()
+            && v.orbital() == new_value.orbital()
+    }) {
+        Some(v) => v.set_value(new_value.value()),
+        None => values.push(new_value),
+    }
+}
+
+/// U values (eV) on the 3d orbitals of common transition metals, as fitted to
+/// oxide formation energies by the Materials Project for GGA+U.
+/// `None` for elements without a tabulated value.
+pub fn default_3d_u(symbol: &str) -> Option<f64> {
+    match symbol {
+        "V" => Some(3.25),
+        "Cr" => Some(3.7),
+        "Mn" => Some(3.9),
+        "Fe" => Some(5.3),
+        "Co" => Some(3.32),
+        "Ni" => Some(6.2),
+        _ => None,
+    }
+}
+
+impl<T: ModelInfo> LatticeModel<T> {
+    /// Set the U on the d orbitals of the species of 3d metals in the model from
+    /// `default_3d_u`. Values already set in `Settings<T>` are kept.
+    pub fn set_default_hubbard_u(&mut self) {
+        let species_set = self.species_set();
+        let settings = self.settings_mut();
+        species_set.iter().for_each(|species| {
+            if let Some(value) = default_3d_u(species_element(species)) {
+                if settings
+                    .species_hubbard_u(species, HubbardOrbital::D)
+                    .is_none()
+                {
+                    settings.set_species_hubbard_u(species, HubbardOrbital::D, value);
+                }
+            }
+        });
+    }
+}
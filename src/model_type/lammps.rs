@@ -0,0 +1,252 @@
+use std::fmt::Display;
+
+use cpt::{data::ELEMENT_TABLE, element::LookupElement};
+use na::{Point3, UnitQuaternion, Vector3};
+
+use crate::{atom::visitor::VisitCollection, lattice::LatticeModel, Transformation};
+
+use super::{LammpsExport, ModelInfo};
+
+/// Space left around the atoms of a model without lattice vectors, in Å.
+const NON_PERIODIC_PADDING: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy)]
+/// The `atom_style` of the `Atoms` section in a LAMMPS `data` file.
+pub enum LammpsAtomStyle<'a> {
+    /// `atom-ID atom-type x y z`
+    Atomic,
+    /// `atom-ID atom-type q x y z`, with the charges given in the order of atoms.
+    /// Atoms beyond the given charges have zero charge.
+    Charge(&'a [f64]),
+}
+
+impl Display for LammpsAtomStyle<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Atomic => write!(f, "atomic"),
+            Self::Charge(_) => write!(f, "charge"),
+        }
+    }
+}
+
+impl<T: ModelInfo> LatticeModel<T> {
+    /// Rotate into the restricted triclinic convention of LAMMPS:
+    /// a along the x axis, and b in the xy plane with a positive y component.
+    fn rotate_to_lammps_frame(&mut self) {
+        self.rotate_a_to_x();
+        if let Some(lattice_vectors) = self.lattice_vectors() {
+            let b_vec = lattice_vectors.vectors().column(1);
+            let b_to_xy_angle = b_vec.z.atan2(b_vec.y);
+            if b_to_xy_angle != 0.0 {
+                let rot_quatd = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), -b_to_xy_angle);
+                self.rotate(&rot_quatd);
+            }
+        }
+    }
+    /// The box lines and the positions of atoms inside the box.
+    /// Without lattice vectors, or with singular ones, the box is the padded
+    /// bounding box of atoms.
+    fn lammps_box(&self) -> (String, Vec<Point3<f64>>) {
+        let lattice = self.lattice_vectors().and_then(|lattice_vectors| {
+            let vectors = lattice_vectors.vectors();
+            vectors.try_inverse().map(|to_frac| (vectors, to_frac))
+        });
+        match lattice {
+            Some((vectors, to_frac)) => {
+                let wrap = |x: f64| {
+                    let wrapped = x.rem_euclid(1.0);
+                    if wrapped >= 1.0 {
+                        0.0
+                    } else {
+                        wrapped
+                    }
+                };
+                let positions = self
+                    .atoms()
+                    .xyz_coords()
+                    .iter()
+                    .map(|xyz| vectors * (to_frac * xyz).map(wrap))
+                    .collect();
+                let mut box_lines = format!(
+                    "{:18.12} {:18.12} xlo xhi\n{:18.12} {:18.12} ylo yhi\n{:18.12} {:18.12} zlo zhi\n",
+                    0.0,
+                    vectors[(0, 0)],
+                    0.0,
+                    vectors[(1, 1)],
+                    0.0,
+                    vectors[(2, 2)]
+                );
+                let tilts = [vectors[(0, 1)], vectors[(0, 2)], vectors[(1, 2)]];
+                if tilts.iter().any(|tilt| tilt.abs() > 1e-10) {
+                    box_lines.push_str(&format!(
+                        "{:18.12} {:18.12} {:18.12} xy xz yz\n",
+                        tilts[0], tilts[1], tilts[2]
+                    ));
+                }
+                (box_lines, positions)
+            }
+            None => {
+                let coords = self.atoms().xyz_coords();
+                let bound = |axis: usize| {
+                    coords.iter().fold((f64::MAX, f64::MIN), |(lo, hi), xyz| {
+                        (lo.min(xyz[axis]), hi.max(xyz[axis]))
+                    })
+                };
+                let box_lines: Vec<String> = ["x", "y", "z"]
+                    .iter()
+                    .enumerate()
+                    .map(|(axis, name)| {
+                        let (lo, hi) = bound(axis);
+                        format!(
+                            "{:18.12} {:18.12} {name}lo {name}hi\n",
+                            lo - NON_PERIODIC_PADDING,
+                            hi + NON_PERIODIC_PADDING
+                        )
+                    })
+                    .collect();
+                (box_lines.concat(), coords.to_vec())
+            }
+        }
+    }
+}
+
+impl<T, M> LammpsExport<M> for T
+where
+    T: AsRef<LatticeModel<M>>,
+    M: ModelInfo,
+{
+    fn export_lammps(&self, atom_style: LammpsAtomStyle) -> String {
+        let mut model = self.as_ref().clone();
+        model.rotate_to_lammps_frame();
+        let type_ids = self.lammps_type_ids();
+        let (box_lines, positions) = model.lammps_box();
+        let masses: Vec<String> = type_ids
+            .iter()
+            .map(|(elm, type_id)| {
+                let mass = ELEMENT_TABLE.get_by_symbol(elm).unwrap().mass();
+                format!("{:>4} {:12.6}  # {}\n", type_id, mass, elm)
+            })
+            .collect();
+        let atoms = model.atoms();
+        let atom_lines: Vec<String> = positions
+            .iter()
+            .enumerate()
+            .map(|(i, xyz)| {
+                let symbol = &atoms.element_symbols()[i];
+                let (_, type_id) = type_ids.iter().find(|(elm, _)| elm == symbol).unwrap();
+                let charge = match atom_style {
+                    LammpsAtomStyle::Atomic => String::new(),
+                    LammpsAtomStyle::Charge(charges) => {
+                        format!(" {:10.6}", charges.get(i).copied().unwrap_or(0.0))
+                    }
+                };
+                format!(
+                    "{:>6} {:>4}{} {:18.12} {:18.12} {:18.12}\n",
+                    atoms.atom_ids()[i],
+                    type_id,
+                    charge,
+                    xyz.x,
+                    xyz.y,
+                    xyz.z
+                )
+            })
+            .collect();
+        format!(
+            "LAMMPS data file written by castep-model-core\n\n{:>8} atoms\n{:>8} atom types\n\n{}\nMasses\n\n{}\nAtoms # {}\n\n{}",
+            atoms.size(),
+            type_ids.len(),
+            box_lines,
+            masses.concat(),
+            atom_style,
+            atom_lines.concat()
+        )
+    }
+
+    fn lammps_type_ids(&self) -> Vec<(String, usize)> {
+        self.as_ref()
+            .element_set()
+            .into_iter()
+            .enumerate()
+            .map(|(i, elm)| (elm, i + 1))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use na::Matrix3;
+
+    use crate::{
+        lattice::LatticeVectors,
+        model_type::{lammps::LammpsAtomStyle, LammpsExport},
+        CellModel, LatticeModel,
+    };
+
+    /// Hexagonal cell with a along y and b in the yz plane.
+    const CELL: &str = r#"%BLOCK LATTICE_CART
+   0.0   4.0   0.0
+   0.0  -2.0   3.4641016151377544
+   6.0   0.0   0.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+   O   0.3333333333333333   0.6666666666666667   0.5
+  Fe  -0.5   0.0   0.0
+%ENDBLOCK POSITIONS_FRAC
+"#;
+
+    #[test]
+    fn export_lammps_data() {
+        let model = LatticeModel::<CellModel>::from_str(CELL).unwrap();
+        assert_eq!(
+            model.lammps_type_ids(),
+            vec![("O".to_string(), 1), ("Fe".to_string(), 2)]
+        );
+        let data = model.export_lammps(LammpsAtomStyle::Charge(&[-2.0, 3.0]));
+        let line_values = |suffix: &str| -> Vec<f64> {
+            let line = data.lines().find(|line| line.ends_with(suffix)).unwrap();
+            line.split_whitespace()
+                .filter_map(|value| value.parse().ok())
+                .collect()
+        };
+        let close = |a: &[f64], b: &[f64]| {
+            a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-8)
+        };
+        assert!(close(&line_values("xlo xhi"), &[0.0, 4.0]));
+        assert!(close(&line_values("ylo yhi"), &[0.0, 3.4641016151377544]));
+        assert!(close(&line_values("zlo zhi"), &[0.0, 6.0]));
+        assert!(close(&line_values("xy xz yz"), &[-2.0, 0.0, 0.0]));
+        assert!(data.contains("Atoms # charge"));
+        assert!(data.contains("# Fe"));
+        let atom_lines: Vec<Vec<f64>> = data
+            .lines()
+            .skip_while(|line| !line.starts_with("Atoms"))
+            .skip(2)
+            .map(|line| {
+                line.split_whitespace()
+                    .map(|value| value.parse().unwrap())
+                    .collect()
+            })
+            .collect();
+        assert!(close(
+            &atom_lines[0],
+            &[1.0, 1.0, -2.0, 0.0, 2.3094010767585034, 3.0]
+        ));
+        // Wrapped into the box.
+        assert!(close(&atom_lines[1], &[2.0, 2.0, 3.0, 2.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn export_singular_lattice() {
+        let mut model = LatticeModel::<CellModel>::from_str(CELL).unwrap();
+        *model.lattice_vectors_mut() = Some(LatticeVectors::new(Matrix3::new(
+            4.0, 8.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 6.0,
+        )));
+        let data = model.export_lammps(LammpsAtomStyle::Atomic);
+        // The padded bounding box of the atoms.
+        assert!(data.contains(&format!("{:18.12} {:18.12} xlo xhi\n", -1.0, 4.0)));
+        assert!(!data.contains("xy xz yz"));
+    }
+}
@@ -1,13 +1,35 @@
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug};
 
-use crate::{CellModel, MsiModel};
+use cpt::{data::ELEMENT_TABLE, element::LookupElement};
+
+use crate::{
+    atom::species_element, error::CellConstraintsError, lattice::symmetry::SymmetryOperation,
+    CellModel, MsiModel,
+};
+
+use self::{
+    cell_constraints::CellConstraints,
+    hubbard::{find_hubbard_value, insert_hubbard_value, HubbardOrbital, HubbardValue},
+    lammps::LammpsAtomStyle,
+    msi::MsiAttribute,
+    pseudopotential::Pseudopotential,
+};
 
 pub mod cell;
+pub mod cell_constraints;
+mod cif;
+pub mod extxyz;
+pub mod hubbard;
+pub mod lammps;
 pub mod msi;
+pub mod poscar;
+pub mod pseudopotential;
+pub mod xsd;
 
 pub trait ModelInfo: Debug + Clone + Default {}
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Settings<T: ModelInfo> {
     /// List of k-points. Each k-point has xyz and a weight factor.
     kpoints_list: Vec<[f64; 4]>,
@@ -19,6 +41,8 @@ pub struct Settings<T: ModelInfo> {
     kpoints_mp_offset: [f64; 3],
     /// Option in `IONIC_CONSTRAINTS` in cell format
     fix_all_cell: bool,
+    /// `CELL_CONSTRAINTS` in cell format, only used when `fix_all_cell` is false.
+    cell_constraints: Option<CellConstraints>,
     /// Option in `IONIC_CONSTRAINTS` in cell format
     fix_com: bool,
     /// Option in `cell` format
@@ -29,10 +53,24 @@ pub struct Settings<T: ModelInfo> {
     cry_display: (u32, u32),
     /// A parameter in `msi` format
     periodic_type: u8,
-    /// A parameter in `msi` format
+    /// The space group, `"1 1"` style in `msi`, or the Hermann-Mauguin symbol from `cif`.
     space_group: String,
+    /// Operations of the space group, in fractional coordinates. Empty if unknown.
+    symmetry_operations: Vec<SymmetryOperation>,
     /// A parameter in `msi` format
     cry_tolerance: f64,
+    /// Model attributes in `msi` not recognised by the parser, written back on export.
+    unknown_attributes: Vec<MsiAttribute>,
+    /// Pseudopotentials by species, overriding those in `ELEMENT_TABLE`.
+    species_potentials: HashMap<String, Pseudopotential>,
+    /// Masses by species in amu, overriding those in `ELEMENT_TABLE`, e.g. for isotopes.
+    species_masses: HashMap<String, f64>,
+    /// Numbers of LCAO states by species, overriding those in `ELEMENT_TABLE`.
+    species_lcao_states: HashMap<String, u8>,
+    /// U values of DFT+U, by species and orbital.
+    hubbard_u: Vec<HubbardValue>,
+    /// Alpha values of DFT+U, by species and orbital.
+    hubbard_alpha: Vec<HubbardValue>,
     format_marker: T,
 }
 
@@ -55,18 +93,183 @@ impl<T: ModelInfo> Default for Settings<T> {
             kpoints_mp_spacing: None,
             kpoints_mp_offset: [0.0, 0.0, 0.0],
             fix_all_cell: true,
+            cell_constraints: None,
             fix_com: false,
             external_efield: [0.0, 0.0, 0.0],
             external_pressure: [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
             periodic_type: 100_u8,
             space_group: "1 1".to_string(),
+            symmetry_operations: Vec::new(),
             cry_tolerance: 0.05,
             cry_display: (192, 256),
+            unknown_attributes: Vec::new(),
+            species_potentials: HashMap::new(),
+            species_masses: HashMap::new(),
+            species_lcao_states: HashMap::new(),
+            hubbard_u: Vec::new(),
+            hubbard_alpha: Vec::new(),
             format_marker: T::default(),
         }
     }
 }
 
+/// Methods for all formats
+impl<T: ModelInfo> Settings<T> {
+    pub fn space_group(&self) -> &str {
+        self.space_group.as_ref()
+    }
+
+    pub fn set_space_group(&mut self, space_group: &str) {
+        self.space_group = space_group.into();
+    }
+
+    pub fn symmetry_operations(&self) -> &[SymmetryOperation] {
+        self.symmetry_operations.as_ref()
+    }
+
+    pub fn set_symmetry_operations(&mut self, symmetry_operations: Vec<SymmetryOperation>) {
+        self.symmetry_operations = symmetry_operations;
+    }
+    /// The pseudopotentials assigned to species.
+    pub fn species_potentials(&self) -> &HashMap<String, Pseudopotential> {
+        &self.species_potentials
+    }
+    /// Assign the pseudopotential to the species.
+    pub fn set_species_potential(&mut self, species: &str, potential: Pseudopotential) {
+        self.species_potentials.insert(species.into(), potential);
+    }
+    /// The pseudopotential assigned to the species, or the potential file of the
+    /// element in `ELEMENT_TABLE`.
+    pub fn species_potential(&self, species: &str) -> Pseudopotential {
+        self.species_potentials
+            .get(species)
+            .cloned()
+            .unwrap_or_else(|| {
+                Pseudopotential::File(
+                    ELEMENT_TABLE
+                        .get_by_symbol(species_element(species))
+                        .unwrap()
+                        .potential()
+                        .into(),
+                )
+            })
+    }
+    /// Set the mass (amu) of the species, e.g. `2.014` for `H:D`.
+    pub fn set_species_mass(&mut self, species: &str, mass: f64) {
+        self.species_masses.insert(species.into(), mass);
+    }
+    /// The mass (amu) of the species, or that of the element in `ELEMENT_TABLE`.
+    pub fn species_mass(&self, species: &str) -> f64 {
+        self.species_masses
+            .get(species)
+            .copied()
+            .unwrap_or_else(|| {
+                ELEMENT_TABLE
+                    .get_by_symbol(species_element(species))
+                    .unwrap()
+                    .mass()
+            })
+    }
+    /// Set the number of LCAO states of the species for population analysis.
+    pub fn set_species_lcao_state(&mut self, species: &str, lcao_state: u8) {
+        self.species_lcao_states.insert(species.into(), lcao_state);
+    }
+    /// The number of LCAO states of the species, or that of the element in `ELEMENT_TABLE`.
+    pub fn species_lcao_state(&self, species: &str) -> u8 {
+        self.species_lcao_states
+            .get(species)
+            .copied()
+            .unwrap_or_else(|| {
+                ELEMENT_TABLE
+                    .get_by_symbol(species_element(species))
+                    .unwrap()
+                    .lcao()
+            })
+    }
+    /// The U values of DFT+U. Empty if none is set.
+    pub fn hubbard_u(&self) -> &[HubbardValue] {
+        self.hubbard_u.as_ref()
+    }
+
+    pub fn set_hubbard_u(&mut self, hubbard_u: Vec<HubbardValue>) {
+        self.hubbard_u = hubbard_u;
+    }
+    /// The U (eV) on the orbital of the species, `None` if not set.
+    pub fn species_hubbard_u(&self, species: &str, orbital: HubbardOrbital) -> Option<f64> {
+        find_hubbard_value(&self.hubbard_u, species, None, orbital)
+    }
+    /// Set the U (eV) on the orbital of the species, replacing the previous value.
+    pub fn set_species_hubbard_u(&mut self, species: &str, orbital: HubbardOrbital, value: f64) {
+        insert_hubbard_value(
+            &mut self.hubbard_u,
+            HubbardValue::new(species, orbital, value),
+        )
+    }
+    /// The U (eV) on the orbital of one ion of the species, `None` if not set.
+    pub fn ion_hubbard_u(&self, species: &str, ion: u32, orbital: HubbardOrbital) -> Option<f64> {
+        find_hubbard_value(&self.hubbard_u, species, Some(ion), orbital)
+    }
+    /// Set the U (eV) on the orbital of one ion of the species, replacing the previous value.
+    pub fn set_ion_hubbard_u(
+        &mut self,
+        species: &str,
+        ion: u32,
+        orbital: HubbardOrbital,
+        value: f64,
+    ) {
+        insert_hubbard_value(
+            &mut self.hubbard_u,
+            HubbardValue::new_on_ion(species, ion, orbital, value),
+        )
+    }
+    /// The alpha values of DFT+U. Empty if none is set.
+    pub fn hubbard_alpha(&self) -> &[HubbardValue] {
+        self.hubbard_alpha.as_ref()
+    }
+
+    pub fn set_hubbard_alpha(&mut self, hubbard_alpha: Vec<HubbardValue>) {
+        self.hubbard_alpha = hubbard_alpha;
+    }
+    /// The alpha (eV) on the orbital of the species, `None` if not set.
+    pub fn species_hubbard_alpha(&self, species: &str, orbital: HubbardOrbital) -> Option<f64> {
+        find_hubbard_value(&self.hubbard_alpha, species, None, orbital)
+    }
+    /// Set the alpha (eV) on the orbital of the species, replacing the previous value.
+    pub fn set_species_hubbard_alpha(
+        &mut self,
+        species: &str,
+        orbital: HubbardOrbital,
+        value: f64,
+    ) {
+        insert_hubbard_value(
+            &mut self.hubbard_alpha,
+            HubbardValue::new(species, orbital, value),
+        )
+    }
+    /// The alpha (eV) on the orbital of one ion of the species, `None` if not set.
+    pub fn ion_hubbard_alpha(
+        &self,
+        species: &str,
+        ion: u32,
+        orbital: HubbardOrbital,
+    ) -> Option<f64> {
+        find_hubbard_value(&self.hubbard_alpha, species, Some(ion), orbital)
+    }
+    /// Set the alpha (eV) on the orbital of one ion of the species, replacing the previous value.
+    pub fn set_ion_hubbard_alpha(
+        &mut self,
+        species: &str,
+        ion: u32,
+        orbital: HubbardOrbital,
+        value: f64,
+    ) {
+        insert_hubbard_value(
+            &mut self.hubbard_alpha,
+            HubbardValue::new_on_ion(species, ion, orbital, value),
+        )
+    }
+}
+
 /// Methods exposed to `CellModel` only
 impl Settings<CellModel> {
     pub fn kpoints_list(&self) -> &[[f64; 4]] {
@@ -89,6 +292,10 @@ impl Settings<CellModel> {
         self.fix_all_cell
     }
 
+    pub fn cell_constraints(&self) -> Option<&CellConstraints> {
+        self.cell_constraints.as_ref()
+    }
+
     pub fn fix_com(&self) -> bool {
         self.fix_com
     }
@@ -100,6 +307,68 @@ impl Settings<CellModel> {
     pub fn external_pressure(&self) -> [f64; 6] {
         self.external_pressure
     }
+
+    pub fn set_kpoints_list(&mut self, kpoints_list: Vec<[f64; 4]>) {
+        self.kpoints_list = kpoints_list;
+    }
+
+    pub fn set_kpoints_grid(&mut self, kpoints_grid: [u8; 3]) {
+        self.kpoints_grid = kpoints_grid;
+    }
+
+    pub fn set_kpoints_mp_spacing(&mut self, kpoints_mp_spacing: Option<f64>) {
+        self.kpoints_mp_spacing = kpoints_mp_spacing;
+    }
+
+    pub fn set_kpoints_mp_offset(&mut self, kpoints_mp_offset: [f64; 3]) {
+        self.kpoints_mp_offset = kpoints_mp_offset;
+    }
+
+    /// With `fix_all_cell` true, the `cell_constraints` are not written.
+    /// # Errors
+    /// This function will return an error if `fix_all_cell` is true while the
+    /// `cell_constraints` relax the cell. Clear them with `set_cell_constraints(None)` first.
+    pub fn set_fix_all_cell(&mut self, fix_all_cell: bool) -> Result<(), CellConstraintsError> {
+        match self.cell_constraints {
+            Some(constraints) if fix_all_cell && !constraints.is_all_fixed() => {
+                Err(CellConstraintsError::ConflictWithFixAllCell)
+            }
+            _ => {
+                self.fix_all_cell = fix_all_cell;
+                Ok(())
+            }
+        }
+    }
+    /// Set the `CELL_CONSTRAINTS`. Set `fix_all_cell` to false first.
+    /// # Errors
+    /// This function will return an error if the constraints relax the cell
+    /// while `fix_all_cell` is true.
+    pub fn set_cell_constraints(
+        &mut self,
+        cell_constraints: Option<CellConstraints>,
+    ) -> Result<(), CellConstraintsError> {
+        match cell_constraints {
+            Some(constraints) if self.fix_all_cell && !constraints.is_all_fixed() => {
+                Err(CellConstraintsError::ConflictWithFixAllCell)
+            }
+            _ => {
+                self.cell_constraints = cell_constraints;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_fix_com(&mut self, fix_com: bool) {
+        self.fix_com = fix_com;
+    }
+
+    pub fn set_external_efield(&mut self, external_efield: [f64; 3]) {
+        self.external_efield = external_efield;
+    }
+
+    pub fn set_external_pressure(&mut self, external_pressure: [f64; 6]) {
+        self.external_pressure = external_pressure;
+    }
 }
 
 /// Methods exposed to `MsiModel` only
@@ -108,13 +377,17 @@ impl Settings<MsiModel> {
         self.periodic_type
     }
 
-    pub fn space_group(&self) -> &str {
-        self.space_group.as_ref()
-    }
-
     pub fn cry_tolerance(&self) -> f64 {
         self.cry_tolerance
     }
+
+    pub fn unknown_attributes(&self) -> &[MsiAttribute] {
+        self.unknown_attributes.as_ref()
+    }
+
+    pub fn set_unknown_attributes(&mut self, unknown_attributes: Vec<MsiAttribute>) {
+        self.unknown_attributes = unknown_attributes;
+    }
 }
 
 pub trait DefaultExport<T: ModelInfo> {
@@ -124,3 +397,21 @@ pub trait DefaultExport<T: ModelInfo> {
 pub trait BandStructureExport<T: ModelInfo> {
     fn export(&self) -> String;
 }
+
+pub trait CifExport<T: ModelInfo> {
+    /// Export as a P1 `cif`, with all atoms listed.
+    /// Returns `None` without invertible lattice vectors.
+    fn export_cif(&self) -> Option<String>;
+    /// Export the asymmetric unit with the operations in `Settings<T>`.
+    /// Returns `None` without invertible lattice vectors, when the space group is
+    /// unknown, or the atoms do not follow the operations within `tolerance` (Å).
+    fn export_symmetrized_cif(&self, tolerance: f64) -> Option<String>;
+}
+
+pub trait LammpsExport<T: ModelInfo> {
+    /// Export as a LAMMPS `data` file, rotated into the restricted triclinic
+    /// convention with a along x and b in the xy plane.
+    fn export_lammps(&self, atom_style: LammpsAtomStyle) -> String;
+    /// Atom type ids numbered from 1, in the order of `element_set()`.
+    fn lammps_type_ids(&self) -> Vec<(String, usize)>;
+}
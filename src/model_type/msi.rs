@@ -4,7 +4,7 @@ use na::{UnitQuaternion, Vector, Vector3};
 
 use crate::{
     atom::{visitor::VisitCollection, Atom, AtomCollection, AtomCollectionBuilder, AtomView},
-    bond::Bonds,
+    bond::{BondType, Bonds},
     builder_typestate::No,
     lattice::{LatticeModel, LatticeVectors},
     Transformation,
@@ -13,6 +13,7 @@ use crate::{
 use super::{cell::CellModel, DefaultExport, ModelInfo, Settings};
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A unit struct to mark `msi` format
 pub struct MsiModel;
 
@@ -30,6 +31,90 @@ pub struct MsiFile {
     bonds: Option<Bonds<MsiModel>>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The type annotation of an attribute in `msi`.
+pub enum MsiAttributeType {
+    /// `C`, a quoted string.
+    Char,
+    /// `D`, double precision float.
+    Double,
+    /// `I`, integer.
+    Int,
+    /// `F`, float.
+    Float,
+    /// `O`, reference to an object number.
+    Object,
+}
+
+impl MsiAttributeType {
+    pub fn from_tag(tag: char) -> Option<Self> {
+        match tag {
+            'C' => Some(Self::Char),
+            'D' => Some(Self::Double),
+            'I' => Some(Self::Int),
+            'F' => Some(Self::Float),
+            'O' => Some(Self::Object),
+            _ => None,
+        }
+    }
+
+    pub fn tag(&self) -> char {
+        match self {
+            Self::Char => 'C',
+            Self::Double => 'D',
+            Self::Int => 'I',
+            Self::Float => 'F',
+            Self::Object => 'O',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An attribute kept as it is written, e.g. `(A I FormalCharge 1)`.
+/// The value is the raw text, quotes and parentheses included,
+/// so it is written back unchanged.
+pub struct MsiAttribute {
+    attribute_type: MsiAttributeType,
+    name: String,
+    value: String,
+}
+
+impl MsiAttribute {
+    pub fn new(attribute_type: MsiAttributeType, name: &str, value: &str) -> Self {
+        Self {
+            attribute_type,
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn attribute_type(&self) -> MsiAttributeType {
+        self.attribute_type
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    pub fn value(&self) -> &str {
+        self.value.as_ref()
+    }
+}
+
+impl Display for MsiAttribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "(A {} {} {})",
+            self.attribute_type.tag(),
+            self.name,
+            self.value
+        )
+    }
+}
+
 /// Display trait for `Atom<MsiModel>`
 impl Display for Atom<MsiModel> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -86,12 +171,20 @@ where
             .unwrap()
             .with_atomic_nums(src.as_ref().atomic_nums())
             .unwrap()
+            .with_species_labels(src.as_ref().species_labels())
+            .unwrap()
             .with_xyz_coords(src.as_ref().xyz_coords())
             .unwrap()
             .with_fractional_xyz(src.as_ref().fractional_xyz())
             .unwrap()
             .with_atom_ids(src.as_ref().atom_ids())
             .unwrap()
+            .with_fixed_axes(src.as_ref().fixed_axes())
+            .unwrap()
+            .with_magnetic_moments(src.as_ref().magnetic_moments())
+            .unwrap()
+            .with_unknown_attributes(src.as_ref().unknown_attributes())
+            .unwrap()
             .finish()
             .unwrap()
             .build()
@@ -117,7 +210,10 @@ where
         msi_atom_array.sort_by_key(|a| a.atom_id());
         // Convert AoS back to SoA.
         let msi_atom_collection: AtomCollection<MsiModel> = msi_atom_array.into();
-        let mut msi_model = Self::new(Some(new_lat_vec), msi_atom_collection, Settings::default());
+        let mut settings = Settings::default();
+        settings.set_hubbard_u(cell_model.as_ref().settings().hubbard_u().to_vec());
+        settings.set_hubbard_alpha(cell_model.as_ref().settings().hubbard_alpha().to_vec());
+        let mut msi_model = Self::new(Some(new_lat_vec), msi_atom_collection, settings);
         let y_axis: Vector3<f64> = Vector::y();
         let b_vec = cell_model
             .as_ref()
@@ -135,11 +231,55 @@ where
     }
 }
 
+/// `Bond` objects numbered after the atom objects. The atoms are referred
+/// by their object numbers, which are the atom ids plus one.
+fn bond_objects(model: &LatticeModel<MsiModel>) -> String {
+    let first_object = model.atoms().atom_ids().iter().max().copied().unwrap_or(0) + 2;
+    model
+        .bonds()
+        .bonds()
+        .iter()
+        .zip(model.bonds().bond_types().iter())
+        .enumerate()
+        .map(|(i, (bond, bond_type))| {
+            let (atom_a, atom_b) = bond.atom_ids();
+            let type_line = match bond_type {
+                BondType::Single => String::new(),
+                BondType::Double => "    (A I Type 2)\n".to_string(),
+                BondType::Triple => "    (A I Type 3)\n".to_string(),
+            };
+            format!(
+                "  ({} Bond\n    (A O Atom1 {})\n    (A O Atom2 {})\n{}  )\n",
+                first_object + i as u32,
+                atom_a + 1,
+                atom_b + 1,
+                type_line
+            )
+        })
+        .collect()
+}
+
+/// Atom objects with their kept attributes.
+fn atom_objects(model: &LatticeModel<MsiModel>) -> String {
+    (0..model.atoms().size())
+        .map(|i| model.atoms().view_atom_at_index(i).unwrap().msi_object())
+        .collect()
+}
+
 impl<T> DefaultExport<MsiModel> for T
 where
     T: AsRef<LatticeModel<MsiModel>>,
 {
+    /// Attributes kept from parsing are written back after the known ones,
+    /// for the model and for each atom.
     fn export(&self) -> String {
+        let unknown_attributes: Vec<String> = self
+            .as_ref()
+            .settings()
+            .unknown_attributes()
+            .iter()
+            .map(|attr| format!("  {}\n", attr))
+            .collect();
         if let Some(lattice_vectors) = self.as_ref().lattice_vectors() {
             let headers_vectors: Vec<String> = vec![
                 "# MSI CERIUS2 DataModel File Version 4 0\n".to_string(),
@@ -159,36 +299,69 @@ where
                     self.as_ref().settings().cry_tolerance()
                 ),
             ];
-            format!("{}{})", headers_vectors.concat(), self.as_ref().atoms())
+            format!(
+                "{}{}{}{})",
+                headers_vectors.concat(),
+                unknown_attributes.concat(),
+                atom_objects(self.as_ref()),
+                bond_objects(self.as_ref())
+            )
         } else {
             let headers = "# MSI CERIUS2 DataModel File Version 4 0\n(1 Model\n";
-            format!("{}{})", headers, self.as_ref().atoms())
+            format!(
+                "{}{}{}{})",
+                headers,
+                unknown_attributes.concat(),
+                atom_objects(self.as_ref()),
+                bond_objects(self.as_ref())
+            )
         }
     }
 }
 
-impl<'a> Display for AtomView<'a, MsiModel> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            r#"  ({item_id} Atom
-    (A C ACL "{elm_id} {elm}")
-    (A C Label "{elm}")
-    (A D XYZ ({x:.12} {y:.12} {z:.12}))
-    (A I Id {atom_id})
-  )
-"#,
+impl<'a> AtomView<'a, MsiModel> {
+    /// The atom object, followed by the attributes kept from parsing.
+    /// A kept `Label` replaces the default one of the element symbol.
+    fn msi_object(&self) -> String {
+        let unknown_attributes = self.unknown_attributes();
+        let label = if unknown_attributes.iter().any(|attr| attr.name() == "Label") {
+            String::new()
+        } else {
+            format!("    (A C Label \"{}\")\n", self.element_symbol())
+        };
+        let unknown_lines: Vec<String> = unknown_attributes
+            .iter()
+            .map(|attr| format!("    {}\n", attr))
+            .collect();
+        // `msi` only fixes an atom as a whole. The flags of an atom fixed along
+        // some of the axes stay in the model but are not written.
+        let fixed = if self.fixed_axes().iter().all(|fixed| *fixed) {
+            "    (A I IsMotionFixed 1)\n"
+        } else {
+            ""
+        };
+        format!(
+            "  ({item_id} Atom\n    (A C ACL \"{elm_id} {elm}\")\n{label}    (A D XYZ ({x:.12} {y:.12} {z:.12}))\n    (A I Id {atom_id})\n{fixed}{unknown}  )\n",
             item_id = self.atom_id() + 1,
             elm_id = self.atomic_number(),
             elm = self.element_symbol(),
+            label = label,
             x = self.xyz().x,
             y = self.xyz().y,
             z = self.xyz().z,
             atom_id = self.atom_id(),
+            fixed = fixed,
+            unknown = unknown_lines.concat(),
         )
     }
 }
 
+impl<'a> Display for AtomView<'a, MsiModel> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.msi_object())
+    }
+}
+
 impl Display for AtomCollection<MsiModel> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let msi_atom_strings: Vec<String> = (0..self.size())
@@ -0,0 +1,165 @@
+use std::fmt::Display;
+
+use crate::{
+    atom::{visitor::VisitCollection, AtomCollection},
+    lattice::{LatticeModel, LatticeVectors},
+};
+
+use super::{cell::CellModel, msi::MsiModel, DefaultExport, ModelInfo, Settings};
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A unit struct to mark `POSCAR`/`CONTCAR` format of VASP.
+pub struct PoscarModel;
+
+impl ModelInfo for PoscarModel {}
+
+/// Transition from `LatticeModel<CellModel>` to `LatticeModel<PoscarModel>`
+impl From<LatticeModel<CellModel>> for LatticeModel<PoscarModel> {
+    fn from(cell_model: LatticeModel<CellModel>) -> Self {
+        let lattice_vectors = cell_model
+            .lattice_vectors()
+            .map(|lat_vec| LatticeVectors::new(lat_vec.vectors().to_owned()));
+        Self::new(
+            lattice_vectors,
+            cell_model.atoms().to_format(),
+            Settings::default(),
+        )
+    }
+}
+
+/// Transition from `LatticeModel<PoscarModel>` to `LatticeModel<CellModel>`
+/// The selective dynamics flags are kept as the fixed axes of atoms.
+impl From<LatticeModel<PoscarModel>> for LatticeModel<CellModel> {
+    fn from(poscar_model: LatticeModel<PoscarModel>) -> Self {
+        let lattice_vectors = poscar_model
+            .lattice_vectors()
+            .map(|lat_vec| LatticeVectors::new(lat_vec.vectors().to_owned()));
+        Self::new(
+            lattice_vectors,
+            poscar_model.atoms().to_format(),
+            Settings::default(),
+        )
+    }
+}
+
+/// Transition from `LatticeModel<MsiModel>` to `LatticeModel<PoscarModel>`
+/// through `LatticeModel<CellModel>`, so vector a is along the x axis.
+impl From<LatticeModel<MsiModel>> for LatticeModel<PoscarModel> {
+    fn from(msi_model: LatticeModel<MsiModel>) -> Self {
+        LatticeModel::<CellModel>::from(msi_model).into()
+    }
+}
+
+/// The lattice vectors as rows, with the scale factor of 1.0.
+impl Display for LatticeVectors<PoscarModel> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:19.14}", 1.0)?;
+        self.vectors()
+            .column_iter()
+            .try_for_each(|col| writeln!(f, "{:22.16}{:22.16}{:22.16}", col.x, col.y, col.z))
+    }
+}
+
+impl AtomCollection<PoscarModel> {
+    /// Indices of atoms grouped by species, in the order of `element_set()`.
+    fn grouped_indices(&self) -> Vec<(String, Vec<usize>)> {
+        self.element_set()
+            .into_iter()
+            .map(|elm| {
+                let indices: Vec<usize> = self
+                    .element_symbols()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, symbol)| **symbol == elm)
+                    .map(|(i, _)| i)
+                    .collect();
+                (elm, indices)
+            })
+            .collect()
+    }
+}
+
+impl<T> DefaultExport<PoscarModel> for T
+where
+    T: AsRef<LatticeModel<PoscarModel>>,
+{
+    /// Species are grouped in the order of `element_set()`, with `Direct` coordinates.
+    /// `Selective dynamics` is written when any atom has a fixed axis.
+    /// Without invertible lattice vectors the positions are written as `Cartesian`,
+    /// and a missing lattice is written as zero vectors, which VASP rejects.
+    fn export(&self) -> String {
+        let model = self.as_ref();
+        let lattice_block = model
+            .lattice_vectors()
+            .map(|lattice_vectors| lattice_vectors.to_string())
+            .unwrap_or_else(|| {
+                format!(
+                    "{:19.14}\n{}",
+                    1.0,
+                    format!("{:22.16}{:22.16}{:22.16}\n", 0.0, 0.0, 0.0).repeat(3)
+                )
+            });
+        let to_frac = model
+            .lattice_vectors()
+            .and_then(|lattice_vectors| lattice_vectors.vectors().try_inverse());
+        let atoms = model.atoms();
+        let groups = atoms.grouped_indices();
+        let species_line: Vec<String> = groups
+            .iter()
+            .map(|(elm, _)| format!("{:>5}", elm))
+            .collect();
+        let counts_line: Vec<String> = groups
+            .iter()
+            .map(|(_, indices)| format!("{:>5}", indices.len()))
+            .collect();
+        let title: Vec<String> = groups
+            .iter()
+            .map(|(elm, indices)| format!("{}{}", elm, indices.len()))
+            .collect();
+        let selective_dynamics = atoms.fixed_axes().iter().flatten().any(|fixed| *fixed);
+        let positions: Vec<String> = groups
+            .iter()
+            .flat_map(|(_, indices)| indices.iter())
+            .map(|&i| {
+                let coord = match to_frac {
+                    Some(to_frac) => {
+                        atoms.fractional_xyz()[i].unwrap_or_else(|| to_frac * atoms.xyz_coords()[i])
+                    }
+                    None => atoms.xyz_coords()[i],
+                };
+                let flags = if selective_dynamics {
+                    let flags: Vec<&str> = atoms.fixed_axes()[i]
+                        .iter()
+                        .map(|fixed| if *fixed { "F" } else { "T" })
+                        .collect();
+                    format!("   {}", flags.join(" "))
+                } else {
+                    String::new()
+                };
+                format!(
+                    "{:20.16}{:20.16}{:20.16}{}\n",
+                    coord.x, coord.y, coord.z, flags
+                )
+            })
+            .collect();
+        format!(
+            "{}\n{}{}\n{}\n{}{}\n{}",
+            title.join(" "),
+            lattice_block,
+            species_line.concat(),
+            counts_line.concat(),
+            if selective_dynamics {
+                "Selective dynamics\n"
+            } else {
+                ""
+            },
+            if to_frac.is_some() {
+                "Direct"
+            } else {
+                "Cartesian"
+            },
+            positions.concat()
+        )
+    }
+}
@@ -0,0 +1,46 @@
+use std::{fmt::Display, path::Path};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The pseudopotential assigned to a species, overriding the one in `ELEMENT_TABLE`.
+pub enum Pseudopotential {
+    /// A potential file, relative to the potential directory or absolute.
+    /// It is copied into the seed folder, and written by the file name.
+    File(String),
+    /// An on-the-fly generation string of `castep`, e.g. `3|1.8|7|8|9|40:41:32(qc=5)`.
+    Otfg(String),
+}
+
+impl Pseudopotential {
+    /// An entry of `SPECIES_POT`, which is an OTFG string when it contains `|`.
+    pub fn from_entry(entry: &str) -> Self {
+        if entry.contains('|') {
+            Self::Otfg(entry.into())
+        } else {
+            Self::File(entry.into())
+        }
+    }
+    /// The potential file to copy and read, `None` for OTFG strings.
+    pub fn file(&self) -> Option<&str> {
+        match self {
+            Self::File(file) => Some(file),
+            Self::Otfg(_) => None,
+        }
+    }
+}
+
+/// The entry in `SPECIES_POT`.
+impl Display for Pseudopotential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(file) => {
+                let file_name = Path::new(file)
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or(file);
+                write!(f, "{}", file_name)
+            }
+            Self::Otfg(otfg) => write!(f, "{}", otfg),
+        }
+    }
+}
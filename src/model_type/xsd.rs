@@ -0,0 +1,246 @@
+use crate::{
+    atom::AtomCollection,
+    bond::BondType,
+    lattice::{LatticeModel, LatticeVectors},
+};
+
+use super::{cell::CellModel, msi::MsiModel, DefaultExport, ModelInfo, Settings};
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A unit struct to mark `xsd` format of `Materials Studio`.
+pub struct XsdModel;
+
+impl ModelInfo for XsdModel {}
+
+/// Transition from `LatticeModel<CellModel>` to `LatticeModel<XsdModel>`
+impl From<LatticeModel<CellModel>> for LatticeModel<XsdModel> {
+    fn from(cell_model: LatticeModel<CellModel>) -> Self {
+        let lattice_vectors = cell_model
+            .lattice_vectors()
+            .map(|lat_vec| LatticeVectors::new(lat_vec.vectors().to_owned()));
+        Self::new(
+            lattice_vectors,
+            cell_model.atoms().to_format(),
+            Settings::default(),
+        )
+    }
+}
+
+/// Transition from `LatticeModel<XsdModel>` to `LatticeModel<CellModel>`
+/// Missing fractional coordinates are computed from the lattice vectors.
+/// Bonds are dropped since `cell` has no place for them.
+impl From<LatticeModel<XsdModel>> for LatticeModel<CellModel> {
+    fn from(xsd_model: LatticeModel<XsdModel>) -> Self {
+        let lattice_vectors = xsd_model
+            .lattice_vectors()
+            .map(|lat_vec| LatticeVectors::new(lat_vec.vectors().to_owned()));
+        let mut atoms: AtomCollection<CellModel> = xsd_model.atoms().to_format();
+        if let Some(to_frac) = lattice_vectors
+            .as_ref()
+            .and_then(|lat_vec| lat_vec.vectors().try_inverse())
+        {
+            let xyz_coords = atoms.xyz_coords().to_vec();
+            atoms
+                .fractional_xyz_mut()
+                .iter_mut()
+                .zip(xyz_coords)
+                .filter(|(frac, _)| frac.is_none())
+                .for_each(|(frac, xyz)| *frac = Some(to_frac * xyz));
+        }
+        Self::new(lattice_vectors, atoms, Settings::default())
+    }
+}
+
+/// Transition from `LatticeModel<MsiModel>` to `LatticeModel<XsdModel>`
+/// Bonds are kept.
+impl From<LatticeModel<MsiModel>> for LatticeModel<XsdModel> {
+    fn from(msi_model: LatticeModel<MsiModel>) -> Self {
+        let lattice_vectors = msi_model
+            .lattice_vectors()
+            .map(|lat_vec| LatticeVectors::new(lat_vec.vectors().to_owned()));
+        let mut xsd_model = Self::new(
+            lattice_vectors,
+            msi_model.atoms().to_format(),
+            Settings::default(),
+        );
+        xsd_model.set_bonds(msi_model.bonds().to_format());
+        xsd_model
+    }
+}
+
+/// Transition from `LatticeModel<XsdModel>` to `LatticeModel<MsiModel>`
+/// Bonds are kept.
+impl From<LatticeModel<XsdModel>> for LatticeModel<MsiModel> {
+    fn from(xsd_model: LatticeModel<XsdModel>) -> Self {
+        let lattice_vectors = xsd_model
+            .lattice_vectors()
+            .map(|lat_vec| LatticeVectors::new(lat_vec.vectors().to_owned()));
+        let mut msi_model = Self::new(
+            lattice_vectors,
+            xsd_model.atoms().to_format(),
+            Settings::default(),
+        );
+        msi_model.set_bonds(xsd_model.bonds().to_format());
+        msi_model
+    }
+}
+
+/// Vectors in attributes like `XYZ` and `AVector`, written as `x,y,z`.
+fn xsd_vector(x: f64, y: f64, z: f64) -> String {
+    format!("{:.12},{:.12},{:.12}", x, y, z)
+}
+
+impl<T> DefaultExport<XsdModel> for T
+where
+    T: AsRef<LatticeModel<XsdModel>>,
+{
+    /// Periodic models are written in a `SymmetrySystem` with `P1` space group and
+    /// fractional `XYZ`, otherwise in a `Molecule` with cartesian `XYZ`.
+    /// `ID` of objects starts from 4, after the tree root and the containers.
+    fn export(&self) -> String {
+        let model = self.as_ref();
+        let atoms = model.atoms();
+        let to_frac = model
+            .lattice_vectors()
+            .and_then(|lat_vec| lat_vec.vectors().try_inverse());
+        let atom_xml_id = |i: usize| i + 4;
+        let bond_xml_id = |i: usize| atoms.size() + i + 4;
+        let mut connections: Vec<Vec<usize>> = vec![Vec::new(); atoms.size()];
+        model
+            .bonds()
+            .bonds()
+            .iter()
+            .enumerate()
+            .for_each(|(i, bond)| {
+                let (a, b) = bond.atom_ids();
+                connections[a as usize - 1].push(bond_xml_id(i));
+                connections[b as usize - 1].push(bond_xml_id(i));
+            });
+        let mut counts: Vec<(String, u32)> = Vec::new();
+        let atom_lines: Vec<String> = (0..atoms.size())
+            .map(|i| {
+                let symbol = &atoms.element_symbols()[i];
+                let count = match counts.iter_mut().find(|(elm, _)| elm == symbol) {
+                    Some((_, count)) => {
+                        *count += 1;
+                        *count
+                    }
+                    None => {
+                        counts.push((symbol.to_string(), 1));
+                        1
+                    }
+                };
+                let xyz = match to_frac {
+                    Some(to_frac) => atoms.fractional_xyz()[i]
+                        .unwrap_or_else(|| to_frac * atoms.xyz_coords()[i]),
+                    None => atoms.xyz_coords()[i],
+                };
+                let connections_attr = if connections[i].is_empty() {
+                    String::new()
+                } else {
+                    let ids: Vec<String> = connections[i].iter().map(|id| id.to_string()).collect();
+                    format!(" Connections=\"{}\"", ids.join(","))
+                };
+                let restricted = if atoms.fixed_axes()[i].iter().all(|fixed| *fixed) {
+                    if to_frac.is_some() {
+                        " RestrictedProperties=\"FractionalXYZ\""
+                    } else {
+                        " RestrictedProperties=\"XYZ\""
+                    }
+                } else {
+                    ""
+                };
+                format!(
+                    "\t\t\t\t<Atom3d ID=\"{}\" Mapping=\"{}\" Parent=\"2\" Name=\"{}{}\" UserID=\"{}\" XYZ=\"{}\"{} Components=\"{}\"{}/>\n",
+                    atom_xml_id(i),
+                    atom_xml_id(i),
+                    symbol,
+                    count,
+                    atoms.atom_ids()[i],
+                    xsd_vector(xyz.x, xyz.y, xyz.z),
+                    connections_attr,
+                    symbol,
+                    restricted
+                )
+            })
+            .collect();
+        let bond_lines: Vec<String> = model
+            .bonds()
+            .bonds()
+            .iter()
+            .zip(model.bonds().bond_types().iter())
+            .enumerate()
+            .map(|(i, (bond, bond_type))| {
+                let (a, b) = bond.atom_ids();
+                let type_attr = match bond_type {
+                    BondType::Single => "",
+                    BondType::Double => " Type=\"Double\"",
+                    BondType::Triple => " Type=\"Triple\"",
+                };
+                format!(
+                    "\t\t\t\t<Bond ID=\"{}\" Mapping=\"{}\" Parent=\"2\" Connects=\"{},{}\"{}/>\n",
+                    bond_xml_id(i),
+                    bond_xml_id(i),
+                    atom_xml_id(a as usize - 1),
+                    atom_xml_id(b as usize - 1),
+                    type_attr
+                )
+            })
+            .collect();
+        let body = match model.lattice_vectors() {
+            Some(lat_vec) => {
+                let vectors = lat_vec.vectors();
+                let space_group_id = bond_xml_id(model.bonds().len());
+                let column = |i: usize| {
+                    let col = vectors.column(i);
+                    xsd_vector(col.x, col.y, col.z)
+                };
+                format!(
+                    "\t\t<SymmetrySystem ID=\"2\" Mapping=\"3\" Children=\"{children}\" Normalized=\"1\" Name=\"SymmetrySystem\" UserID=\"1\" XYZ=\"0,0,0\" OverspecificationTolerance=\"0.05\" PeriodicDisplayType=\"Original\">\n\
+                     \t\t\t<MappingSet ID=\"3\" SymmetryDefinition=\"{space_group_id}\" ActiveSystem=\"2\" NumFamilies=\"1\" OwnsTotalConstraintMapping=\"1\" TotalConstraintMapping=\"3\">\n\
+                     \t\t\t\t<MappingFamily ID=\"{family_id}\" NumImageMappings=\"0\">\n\
+                     \t\t\t\t\t<IdentityMapping ID=\"{identity_id}\" Element=\"1,0,0,0,0,1,0,0,0,0,1,0\" Constraint=\"1,0,0,0,0,1,0,0,0,0,1,0\" MappedObjects=\"{children}\" DefectObjects=\"{space_group_id}\" NumImages=\"{num_images}\" NumDefects=\"1\">\n\
+                     {atom_lines}{bond_lines}\
+                     \t\t\t\t\t</IdentityMapping>\n\
+                     \t\t\t\t</MappingFamily>\n\
+                     \t\t\t</MappingSet>\n\
+                     \t\t\t<SpaceGroup ID=\"{space_group_id}\" Parent=\"2\" Children=\"\" DisplayStyle=\"Solid\" XYZ=\"0,0,0\" Color=\"0,0,0,0\" AVector=\"{a}\" BVector=\"{b}\" CVector=\"{c}\" OrientationBase=\"C along Z, B in YZ plane\" Centering=\"3D Primitive-Centered\" Lattice=\"3D Triclinic\" GroupName=\"P1\" Operators=\"1,0,0,0,0,1,0,0,0,0,1,0\" DisplayRange=\"0,1,0,1,0,1\" LineThickness=\"2\" CylinderRadius=\"0.2\" LabelAxes=\"1\" ActiveSystem=\"2\" ITNumber=\"1\" LongName=\"P 1\" Qualifier=\"Origin-1\" SchoenfliesName=\"C1-1\" System=\"Triclinic\" Class=\"1\"/>\n\
+                     \t\t</SymmetrySystem>\n",
+                    children = (4..space_group_id)
+                        .map(|id| id.to_string())
+                        .collect::<Vec<String>>()
+                        .join(","),
+                    space_group_id = space_group_id,
+                    family_id = space_group_id + 1,
+                    identity_id = space_group_id + 2,
+                    num_images = space_group_id - 4,
+                    atom_lines = atom_lines.concat(),
+                    bond_lines = bond_lines.concat(),
+                    a = column(0),
+                    b = column(1),
+                    c = column(2),
+                )
+            }
+            None => format!(
+                "\t\t<Molecule ID=\"2\" Mapping=\"3\" Children=\"{}\" Name=\"Molecule\">\n{}{}\t\t</Molecule>\n",
+                (4..bond_xml_id(model.bonds().len()))
+                    .map(|id| id.to_string())
+                    .collect::<Vec<String>>()
+                    .join(","),
+                atom_lines.concat(),
+                bond_lines.concat()
+            ),
+        };
+        format!(
+            "<?xml version=\"1.0\" encoding=\"latin1\"?>\n\
+             <!DOCTYPE XSD []>\n\
+             <XSD Version=\"6.0\">\n\
+             \t<AtomisticTreeRoot ID=\"1\" NumProperties=\"0\" NumChildren=\"1\">\n\
+             {}\
+             \t</AtomisticTreeRoot>\n\
+             </XSD>\n",
+            body
+        )
+    }
+}
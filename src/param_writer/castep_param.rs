@@ -4,15 +4,33 @@ use std::{
     marker::PhantomData,
 };
 
-use crate::builder_typestate::{No, ToAssign, Yes};
+use crate::{
+    builder_typestate::{No, ToAssign, Yes},
+    parser::param_parser::{ParamParseError, ParamValue},
+};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum FiniteBasisCorr {
     No,
     Manual,
     Auto,
 }
 
+impl FiniteBasisCorr {
+    fn from_param(value: &ParamValue) -> Result<Self, ParamParseError> {
+        match value.as_str().to_lowercase().as_str() {
+            "0" | "none" => Ok(Self::No),
+            "1" | "manual" => Ok(Self::Manual),
+            "2" | "automatic" | "auto" => Ok(Self::Auto),
+            _ => Err(ParamParseError::InvalidValue {
+                keyword: value.keyword().into(),
+                value: value.as_str().into(),
+            }),
+        }
+    }
+}
+
 impl Display for FiniteBasisCorr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -24,14 +42,26 @@ impl Display for FiniteBasisCorr {
 }
 
 /// Trait to limit the type passed to `CastepParam<T>`
-pub trait Task: Default + Display {}
+pub trait Task: Default + Display {
+    /// The `task` keyword written in the `param` file.
+    const TASK_NAME: &'static str;
+    /// Whether the task can relax the cell, which `set_variable_cell` prepares for.
+    const VARIABLE_CELL: bool = false;
+    /// Set the task-specific keyword from a `param` file.
+    /// Returns `Ok(false)` when the keyword is not recognised.
+    fn set_keyword(&mut self, _value: &ParamValue) -> Result<bool, ParamParseError> {
+        Ok(false)
+    }
+}
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Struct to represent a Castep parameter file.
 pub struct CastepParam<T: Task> {
     xc_functional: String,
     spin_polarized: bool,
-    spin: u8,
+    /// The net spin, negative for a net spin down.
+    spin: f64,
     opt_strategy: String,
     page_wvfns: u32,
     cut_off_energy: f64,
@@ -53,9 +83,12 @@ pub struct CastepParam<T: Task> {
     calculate_densdiff: bool,
     pdos_calculate_weights: bool,
     extra_setting: T,
+    unknown_keywords: Vec<(String, String)>,
+    unknown_blocks: Vec<(String, String)>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MetalsMethod {
     DensityMixing(DensityMixing),
     EDFT(EDFT),
@@ -75,6 +108,7 @@ impl Display for MetalsMethod {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DensityMixing {
     mixing_scheme: String,
     mix_charge_amp: f64,
@@ -97,6 +131,21 @@ impl Default for DensityMixing {
     }
 }
 
+impl DensityMixing {
+    fn set_keyword(&mut self, value: &ParamValue) -> Result<bool, ParamParseError> {
+        match value.keyword() {
+            "mixing_scheme" => self.mixing_scheme = value.as_str().into(),
+            "mix_charge_amp" => self.mix_charge_amp = value.as_f64()?,
+            "mix_spin_amp" => self.mix_spin_amp = value.as_f64()?,
+            "mix_charge_gmax" => self.mix_charge_gmax = value.as_f64()?,
+            "mix_spin_gmax" => self.mix_spin_gmax = value.as_f64()?,
+            "mix_history_length" => self.mix_history_length = value.as_u32()?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
 impl Display for DensityMixing {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let output = format!(
@@ -119,10 +168,21 @@ mix_history_length :       {}"#,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EDFT {
     num_occ_cycles: u32,
 }
 
+impl EDFT {
+    fn set_keyword(&mut self, value: &ParamValue) -> Result<bool, ParamParseError> {
+        match value.keyword() {
+            "num_occ_cycles" => self.num_occ_cycles = value.as_u32()?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
 impl Display for EDFT {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -144,6 +204,119 @@ impl<T: Task> CastepParam<T> {
     pub fn build() -> CastepParamBuilder<T, No, No, No> {
         CastepParamBuilder::<T, No, No, No>::new()
     }
+
+    pub fn spin(&self) -> f64 {
+        self.spin
+    }
+
+    pub fn cut_off_energy(&self) -> f64 {
+        self.cut_off_energy
+    }
+
+    pub fn spin_polarized(&self) -> bool {
+        self.spin_polarized
+    }
+
+    pub fn fix_occupancy(&self) -> bool {
+        self.fix_occupancy
+    }
+    /// Width of the Gaussian smearing in eV.
+    pub fn smearing_width(&self) -> f64 {
+        self.smearing_width
+    }
+
+    pub fn extra_setting(&self) -> &T {
+        &self.extra_setting
+    }
+
+    pub fn extra_setting_mut(&mut self) -> &mut T {
+        &mut self.extra_setting
+    }
+
+    pub fn calculate_stress(&self) -> bool {
+        self.calculate_stress
+    }
+    /// Keywords read from a `param` file without a corresponding field.
+    pub fn unknown_keywords(&self) -> &[(String, String)] {
+        self.unknown_keywords.as_ref()
+    }
+    /// Blocks read from a `param` file, as `(name, content)`.
+    pub fn unknown_blocks(&self) -> &[(String, String)] {
+        self.unknown_blocks.as_ref()
+    }
+
+    pub(crate) fn push_unknown_keyword(&mut self, keyword: &str, value: &str) {
+        self.unknown_keywords.push((keyword.into(), value.into()));
+    }
+
+    pub(crate) fn push_unknown_block(&mut self, name: &str, content: &str) {
+        self.unknown_blocks.push((name.into(), content.into()));
+    }
+    /// Set the field matching the lowercase keyword.
+    /// Returns `Ok(false)` when the keyword is not recognised, or not applicable
+    /// to the current `metals_method`.
+    pub(crate) fn set_keyword(&mut self, value: &ParamValue) -> Result<bool, ParamParseError> {
+        match value.keyword() {
+            "xc_functional" => self.xc_functional = value.as_str().into(),
+            "spin_polarized" | "spin_polarised" => self.spin_polarized = value.as_bool()?,
+            "spin" => self.spin = value.as_f64()?,
+            "opt_strategy" => self.opt_strategy = value.as_str().into(),
+            "page_wvfns" => self.page_wvfns = value.as_u32()?,
+            "cut_off_energy" => self.cut_off_energy = value.as_energy()?,
+            "grid_scale" => self.grid_scale = value.as_f64()?,
+            "fine_grid_scale" => self.fine_grid_scale = value.as_f64()?,
+            "finite_basis_corr" => self.finite_basis_corr = FiniteBasisCorr::from_param(value)?,
+            "elec_energy_tol" => self.elec_energy_tol = value.as_energy()?,
+            "max_scf_cycles" => self.max_scf_cycles = value.as_u32()?,
+            "fix_occupancy" => self.fix_occupancy = value.as_bool()?,
+            "metals_method" => {
+                self.metals_method = match value.as_str().to_lowercase().as_str() {
+                    "dm" => MetalsMethod::DensityMixing(DensityMixing::default()),
+                    "edft" => MetalsMethod::EDFT(EDFT::default()),
+                    _ => {
+                        return Err(ParamParseError::InvalidValue {
+                            keyword: value.keyword().into(),
+                            value: value.as_str().into(),
+                        })
+                    }
+                }
+            }
+            "perc_extra_bands" => self.perc_extra_bands = value.as_u32()?,
+            "smearing_width" => self.smearing_width = value.as_energy()?,
+            "spin_fix" => self.spin_fix = value.as_u32()?,
+            "num_dump_cycles" => self.num_dump_cycles = value.as_u32()?,
+            "calculate_elf" => self.calculate_elf = value.as_bool()?,
+            "calculate_stress" => self.calculate_stress = value.as_bool()?,
+            "popn_calculate" => self.popn_calculate = value.as_bool()?,
+            "calculate_hirshfeld" => self.calculate_hirshfeld = value.as_bool()?,
+            "calculate_densdiff" => self.calculate_densdiff = value.as_bool()?,
+            "pdos_calculate_weights" => self.pdos_calculate_weights = value.as_bool()?,
+            _ => {
+                let set_by_metals_method = match &mut self.metals_method {
+                    MetalsMethod::DensityMixing(dm) => dm.set_keyword(value)?,
+                    MetalsMethod::EDFT(edft) => edft.set_keyword(value)?,
+                };
+                return if set_by_metals_method {
+                    Ok(true)
+                } else {
+                    self.extra_setting.set_keyword(value)
+                };
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<T: Task + 'static> CastepParam<T> {
+    /// Calculate the stress with the automatic finite basis set correction, which a
+    /// geometry optimization with a variable cell needs. No effect on tasks without
+    /// `Task::VARIABLE_CELL`.
+    pub fn set_variable_cell(&mut self) {
+        if T::VARIABLE_CELL {
+            self.calculate_stress = true;
+            self.finite_basis_corr = FiniteBasisCorr::Auto;
+        }
+    }
 }
 
 impl From<CastepParam<GeomOptParam>> for CastepParam<BandStructureParam> {
@@ -157,11 +330,15 @@ impl From<CastepParam<GeomOptParam>> for CastepParam<BandStructureParam> {
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Parameters in `Geometry Optimization` only.
 pub struct GeomOptParam {
     geom_energy_tol: f64,
     geom_force_tol: f64,
     geom_stress_tol: f64,
+    /// Estimate of the bulk modulus in GPa, for the initial step of the cell.
+    /// Written only when set, leaving `castep` its own default otherwise.
+    geom_modulus_est: Option<f64>,
     geom_disp_tol: f64,
     geom_max_iter: u32,
     geom_method: String,
@@ -169,7 +346,45 @@ pub struct GeomOptParam {
     popn_bond_cutoff: f64,
 }
 
-impl Task for GeomOptParam {}
+impl Task for GeomOptParam {
+    const TASK_NAME: &'static str = "GeometryOptimization";
+    const VARIABLE_CELL: bool = true;
+
+    fn set_keyword(&mut self, value: &ParamValue) -> Result<bool, ParamParseError> {
+        match value.keyword() {
+            "geom_energy_tol" => self.geom_energy_tol = value.as_energy()?,
+            "geom_force_tol" => self.geom_force_tol = value.as_force()?,
+            "geom_stress_tol" => self.geom_stress_tol = value.as_pressure()?,
+            "geom_modulus_est" => self.geom_modulus_est = Some(value.as_pressure()?),
+            "geom_disp_tol" => self.geom_disp_tol = value.as_length()?,
+            "geom_max_iter" => self.geom_max_iter = value.as_u32()?,
+            "geom_method" => self.geom_method = value.as_str().into(),
+            "fixed_npw" => self.fixed_npw = value.as_bool()?,
+            "popn_bond_cutoff" => self.popn_bond_cutoff = value.as_length()?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl GeomOptParam {
+    /// Tolerance of the stress in GPa for a variable cell.
+    pub fn geom_stress_tol(&self) -> f64 {
+        self.geom_stress_tol
+    }
+
+    pub fn set_geom_stress_tol(&mut self, geom_stress_tol: f64) {
+        self.geom_stress_tol = geom_stress_tol;
+    }
+
+    pub fn geom_modulus_est(&self) -> Option<f64> {
+        self.geom_modulus_est
+    }
+
+    pub fn set_geom_modulus_est(&mut self, geom_modulus_est: Option<f64>) {
+        self.geom_modulus_est = geom_modulus_est;
+    }
+}
 
 impl Default for GeomOptParam {
     fn default() -> Self {
@@ -177,6 +392,7 @@ impl Default for GeomOptParam {
             geom_energy_tol: 5e-5,
             geom_force_tol: 0.1,
             geom_stress_tol: 0.2,
+            geom_modulus_est: None,
             geom_disp_tol: 0.005,
             geom_max_iter: 6000,
             geom_method: "BFGS".into(),
@@ -192,7 +408,7 @@ impl Display for GeomOptParam {
             r#"geom_energy_tol :   {:22.15e}
 geom_force_tol :        {:18.15}
 geom_stress_tol :        {:18.15}
-geom_disp_tol :        {:18.15}
+{}geom_disp_tol :        {:18.15}
 geom_max_iter :     {}
 geom_method : {}
 fixed_npw : {}
@@ -200,6 +416,9 @@ popn_bond_cutoff :        {:18.15}"#,
             self.geom_energy_tol,
             self.geom_force_tol,
             self.geom_stress_tol,
+            self.geom_modulus_est
+                .map(|modulus| format!("geom_modulus_est :        {:18.15}\n", modulus))
+                .unwrap_or_default(),
             self.geom_disp_tol,
             self.geom_max_iter,
             self.geom_method,
@@ -210,6 +429,7 @@ popn_bond_cutoff :        {:18.15}"#,
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Parameters in `Band Structure` task only.
 pub struct BandStructureParam {
     bs_nextra_bands: u32,
@@ -218,7 +438,20 @@ pub struct BandStructureParam {
     bs_write_eigenvalues: bool,
 }
 
-impl Task for BandStructureParam {}
+impl Task for BandStructureParam {
+    const TASK_NAME: &'static str = "BandStructure";
+
+    fn set_keyword(&mut self, value: &ParamValue) -> Result<bool, ParamParseError> {
+        match value.keyword() {
+            "bs_nextra_bands" => self.bs_nextra_bands = value.as_u32()?,
+            "bs_xc_functional" => self.bs_xc_functional = value.as_str().into(),
+            "bs_eigenvalue_tol" => self.bs_eigenvalue_tol = value.as_energy()?,
+            "bs_write_eigenvalues" => self.bs_write_eigenvalues = value.as_bool()?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
 
 impl Default for BandStructureParam {
     fn default() -> Self {
@@ -262,7 +495,7 @@ where
         Self {
             xc_functional: "PBE".into(),
             spin_polarized: true,
-            spin: 0,
+            spin: 0.0,
             opt_strategy: "Speed".into(),
             page_wvfns: 0,
             cut_off_energy: 0.0,
@@ -284,6 +517,8 @@ where
             calculate_densdiff: false,
             pdos_calculate_weights: true,
             extra_setting: T::default(),
+            unknown_keywords: Vec::new(),
+            unknown_blocks: Vec::new(),
         }
     }
 }
@@ -293,14 +528,7 @@ where
     T: Task + 'static,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let task_type_id = TypeId::of::<T>();
-        let task = if task_type_id == TypeId::of::<GeomOptParam>() {
-            "GeometryOptimization"
-        } else if task_type_id == TypeId::of::<BandStructureParam>() {
-            "BandStructure"
-        } else {
-            panic!("Unsupported task type!")
-        };
+        let task = T::TASK_NAME;
         let content = format!(
             r#"task : {}
 comment : CASTEP calculation from Materials Studio
@@ -355,7 +583,13 @@ pdos_calculate_weights : {}
             self.calculate_densdiff,
             self.pdos_calculate_weights
         );
-        write!(f, "{}", content)
+        write!(f, "{}", content)?;
+        self.unknown_keywords
+            .iter()
+            .try_for_each(|(keyword, value)| writeln!(f, "{} : {}", keyword, value))?;
+        self.unknown_blocks.iter().try_for_each(|(name, block)| {
+            writeln!(f, "%BLOCK {}\n{}\n%ENDBLOCK {}", name, block, name)
+        })
     }
 }
 
@@ -369,7 +603,7 @@ where
     EMSet: ToAssign,
 {
     task: T,
-    spin_total: u8,
+    spin_total: f64,
     cut_off_energy: f64,
     metals_method: Option<MetalsMethod>,
     spin_set: PhantomData<SpinSet>,
@@ -388,7 +622,7 @@ where
     pub fn new() -> CastepParamBuilder<T, No, No, E> {
         CastepParamBuilder {
             task: T::default(),
-            spin_total: 0_u8,
+            spin_total: 0.0,
             cut_off_energy: 0.0,
             metals_method: None,
             spin_set: PhantomData,
@@ -396,7 +630,7 @@ where
             electronic_minimizer_set: PhantomData,
         }
     }
-    pub fn with_spin_total(self, spin_total: u8) -> CastepParamBuilder<T, Yes, C, E> {
+    pub fn with_spin_total(self, spin_total: f64) -> CastepParamBuilder<T, Yes, C, E> {
         CastepParamBuilder {
             task: self.task,
             spin_total,
@@ -7,6 +7,7 @@ use std::{
 pub mod castep_param;
 pub mod kpoints;
 pub mod ms_aux_files;
+pub mod pw_input;
 pub mod seed_writer;
 
 pub trait MyFilePath: AsRef<Path> + Into<OsString> + Clone {}
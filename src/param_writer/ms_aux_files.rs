@@ -3,7 +3,9 @@ use std::{
     ffi::OsString,
     fs::{self, create_dir_all},
     io,
+    num::ParseIntError,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use glob::glob;
@@ -195,6 +197,10 @@ impl TrjAux {
         Self { atom_ids }
     }
 
+    pub fn atom_ids(&self) -> &[u32] {
+        self.atom_ids.as_ref()
+    }
+
     pub fn export(&self) -> String {
         {
             let mut trjaux_contents = String::new();
@@ -213,8 +219,24 @@ impl TrjAux {
     }
 }
 
+/// Read back the ids from a `.trjaux` file. Comment lines starting with `#` are skipped.
+impl FromStr for TrjAux {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let atom_ids = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.parse::<u32>())
+            .collect::<Result<Vec<u32>, ParseIntError>>()?;
+        Ok(Self::new(atom_ids))
+    }
+}
+
 /// Scan the generated `msi` files, create a perl script to be run in `Materials Studio`
 /// to save as `xsd` format.
+/// The `xsd` can also be written directly from `LatticeModel<XsdModel>`.
 pub fn to_xsd_scripts(target_root_dir: &str) -> Result<(), Box<dyn Error>> {
     let msi_pattern = format!("{target_root_dir}/**/*.msi");
     let item_collection = glob(&msi_pattern)
@@ -0,0 +1,321 @@
+use std::{collections::HashMap, fmt::Display};
+
+use cpt::{data::ELEMENT_TABLE, element::LookupElement};
+use na::Matrix3;
+
+use crate::{
+    atom::{species_element, visitor::VisitCollection},
+    error::PwInputError,
+    lattice::LatticeModel,
+    model_type::cell::CellModel,
+    units::HARTREE_TO_EV,
+};
+
+use super::castep_param::{CastepParam, GeomOptParam, Task};
+
+#[derive(Debug)]
+/// Struct to present the input file of `pw.x` in Quantum ESPRESSO, written from
+/// the same model and parameters as the `castep` seed files.
+/// - `GeometryOptimization` becomes `relax`, or `vc-relax` when the cell is not fixed.
+///   Other tasks become `scf`.
+/// - `cut_off_energy` becomes `ecutwfc`, and `smearing_width` the Gaussian `degauss`.
+/// - `spin` becomes `tot_magnetization` when `spin_polarized`.
+/// - Each species of `species_set()` is a type. A labelled species is named by the
+///   element and a number, e.g. `Fe:up` becomes `Fe1`.
+/// - The pseudopotential file of a species is `{element}.UPF`, e.g. `Fe.UPF` for
+///   `Fe:up`, unless given by `with_pseudopotential`.
+pub struct PwInput<'a, T>
+where
+    T: Task,
+{
+    cell: &'a LatticeModel<CellModel>,
+    param: &'a CastepParam<T>,
+    vectors: Matrix3<f64>,
+    to_frac: Matrix3<f64>,
+    prefix: &'a str,
+    pseudo_dir: &'a str,
+    pseudopotentials: HashMap<&'a str, &'a str>,
+}
+
+impl<'a, T> PwInput<'a, T>
+where
+    T: Task + 'static,
+{
+    /// The model needs invertible lattice vectors.
+    pub fn new(
+        cell: &'a LatticeModel<CellModel>,
+        param: &'a CastepParam<T>,
+    ) -> Result<Self, PwInputError> {
+        let vectors = cell
+            .lattice_vectors()
+            .ok_or(PwInputError::NotPeriodic)?
+            .vectors()
+            .to_owned();
+        let to_frac = vectors.try_inverse().ok_or(PwInputError::SingularLattice)?;
+        Ok(Self {
+            cell,
+            param,
+            vectors,
+            to_frac,
+            prefix: "pwscf",
+            pseudo_dir: "./",
+            pseudopotentials: HashMap::new(),
+        })
+    }
+
+    pub fn with_prefix(self, prefix: &'a str) -> Self {
+        Self { prefix, ..self }
+    }
+
+    pub fn with_pseudo_dir(self, pseudo_dir: &'a str) -> Self {
+        Self { pseudo_dir, ..self }
+    }
+    /// The pseudopotential file of the species, e.g. `Fe:up`, in place of `{element}.UPF`.
+    pub fn with_pseudopotential(mut self, species: &'a str, file: &'a str) -> Self {
+        self.pseudopotentials.insert(species, file);
+        self
+    }
+    /// The species in the model and their names in `pw.x`, in the order of `species_set()`.
+    fn species_names(&self) -> Vec<(String, String)> {
+        let mut label_counts: HashMap<String, u32> = HashMap::new();
+        self.cell
+            .species_set()
+            .into_iter()
+            .map(|species| {
+                let element = species_element(&species).to_string();
+                let name = if species == element {
+                    element
+                } else {
+                    let count = label_counts.entry(element.clone()).or_insert(0);
+                    *count += 1;
+                    format!("{}{}", element, count)
+                };
+                (species, name)
+            })
+            .collect()
+    }
+
+    fn calculation(&self) -> &str {
+        if T::TASK_NAME != GeomOptParam::TASK_NAME {
+            "scf"
+        } else if self.cell.settings().fix_all_cell() {
+            "relax"
+        } else {
+            "vc-relax"
+        }
+    }
+
+    fn system_namelist(&self) -> String {
+        let rydberg = HARTREE_TO_EV / 2.0;
+        let mut lines = vec![
+            "  ibrav = 0".to_string(),
+            format!("  nat = {}", self.cell.atoms().size()),
+            format!("  ntyp = {}", self.cell.species_set().len()),
+            format!("  ecutwfc = {:.6}", self.param.cut_off_energy() / rydberg),
+        ];
+        if self.param.spin_polarized() {
+            lines.push("  nspin = 2".into());
+            lines.push(format!("  tot_magnetization = {}", self.param.spin()));
+        }
+        if self.param.fix_occupancy() {
+            lines.push("  occupations = 'fixed'".into());
+        } else {
+            lines.push("  occupations = 'smearing'".into());
+            lines.push("  smearing = 'gaussian'".into());
+            lines.push(format!(
+                "  degauss = {:.8}",
+                self.param.smearing_width() / rydberg
+            ));
+        }
+        format!("&SYSTEM\n{}\n/\n", lines.join("\n"))
+    }
+
+    fn atomic_species(&self) -> String {
+        let species: Vec<String> = self
+            .species_names()
+            .iter()
+            .map(|(species, name)| {
+                let element = species_element(species);
+                let mass = ELEMENT_TABLE.get_by_symbol(element).unwrap().mass();
+                let file = self
+                    .pseudopotentials
+                    .get(species.as_str())
+                    .map(|file| file.to_string())
+                    .unwrap_or_else(|| format!("{}.UPF", element));
+                format!("  {:<4}{:12.6}  {}\n", name, mass, file)
+            })
+            .collect();
+        format!("ATOMIC_SPECIES\n{}", species.concat())
+    }
+
+    fn cell_parameters(&self) -> String {
+        let vectors: Vec<String> = self
+            .vectors
+            .column_iter()
+            .map(|col| format!("  {:20.14}{:20.14}{:20.14}\n", col.x, col.y, col.z))
+            .collect();
+        format!("CELL_PARAMETERS angstrom\n{}", vectors.concat())
+    }
+    /// `if_pos` flags are written when any axis is fixed, 0 for a fixed axis.
+    fn atomic_positions(&self) -> String {
+        let atoms = self.cell.atoms();
+        let names: HashMap<String, String> = self.species_names().into_iter().collect();
+        let species = atoms.species();
+        let any_fixed = atoms.fixed_axes().iter().flatten().any(|fixed| *fixed);
+        let positions: Vec<String> = (0..atoms.size())
+            .map(|i| {
+                let frac = atoms.fractional_xyz()[i]
+                    .unwrap_or_else(|| self.to_frac * atoms.xyz_coords()[i]);
+                let if_pos = if any_fixed {
+                    let flags: Vec<&str> = atoms.fixed_axes()[i]
+                        .iter()
+                        .map(|fixed| if *fixed { "0" } else { "1" })
+                        .collect();
+                    format!("  {}", flags.join(" "))
+                } else {
+                    String::new()
+                };
+                format!(
+                    "  {:<4}{:20.16}{:20.16}{:20.16}{}\n",
+                    names[&species[i]], frac.x, frac.y, frac.z, if_pos
+                )
+            })
+            .collect();
+        format!("ATOMIC_POSITIONS crystal\n{}", positions.concat())
+    }
+    /// The Monkhorst-Pack grid, shifted by half a step along the axes with an offset.
+    fn k_points(&self) -> String {
+        let settings = self.cell.settings();
+        let [nx, ny, nz] = settings.kpoints_grid();
+        let shifts: Vec<&str> = settings
+            .kpoints_mp_offset()
+            .iter()
+            .map(|offset| if *offset != 0.0 { "1" } else { "0" })
+            .collect();
+        format!(
+            "K_POINTS automatic\n  {} {} {} {}\n",
+            nx,
+            ny,
+            nz,
+            shifts.join(" ")
+        )
+    }
+}
+
+impl<'a, T> Display for PwInput<'a, T>
+where
+    T: Task + 'static,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let calculation = self.calculation();
+        write!(
+            f,
+            "&CONTROL\n  calculation = '{}'\n  prefix = '{}'\n  pseudo_dir = '{}'\n  outdir = './out'\n/\n",
+            calculation, self.prefix, self.pseudo_dir
+        )?;
+        write!(f, "{}&ELECTRONS\n/\n", self.system_namelist())?;
+        if calculation != "scf" {
+            write!(f, "&IONS\n/\n")?;
+        }
+        if calculation == "vc-relax" {
+            write!(f, "&CELL\n/\n")?;
+        }
+        write!(
+            f,
+            "{}{}{}{}",
+            self.atomic_species(),
+            self.cell_parameters(),
+            self.atomic_positions(),
+            self.k_points()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{
+        error::PwInputError,
+        param_writer::castep_param::{BandStructureParam, CastepParam, GeomOptParam},
+        CellModel, LatticeModel,
+    };
+
+    use super::PwInput;
+
+    const CELL: &str = r#"%BLOCK LATTICE_CART
+   4.0   0.0   0.0
+   0.0   4.0   0.0
+   0.0   0.0   6.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+  Fe   0.0   0.0   0.0
+   O   0.5   0.5   0.5
+%ENDBLOCK POSITIONS_FRAC
+
+kpoints_mp_grid : 4 4 2
+fix_all_cell : false
+"#;
+
+    #[test]
+    fn write_pw_input() {
+        let cell = LatticeModel::<CellModel>::from_str(CELL).unwrap();
+        let param = CastepParam::<GeomOptParam>::build()
+            .with_spin_total(2.0)
+            .with_cut_off_energy(408.170_793_689_82)
+            .set_to_dm()
+            .build();
+        let input = PwInput::new(&cell, &param)
+            .unwrap()
+            .with_prefix("feo")
+            .to_string();
+        assert!(input.contains("calculation = 'vc-relax'"));
+        assert!(input.contains("prefix = 'feo'"));
+        assert!(input.contains("ecutwfc = 30.000000"));
+        assert!(input.contains("tot_magnetization = 2"));
+        assert!(input.contains("smearing = 'gaussian'"));
+        assert!(input.contains("&CELL\n/\n"));
+        assert!(input.contains("  O      15.999000  O.UPF\n"));
+        assert!(input.contains("K_POINTS automatic\n  4 4 2 0 0 0\n"));
+        let positions: Vec<&str> = input
+            .lines()
+            .skip_while(|line| !line.starts_with("ATOMIC_POSITIONS"))
+            .skip(1)
+            .take(2)
+            .collect();
+        assert!(positions[1].starts_with("  O"));
+        let band_param: CastepParam<BandStructureParam> = param.into();
+        let band_input = PwInput::new(&cell, &band_param).unwrap().to_string();
+        assert!(band_input.contains("calculation = 'scf'"));
+        assert!(!band_input.contains("&IONS"));
+        let mut no_lattice = cell.clone();
+        *no_lattice.lattice_vectors_mut() = None;
+        assert!(matches!(
+            PwInput::new(&no_lattice, &band_param),
+            Err(PwInputError::NotPeriodic)
+        ));
+    }
+
+    #[test]
+    fn labelled_species() {
+        let cell = LatticeModel::<CellModel>::from_str(&CELL.replace(
+            "  Fe   0.0   0.0   0.0\n",
+            "  Fe   0.0   0.0   0.0\n  Fe:up   0.5   0.0   0.0\n",
+        ))
+        .unwrap();
+        let param = CastepParam::<GeomOptParam>::build()
+            .with_spin_total(0.0)
+            .with_cut_off_energy(408.170_793_689_82)
+            .set_to_dm()
+            .build();
+        let input = PwInput::new(&cell, &param)
+            .unwrap()
+            .with_pseudopotential("Fe:up", "Fe.pbe-spn-rrkjus_psl.1.0.0.UPF")
+            .to_string();
+        assert!(input.contains("ntyp = 3"));
+        assert!(input.contains("  Fe     55.847000  Fe.UPF\n"));
+        assert!(input.contains("  Fe1    55.847000  Fe.pbe-spn-rrkjus_psl.1.0.0.UPF\n"));
+        assert!(input.contains("\n  Fe1   0.5000000000000000"));
+    }
+}
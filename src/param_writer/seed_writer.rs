@@ -6,8 +6,6 @@ use std::{
     path::PathBuf,
 };
 
-use cpt::{data::ELEMENT_TABLE, element::LookupElement};
-
 use crate::{
     atom::visitor::VisitCollection,
     builder_typestate::{No, ToAssign, Yes},
@@ -58,17 +56,23 @@ where
         let filename = format!("{}{}", self.seed_name, extension);
         Ok(export_loc.join(filename))
     }
-    /// Copy the potential files for the elements in the cell to the seed folder.
+    /// Copy the potential files for the species in the cell to the seed folder.
     /// It is suggest to do this only in release version. Because the potential files
     /// take up much disk space.
     /// You can control this behaviour with `[cfg(not(debug_assertions))]`
+    /// Species with OTFG strings in `Settings` have no file to copy.
     pub fn copy_potentials(&self) -> Result<(), io::Error> {
-        let element_list = self.cell.element_set();
-        element_list
+        let species_list = self.cell.species_set();
+        species_list
             .iter()
-            .try_for_each(|elm| -> Result<(), io::Error> {
-                let pot_file = ELEMENT_TABLE.get_by_symbol(elm).unwrap().potential();
-                let pot_src_path = self.potential_loc.join(pot_file);
+            .filter_map(|species| {
+                let potential = self.cell.settings().species_potential(species);
+                potential
+                    .file()
+                    .map(|file| (potential.to_string(), file.to_string()))
+            })
+            .try_for_each(|(pot_file, pot_src)| -> Result<(), io::Error> {
+                let pot_src_path = self.potential_loc.join(pot_src);
                 let dest_dir = self.create_export_dir()?;
                 let pot_dest_path = dest_dir.join(pot_file);
                 if !pot_dest_path.exists() {
@@ -309,7 +313,7 @@ where
     T: Task + 'static,
 {
     pub fn build(self) -> SeedWriter<'a, T> {
-        let param = CastepParam::<T>::build()
+        let mut param = CastepParam::<T>::build()
             .with_spin_total(self.cell.spin_total())
             .with_cut_off_energy(
                 self.cell
@@ -318,6 +322,9 @@ where
             )
             .set_to_dm()
             .build();
+        if !self.cell.settings().fix_all_cell() {
+            param.set_variable_cell();
+        }
         let Self {
             cell,
             param: _,
@@ -335,7 +342,7 @@ where
         }
     }
     pub fn build_edft(self) -> SeedWriter<'a, T> {
-        let param = CastepParam::<T>::build()
+        let mut param = CastepParam::<T>::build()
             .with_spin_total(self.cell.spin_total())
             .with_cut_off_energy(
                 self.cell
@@ -344,6 +351,9 @@ where
             )
             .set_to_edft()
             .build();
+        if !self.cell.settings().fix_all_cell() {
+            param.set_variable_cell();
+        }
         let Self {
             cell,
             param: _,
@@ -0,0 +1,20 @@
+use std::{error::Error, fmt::Display};
+
+#[derive(Debug)]
+/// Error type when parsing a `castep` output file.
+pub enum CastepOutputError {
+    /// Neither the unit cell nor any SCF cycle is found in the input.
+    NoCalculationData,
+}
+
+impl Display for CastepOutputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CastepOutputError::NoCalculationData => {
+                write!(f, "No calculation data found in the castep output!")
+            }
+        }
+    }
+}
+
+impl Error for CastepOutputError {}
@@ -0,0 +1,616 @@
+use std::str::FromStr;
+
+use nalgebra::{Matrix3, Point3, Vector3};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till1},
+    character::complete::{alphanumeric1, char, one_of, space0, space1},
+    combinator::{all_consuming, map, map_res, opt},
+    multi::many1,
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    IResult,
+};
+
+use crate::{
+    atom::AtomCollectionBuilder,
+    builder_typestate::No,
+    lattice::{LatticeModel, LatticeVectors},
+    model_type::Settings,
+    parser::{decimal, lookup_species, number},
+    CellModel,
+};
+
+pub use self::error::CastepOutputError;
+
+mod error;
+
+#[derive(Debug, Clone, Default)]
+/// Results extracted from a `castep` output file.
+/// Every field is optional, since the job may have crashed before
+/// writing it. When a quantity is printed several times (e.g. during a
+/// geometry optimization), the last complete occurrence is kept.
+pub struct CastepOutput {
+    /// `Final energy, E` in eV.
+    final_energy: Option<f64>,
+    /// `Final Enthalpy` of the geometry optimization in eV.
+    final_enthalpy: Option<f64>,
+    /// SCF iterations, grouped by each electronic minimisation.
+    scf_cycles: Vec<Vec<ScfIteration>>,
+    /// Iterations of the geometry optimization.
+    geom_iterations: Vec<GeomIteration>,
+    /// Forces in eV/Å, in the order of the atoms in the cell.
+    forces: Option<Vec<Vector3<f64>>>,
+    /// Stress tensor in GPa.
+    stress: Option<Matrix3<f64>>,
+    /// Pressure in GPa.
+    pressure: Option<f64>,
+    /// Real lattice in Å, vectors as columns.
+    lattice_vectors: Option<Matrix3<f64>>,
+    /// Species and fractional coordinates of the atoms.
+    positions: Option<Vec<(String, Point3<f64>)>>,
+    /// Lines of warnings.
+    warnings: Vec<String>,
+    /// Whether the run reaches the end and reports the total time.
+    finished: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A line in the SCF table.
+pub struct ScfIteration {
+    /// `None` for the `Initial` line.
+    iteration: Option<u32>,
+    energy: f64,
+    fermi_energy: Option<f64>,
+    energy_gain: Option<f64>,
+    timer: f64,
+}
+
+impl ScfIteration {
+    /// The energy is always the first and the timer the last column.
+    fn new(iteration: Option<u32>, values: &[f64], has_fermi: bool) -> Self {
+        let energy = values[0];
+        let timer = values[values.len() - 1];
+        let (fermi_energy, energy_gain) = if has_fermi {
+            (
+                (values.len() >= 3).then(|| values[1]),
+                (values.len() >= 4).then(|| values[2]),
+            )
+        } else {
+            (None, (values.len() >= 3).then(|| values[1]))
+        };
+        Self {
+            iteration,
+            energy,
+            fermi_energy,
+            energy_gain,
+            timer,
+        }
+    }
+
+    pub fn iteration(&self) -> Option<u32> {
+        self.iteration
+    }
+
+    pub fn energy(&self) -> f64 {
+        self.energy
+    }
+
+    pub fn fermi_energy(&self) -> Option<f64> {
+        self.fermi_energy
+    }
+
+    pub fn energy_gain(&self) -> Option<f64> {
+        self.energy_gain
+    }
+
+    pub fn timer(&self) -> f64 {
+        self.timer
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// One iteration of the geometry optimization.
+pub struct GeomIteration {
+    iteration: u32,
+    enthalpy: f64,
+    convergence: Vec<ConvergenceCriterion>,
+}
+
+impl GeomIteration {
+    pub fn iteration(&self) -> u32 {
+        self.iteration
+    }
+
+    pub fn enthalpy(&self) -> f64 {
+        self.enthalpy
+    }
+
+    pub fn convergence(&self) -> &[ConvergenceCriterion] {
+        self.convergence.as_ref()
+    }
+    /// All the criteria in the table are satisfied.
+    pub fn is_converged(&self) -> bool {
+        !self.convergence.is_empty() && self.convergence.iter().all(|c| c.converged)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A row in the convergence table of geometry optimization.
+/// # Example:
+/// `|  dE/ion   |   1.094488E-004 |   2.000000E-005 |         eV | No  | <-- BFGS`
+pub struct ConvergenceCriterion {
+    parameter: String,
+    value: f64,
+    tolerance: f64,
+    unit: String,
+    converged: bool,
+}
+
+impl ConvergenceCriterion {
+    pub fn parameter(&self) -> &str {
+        self.parameter.as_ref()
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    pub fn unit(&self) -> &str {
+        self.unit.as_ref()
+    }
+
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+}
+
+/// `Initial  -8.50337173E+002  0.00000000E+000    1.41  <-- SCF`
+/// `      1  -8.60011498E+002  5.05447744E+000   4.83716245E-001   2.04  <-- SCF`
+/// An iteration number overflowing `u32` does not match.
+fn scf_line(line: &str) -> IResult<&str, (Option<u32>, Vec<f64>)> {
+    terminated(
+        tuple((
+            alt((
+                map(tag("Initial"), |_| None),
+                map_res(decimal, |num: &str| num.parse::<u32>().map(Some)),
+            )),
+            many1(preceded(space1, number)),
+        )),
+        tuple((space1, tag("<-- SCF"))),
+    )(line)
+}
+
+/// `BFGS: finished iteration     1 with enthalpy= -8.60122379E+002 eV`
+/// An iteration number overflowing `u32` does not match.
+fn geom_iteration_line(line: &str) -> IResult<&str, (u32, f64)> {
+    let (rest, (_, _, iteration, _, enthalpy)) = tuple((
+        alphanumeric1,
+        tag(": finished iteration"),
+        preceded(space1, map_res(decimal, |num: &str| num.parse::<u32>())),
+        tuple((space1, tag("with enthalpy="), space0)),
+        number,
+    ))(line)?;
+    Ok((rest, (iteration, enthalpy)))
+}
+
+/// The parameter name may contain `|` (e.g. `|F|max`), so the row is split
+/// from the right side.
+fn convergence_line(line: &str) -> Option<ConvergenceCriterion> {
+    let body = line.strip_prefix('|')?;
+    let body = body.split("<--").next()?.trim_end().strip_suffix('|')?;
+    let mut fields = body.rsplitn(5, '|');
+    let converged = fields.next()?.trim();
+    let unit = fields.next()?.trim();
+    let (_, tolerance) = all_consuming(delimited(space0, number, space0))(fields.next()?).ok()?;
+    let (_, value) = all_consuming(delimited(space0, number, space0))(fields.next()?).ok()?;
+    let parameter = fields.next()?.trim();
+    Some(ConvergenceCriterion {
+        parameter: parameter.into(),
+        value,
+        tolerance,
+        unit: unit.into(),
+        converged: converged.eq_ignore_ascii_case("yes"),
+    })
+}
+
+/// `Final energy, E             =  -8.600120716024E+002 eV`
+/// or `Final energy =  -8.600120716024E+002 eV` in older versions.
+fn final_energy_line(line: &str) -> IResult<&str, f64> {
+    preceded(
+        tuple((
+            tag("Final energy"),
+            opt(tag(", E")),
+            space0,
+            char('='),
+            space0,
+        )),
+        number,
+    )(line)
+}
+
+/// `BFGS: Final Enthalpy     = -8.60125017E+002 eV`
+fn final_enthalpy_line(line: &str) -> IResult<&str, f64> {
+    preceded(
+        tuple((
+            alphanumeric1,
+            tag(": Final Enthalpy"),
+            space0,
+            char('='),
+            space0,
+        )),
+        number,
+    )(line)
+}
+
+/// A coordinate component in the forces table, which may be
+/// followed by `(cons'd)` when the direction is constrained.
+fn force_component(input: &str) -> IResult<&str, f64> {
+    terminated(
+        preceded(space1, number),
+        opt(preceded(space1, tag("(cons'd)"))),
+    )(input)
+}
+
+/// `* Si              1     -0.00000          0.00000         -0.00000         *`
+fn force_line(line: &str) -> IResult<&str, Vector3<f64>> {
+    let (rest, (_, _, (x, y, z))) = tuple((
+        pair(char('*'), space1),
+        tuple((take_till1(|c: char| c.is_whitespace()), space1, decimal)),
+        tuple((force_component, force_component, force_component)),
+    ))(line)?;
+    Ok((rest, Vector3::new(x, y, z)))
+}
+
+/// `*  x     -0.078203      0.000000      0.000000  *`
+fn stress_line(line: &str) -> IResult<&str, Vector3<f64>> {
+    let (rest, (x, y, z)) = preceded(
+        tuple((char('*'), space1, one_of("xyz"))),
+        tuple((
+            preceded(space1, number),
+            preceded(space1, number),
+            preceded(space1, number),
+        )),
+    )(line)?;
+    Ok((rest, Vector3::new(x, y, z)))
+}
+
+/// `*  Pressure:   -0.0177          *`
+fn pressure_line(line: &str) -> IResult<&str, f64> {
+    preceded(tuple((char('*'), space1, tag("Pressure:"), space0)), number)(line)
+}
+
+/// `2.7150000     2.7150000     0.0000000        1.157124366   1.157124366  -1.157124366`
+fn real_lattice_line(line: &str) -> IResult<&str, Vector3<f64>> {
+    let (rest, (x, y, z)) =
+        tuple((number, preceded(space1, number), preceded(space1, number)))(line)?;
+    Ok((rest, Vector3::new(x, y, z)))
+}
+
+/// `x  Si           1         0.000000   0.000000   0.000000   x`
+fn position_line(line: &str) -> IResult<&str, (&str, Point3<f64>)> {
+    let (rest, (species, _, x, y, z)) = preceded(
+        pair(char('x'), space1),
+        tuple((
+            take_till1(|c: char| c.is_whitespace()),
+            preceded(space1, decimal),
+            preceded(space1, number),
+            preceded(space1, number),
+            preceded(space1, number),
+        )),
+    )(line)?;
+    Ok((rest, (species, Point3::new(x, y, z))))
+}
+
+/// The line consists of only the given char, e.g. the borders of the tables.
+fn is_border(line: &str, border: char) -> bool {
+    line.len() > 1 && line.chars().all(|c| c == border)
+}
+
+/// Table of forces, until the border of `*`.
+/// Returns `None` when the table is truncated.
+fn parse_forces<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Option<Vec<Vector3<f64>>> {
+    let mut forces = Vec::new();
+    for line in lines.map(str::trim) {
+        if let Ok((_, force)) = force_line(line) {
+            forces.push(force);
+        } else if is_border(line, '*') {
+            return Some(forces);
+        }
+    }
+    None
+}
+
+/// Table of stress tensor, until the border of `*`.
+/// Returns `None` when the table is truncated.
+fn parse_stress<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+) -> Option<(Matrix3<f64>, Option<f64>)> {
+    let mut rows = Vec::with_capacity(3);
+    let mut pressure = None;
+    for line in lines.map(str::trim) {
+        if let Ok((_, row)) = stress_line(line) {
+            rows.push(row.transpose());
+        } else if let Ok((_, value)) = pressure_line(line) {
+            pressure = Some(value);
+        } else if is_border(line, '*') {
+            return (rows.len() == 3).then(|| (Matrix3::from_rows(&rows), pressure));
+        }
+    }
+    None
+}
+
+/// Three lines following the `Real Lattice(A)` header.
+/// Returns `None` when the lattice is truncated.
+fn parse_real_lattice<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Option<Matrix3<f64>> {
+    let vectors = lines
+        .take(3)
+        .map(|line| real_lattice_line(line.trim()).ok().map(|(_, vec)| vec))
+        .collect::<Option<Vec<Vector3<f64>>>>()?;
+    (vectors.len() == 3).then(|| Matrix3::from_columns(&vectors))
+}
+
+/// Table of fractional coordinates, until the border of `x`.
+/// Returns `None` when the table is truncated.
+fn parse_positions<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+) -> Option<Vec<(String, Point3<f64>)>> {
+    let mut positions = Vec::new();
+    for line in lines.map(str::trim) {
+        if let Ok((_, (species, frac))) = position_line(line) {
+            positions.push((species.to_string(), frac));
+        } else if is_border(line, 'x') {
+            return Some(positions);
+        }
+    }
+    None
+}
+
+impl FromStr for CastepOutput {
+    type Err = CastepOutputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut output = CastepOutput::default();
+        // Whether the SCF table has the column of Fermi energy.
+        let mut scf_has_fermi = false;
+        let mut lines = s.lines();
+        while let Some(line) = lines.next() {
+            let line = line.trim();
+            if let Ok((_, (iteration, values))) = scf_line(line) {
+                let scf_iteration = ScfIteration::new(iteration, &values, scf_has_fermi);
+                match output.scf_cycles.last_mut() {
+                    Some(cycle) if iteration.is_some() => cycle.push(scf_iteration),
+                    _ => output.scf_cycles.push(vec![scf_iteration]),
+                }
+            } else if line.starts_with("SCF loop") {
+                scf_has_fermi = line.contains("Fermi");
+            } else if let Ok((_, (iteration, enthalpy))) = geom_iteration_line(line) {
+                output.geom_iterations.push(GeomIteration {
+                    iteration,
+                    enthalpy,
+                    convergence: Vec::new(),
+                });
+            } else if let Some(criterion) = convergence_line(line) {
+                if let Some(geom_iteration) = output.geom_iterations.last_mut() {
+                    geom_iteration.convergence.push(criterion);
+                }
+            } else if let Ok((_, energy)) = final_energy_line(line) {
+                output.final_energy = Some(energy);
+            } else if let Ok((_, enthalpy)) = final_enthalpy_line(line) {
+                output.final_enthalpy = Some(enthalpy);
+            } else if line.starts_with('*') && line.contains(" Forces ") {
+                if let Some(forces) = parse_forces(&mut lines) {
+                    output.forces = Some(forces);
+                }
+            } else if line.starts_with('*') && line.contains("Stress Tensor") {
+                if let Some((stress, pressure)) = parse_stress(&mut lines) {
+                    output.stress = Some(stress);
+                    output.pressure = pressure;
+                }
+            } else if line.starts_with("Real Lattice") {
+                if let Some(lattice) = parse_real_lattice(&mut lines) {
+                    output.lattice_vectors = Some(lattice);
+                }
+            } else if line.contains("Fractional coordinates of atoms") {
+                if let Some(positions) = parse_positions(&mut lines) {
+                    output.positions = Some(positions);
+                }
+            } else if line.to_lowercase().contains("warning") {
+                output.warnings.push(
+                    line.trim_matches(|c: char| c == '*' || c.is_whitespace())
+                        .into(),
+                );
+            } else if line.starts_with("Total time") {
+                output.finished = true;
+            }
+        }
+        if output.lattice_vectors.is_none() && output.scf_cycles.is_empty() {
+            Err(CastepOutputError::NoCalculationData)
+        } else {
+            Ok(output)
+        }
+    }
+}
+
+impl CastepOutput {
+    pub fn final_energy(&self) -> Option<f64> {
+        self.final_energy
+    }
+
+    pub fn final_enthalpy(&self) -> Option<f64> {
+        self.final_enthalpy
+    }
+
+    pub fn scf_cycles(&self) -> &[Vec<ScfIteration>] {
+        self.scf_cycles.as_ref()
+    }
+
+    pub fn geom_iterations(&self) -> &[GeomIteration] {
+        self.geom_iterations.as_ref()
+    }
+
+    pub fn forces(&self) -> Option<&[Vector3<f64>]> {
+        self.forces.as_deref()
+    }
+
+    pub fn stress(&self) -> Option<&Matrix3<f64>> {
+        self.stress.as_ref()
+    }
+
+    pub fn pressure(&self) -> Option<f64> {
+        self.pressure
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        self.warnings.as_ref()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+    /// Build the model from the last complete lattice and positions.
+    /// Atom ids are assigned by the order in the output, starting from 1.
+    /// Returns `None` when either of them is missing, or a species is
+    /// not found in the periodic table.
+    pub fn final_model(&self) -> Option<LatticeModel<CellModel>> {
+        let vectors = self.lattice_vectors?;
+        let positions = self.positions.as_ref()?;
+        let num_atom = positions.len();
+        let (element_symbols, atomic_nums): (Vec<String>, Vec<u8>) = positions
+            .iter()
+            .map(|(species, _)| lookup_species(species))
+            .collect::<Option<Vec<(String, u8)>>>()?
+            .into_iter()
+            .unzip();
+        let xyz_coords: Vec<Point3<f64>> =
+            positions.iter().map(|(_, frac)| vectors * frac).collect();
+        let frac_xyz: Vec<Option<Point3<f64>>> =
+            positions.iter().map(|(_, frac)| Some(*frac)).collect();
+        let atom_ids: Vec<u32> = (1..=num_atom as u32).collect();
+        let atoms = AtomCollectionBuilder::<CellModel, No>::new(num_atom)
+            .with_element_symbols(&element_symbols)
+            .unwrap()
+            .with_atomic_nums(&atomic_nums)
+            .unwrap()
+            .with_xyz_coords(&xyz_coords)
+            .unwrap()
+            .with_fractional_xyz(&frac_xyz)
+            .unwrap()
+            .with_atom_ids(&atom_ids)
+            .unwrap()
+            .finish()
+            .unwrap()
+            .build();
+        Some(LatticeModel::new(
+            Some(LatticeVectors::new(vectors)),
+            atoms,
+            Settings::default(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::{geom_iteration_line, scf_line, CastepOutput};
+
+    /// A geometry optimization which crashed in the middle of the forces table.
+    const TRUNCATED: &str = r#"
+                           -------------------------------
+                                      Unit Cell
+                           -------------------------------
+        Real Lattice(A)              Reciprocal Lattice(1/A)
+     2.7150000     2.7150000     0.0000000        1.157124366   1.157124366  -1.157124366
+     2.7150000     0.0000000     2.7150000        1.157124366  -1.157124366   1.157124366
+     0.0000000     2.7150000     2.7150000       -1.157124366   1.157124366   1.157124366
+
+            xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx
+            x  Element    Atom        Fractional coordinates of atoms  x
+            x            Number           u          v          w      x
+            x----------------------------------------------------------x
+            x  Si           1         0.000000   0.000000   0.000000   x
+            x  Si           2         0.250000   0.250000   0.250000   x
+            xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx
+
+ WARNING - the k-point set is not symmetric
+
+------------------------------------------------------------------------ <-- SCF
+SCF loop      Energy           Fermi           Energy gain       Timer   <-- SCF
+                               energy          per atom          (sec)
+------------------------------------------------------------------------ <-- SCF
+Initial  -8.50337173E+002  0.00000000E+000                         1.41  <-- SCF
+      1  -8.60011498E+002  5.05447744E+000   4.83716245E-001       2.04  <-- SCF
+      2  -8.60012071E+002  5.05440000E+000   2.86500000E-005       2.61  <-- SCF
+------------------------------------------------------------------------ <-- SCF
+
+Final energy, E             =  -8.600120716024E+002 eV
+
+ ***************** Stress Tensor *****************
+ *                                               *
+ *          Cartesian components (GPa)           *
+ * --------------------------------------------- *
+ *             x             y             z     *
+ *                                               *
+ *  x     -0.078203      0.000000      0.000000  *
+ *  y      0.000000     -0.078203      0.000000  *
+ *  z      0.000000      0.000000      0.103443  *
+ *                                               *
+ *  Pressure:   -0.0177                          *
+ *                                               *
+ *************************************************
+
+ BFGS: finished iteration     1 with enthalpy= -8.60122379E+002 eV
+
+ +-----------+-----------------+-----------------+------------+-----+ <-- BFGS
+ | Parameter |      value      |    tolerance    |    units   | OK? | <-- BFGS
+ +-----------+-----------------+-----------------+------------+-----+ <-- BFGS
+ |  dE/ion   |   1.094488E-004 |   2.000000E-005 |         eV | No  | <-- BFGS
+ |  |F|max   |   8.632410E-003 |   5.000000E-002 |       eV/A | Yes | <-- BFGS
+ +-----------+-----------------+-----------------+------------+-----+ <-- BFGS
+
+ ******************************** Forces *********************************
+ *                                                                       *
+ *                           Cartesian components (eV/A)                 *
+ * --------------------------------------------------------------------- *
+ *                         x                    y                    z   *
+ *                                                                       *
+ * Si              1      0.01000              0.00000             -0.00000  *
+"#;
+
+    #[test]
+    fn overflowing_iteration() {
+        let line = "BFGS: finished iteration 99999999999 with enthalpy= -8.60122379E+002 eV";
+        assert!(geom_iteration_line(line).is_err());
+        let (_, (iteration, _)) = geom_iteration_line(&line.replace("99999999999", "2")).unwrap();
+        assert_eq!(iteration, 2);
+        let scf = "99999999999  -8.60011498E+002  5.05447744E+000   2.04  <-- SCF";
+        assert!(scf_line(scf).is_err());
+        let (_, (iteration, _)) = scf_line(&scf.replace("99999999999", "1")).unwrap();
+        assert_eq!(iteration, Some(1));
+    }
+
+    #[test]
+    fn parse_truncated_output() {
+        let output = CastepOutput::from_str(TRUNCATED).unwrap();
+        assert!(!output.finished());
+        assert_eq!(output.final_energy(), Some(-8.600120716024E+002));
+        assert_eq!(output.scf_cycles().len(), 1);
+        assert_eq!(output.scf_cycles()[0].len(), 3);
+        assert_eq!(output.scf_cycles()[0][2].energy_gain(), Some(2.865e-5));
+        assert_eq!(output.geom_iterations().len(), 1);
+        assert_eq!(output.geom_iterations()[0].convergence().len(), 2);
+        assert!(!output.geom_iterations()[0].is_converged());
+        assert_eq!(output.pressure(), Some(-0.0177));
+        assert!((output.stress().unwrap()[(2, 2)] - 0.103443).abs() < 1e-10);
+        // The forces table is not complete.
+        assert!(output.forces().is_none());
+        assert_eq!(output.warnings().len(), 1);
+        let model = output.final_model().unwrap();
+        assert_eq!(model.atoms().size(), 2);
+        assert!((model.atoms().xyz_coords()[1].x - 1.3575).abs() < 1e-10);
+    }
+}
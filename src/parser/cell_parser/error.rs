@@ -0,0 +1,51 @@
+use std::{error::Error, fmt::Display};
+
+use crate::error::CellConstraintsError;
+
+#[derive(Debug)]
+/// Error type when parsing a `cell` file.
+pub enum CellParseError {
+    /// A required block is not found in the file.
+    MissingBlock(String),
+    /// A `%BLOCK` without its matching `%ENDBLOCK`.
+    UnclosedBlock(String),
+    /// A line inside the block does not follow the expected format.
+    InvalidBlockLine { block: String, line: String },
+    /// The value of a keyword can not be parsed.
+    InvalidKeyword { keyword: String, value: String },
+    /// The species does not match any element in the periodic table.
+    UnknownElement(String),
+    /// The unit is not supported.
+    UnknownUnit(String),
+    /// The lattice vectors are linearly dependent.
+    SingularLattice,
+    /// `CELL_CONSTRAINTS` is invalid, or conflicts with `FIX_ALL_CELL`.
+    InvalidCellConstraints(CellConstraintsError),
+}
+
+impl Display for CellParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CellParseError::MissingBlock(block) => write!(f, "Block {} is not found.", block),
+            CellParseError::UnclosedBlock(block) => {
+                write!(f, "Block {} is not closed by %ENDBLOCK.", block)
+            }
+            CellParseError::InvalidBlockLine { block, line } => {
+                write!(f, "Invalid line in block {}: \"{}\"", block, line)
+            }
+            CellParseError::InvalidKeyword { keyword, value } => {
+                write!(f, "Invalid value for keyword {}: \"{}\"", keyword, value)
+            }
+            CellParseError::UnknownElement(symbol) => write!(f, "Unknown element: {}", symbol),
+            CellParseError::UnknownUnit(unit) => write!(f, "Unsupported unit: {}", unit),
+            CellParseError::SingularLattice => {
+                write!(f, "The lattice vectors are not independent!")
+            }
+            CellParseError::InvalidCellConstraints(err) => {
+                write!(f, "Invalid CELL_CONSTRAINTS: {}", err)
+            }
+        }
+    }
+}
+
+impl Error for CellParseError {}
@@ -0,0 +1,908 @@
+use std::{collections::HashMap, str::FromStr};
+
+use nalgebra::{Matrix3, Point3, Vector3};
+use nom::{
+    bytes::complete::take_till1,
+    character::complete::{alpha1, alphanumeric0, anychar, char, digit1, one_of, space0, space1},
+    combinator::{all_consuming, map_opt, map_res, opt, recognize},
+    multi::many1,
+    sequence::{pair, preceded, terminated, tuple},
+    IResult,
+};
+
+use crate::{
+    atom::{species_name, AtomCollection, AtomCollectionBuilder},
+    builder_typestate::No,
+    lattice::{LatticeModel, LatticeVectors},
+    model_type::{
+        cell_constraints::CellConstraints,
+        hubbard::{insert_hubbard_value, HubbardOrbital, HubbardValue},
+        pseudopotential::Pseudopotential,
+        Settings,
+    },
+    parser::{
+        block_end, block_start, decimal, keyword_line, lookup_species, number, strip_comment,
+    },
+    units::{energy_to_ev, length_to_ang, mass_to_amu},
+    CellModel,
+};
+
+pub use self::error::CellParseError;
+
+mod error;
+
+impl FromStr for LatticeModel<CellModel> {
+    type Err = CellParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let content = CellContent::new(s)?;
+        let lattice_vectors = content.parse_lattice()?;
+        let atoms = content.parse_atoms(&lattice_vectors)?;
+        let settings = content.parse_settings()?;
+        Ok(LatticeModel::new(Some(lattice_vectors), atoms, settings))
+    }
+}
+
+/// The content of a `cell` file, sorted into blocks and keywords.
+/// Comments and blank lines have been removed. Block names and keywords
+/// are stored in upper case since `castep` treats them case-insensitively.
+#[derive(Debug)]
+struct CellContent<'a> {
+    blocks: HashMap<String, Vec<&'a str>>,
+    keywords: HashMap<String, &'a str>,
+}
+
+/// A single word as the units line in a block, e.g. `ang` or `bohr`.
+fn units_line(line: &str) -> IResult<&str, &str> {
+    all_consuming(recognize(pair(alpha1, alphanumeric0)))(line)
+}
+
+/// A line of three numbers.
+fn vector_line(line: &str) -> IResult<&str, Vector3<f64>> {
+    let (rest, (x, y, z)) = all_consuming(terminated(
+        tuple((number, preceded(space1, number), preceded(space1, number))),
+        space0,
+    ))(line)?;
+    Ok((rest, Vector3::new(x, y, z)))
+}
+
+/// A line in the positions blocks: species followed by the coordinate.
+/// The trailing settings of the atom (e.g. `SPIN=2.0`) are returned untouched.
+fn position_line(line: &str) -> IResult<&str, (&str, Point3<f64>)> {
+    let (rest, (species, x, y, z)) = tuple((
+        take_till1(|c: char| c.is_whitespace()),
+        preceded(space1, number),
+        preceded(space1, number),
+        preceded(space1, number),
+    ))(line)?;
+    Ok((rest, (species, Point3::new(x, y, z))))
+}
+
+/// The `SPIN=` setting among the trailing settings of an atom, e.g. `SPIN=-2.0`
+/// or `spin : 2`. `None` if the atom has no `SPIN` setting.
+fn spin_setting(settings: &str) -> Option<IResult<&str, f64>> {
+    let start = settings.to_ascii_lowercase().find("spin")?;
+    Some(preceded(tuple((space0, one_of("=:"), space0)), number)(
+        &settings[start + 4..],
+    ))
+}
+
+/// A line in `IONIC_CONSTRAINTS`: the constraint index, species, index of the ion
+/// within its species, and the constrained direction.
+fn constraint_line(line: &str) -> IResult<&str, (&str, usize, Vector3<f64>)> {
+    let (rest, (_, species, ion_index, direction)) = tuple((
+        decimal,
+        preceded(space1, take_till1(|c: char| c.is_whitespace())),
+        preceded(space1, map_res(decimal, |num: &str| num.parse::<usize>())),
+        preceded(space1, vector_line),
+    ))(line)?;
+    Ok((rest, (species, ion_index, direction)))
+}
+
+/// The U or alpha values of a species on its orbitals, e.g. `Fe:1 d: 2.5 p: 0.5`.
+/// The optional ion number restricting the values to one ion of the species
+/// is returned as the second entry.
+type HubbardLine<'a> = (&'a str, Option<u32>, Vec<(HubbardOrbital, f64)>);
+
+/// A line in `HUBBARD_U` or `HUBBARD_ALPHA`: species, the optional ion number,
+/// and `orbital: value` pairs.
+fn hubbard_line(line: &str) -> IResult<&str, HubbardLine<'_>> {
+    all_consuming(terminated(
+        tuple((
+            take_till1(|c: char| c.is_whitespace()),
+            opt(preceded(
+                space1,
+                map_res(digit1, |num: &str| num.parse::<u32>()),
+            )),
+            many1(preceded(
+                space1,
+                pair(
+                    terminated(
+                        map_opt(anychar, HubbardOrbital::from_letter),
+                        pair(char(':'), space0),
+                    ),
+                    number,
+                ),
+            )),
+        )),
+        space0,
+    ))(line)
+}
+
+/// The element symbol, atomic number and label of a species, e.g. `fe:up`
+/// gives `("Fe", 26, Some("up"))`.
+fn parse_species(species: &str) -> Result<(String, u8, Option<String>), CellParseError> {
+    let (symbol, atomic_num) =
+        lookup_species(species).ok_or_else(|| CellParseError::UnknownElement(species.into()))?;
+    let label = species.split_once(':').map(|(_, label)| label.to_string());
+    Ok((symbol, atomic_num, label))
+}
+
+/// The species name with the element normalized, e.g. `Fe:up` for `fe:up`.
+fn normalize_species(species: &str) -> Result<String, CellParseError> {
+    let (symbol, _, label) = parse_species(species)?;
+    Ok(species_name(&symbol, label.as_deref()))
+}
+
+fn parse_bool(keyword: &str, value: &str) -> Result<bool, CellParseError> {
+    match value.to_lowercase().as_str() {
+        "" | "true" | "t" => Ok(true),
+        "false" | "f" => Ok(false),
+        _ => Err(CellParseError::InvalidKeyword {
+            keyword: keyword.into(),
+            value: value.into(),
+        }),
+    }
+}
+
+impl<'a> CellContent<'a> {
+    /// Sort the lines of the input into blocks and keywords.
+    fn new(input: &'a str) -> Result<Self, CellParseError> {
+        let mut blocks = HashMap::new();
+        let mut keywords = HashMap::new();
+        let mut lines = input
+            .lines()
+            .map(|line| strip_comment(line).trim())
+            .filter(|line| !line.is_empty());
+        while let Some(line) = lines.next() {
+            if let Ok((_, block_name)) = block_start(line) {
+                let block_name = block_name.to_uppercase();
+                let mut block_lines = Vec::new();
+                loop {
+                    match lines.next() {
+                        Some(block_line) => match block_end(block_line) {
+                            Ok((_, end_name)) if end_name.eq_ignore_ascii_case(&block_name) => {
+                                break
+                            }
+                            _ => block_lines.push(block_line),
+                        },
+                        None => return Err(CellParseError::UnclosedBlock(block_name)),
+                    }
+                }
+                blocks.insert(block_name, block_lines);
+            } else if let Ok((_, (keyword, value))) = keyword_line(line) {
+                keywords.insert(keyword.to_uppercase(), value);
+            }
+        }
+        Ok(Self { blocks, keywords })
+    }
+    /// Get the block by one of its accepted names.
+    fn block(&self, names: &[&str]) -> Option<&[&'a str]> {
+        names
+            .iter()
+            .find_map(|name| self.blocks.get(*name))
+            .map(|lines| lines.as_slice())
+    }
+    /// Get the value of a keyword by one of its accepted names.
+    fn keyword(&self, names: &[&'static str]) -> Option<(&'static str, &'a str)> {
+        names
+            .iter()
+            .find_map(|name| self.keywords.get(*name).map(|value| (*name, *value)))
+    }
+    /// Split the optional units line at the beginning of a block.
+    /// Returns the conversion factor to Å and the remaining lines.
+    fn block_length_unit<'b>(
+        block_name: &str,
+        lines: &'b [&'a str],
+    ) -> Result<(f64, &'b [&'a str]), CellParseError> {
+        match lines.split_first() {
+            Some((first, remains)) if units_line(first).is_ok() => {
+                let factor = length_to_ang(first)
+                    .ok_or_else(|| CellParseError::UnknownUnit(first.to_string()))?;
+                Ok((factor, remains))
+            }
+            Some(_) => Ok((1.0, lines)),
+            None => Err(CellParseError::MissingBlock(block_name.into())),
+        }
+    }
+    /// Split the lines of a species block into the normalized species and the entry.
+    fn species_entries(
+        block_name: &str,
+        lines: &[&'a str],
+    ) -> Result<Vec<(String, &'a str)>, CellParseError> {
+        lines
+            .iter()
+            .map(|line| {
+                let (species, entry) = line
+                    .split_once(char::is_whitespace)
+                    .map(|(species, entry)| (species, entry.trim()))
+                    .filter(|(_, entry)| !entry.is_empty())
+                    .ok_or_else(|| CellParseError::InvalidBlockLine {
+                        block: block_name.into(),
+                        line: line.to_string(),
+                    })?;
+                Ok((normalize_species(species)?, entry))
+            })
+            .collect()
+    }
+    /// Parse every line of the block as a vector of three numbers.
+    fn block_vectors(
+        block_name: &str,
+        lines: &[&str],
+    ) -> Result<Vec<Vector3<f64>>, CellParseError> {
+        lines
+            .iter()
+            .map(|line| {
+                vector_line(line).map(|(_, vector)| vector).map_err(|_| {
+                    CellParseError::InvalidBlockLine {
+                        block: block_name.into(),
+                        line: line.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+    /// Parse either `LATTICE_CART` or `LATTICE_ABC`.
+    fn parse_lattice(&self) -> Result<LatticeVectors<CellModel>, CellParseError> {
+        if let Some(lines) = self.block(&["LATTICE_CART"]) {
+            let (factor, lines) = Self::block_length_unit("LATTICE_CART", lines)?;
+            let vectors = Self::block_vectors("LATTICE_CART", lines)?;
+            if vectors.len() != 3 {
+                return Err(CellParseError::MissingBlock("LATTICE_CART".into()));
+            }
+            Ok(LatticeVectors::new(
+                Matrix3::from_columns(&vectors) * factor,
+            ))
+        } else if let Some(lines) = self.block(&["LATTICE_ABC"]) {
+            let (factor, lines) = Self::block_length_unit("LATTICE_ABC", lines)?;
+            let params = Self::block_vectors("LATTICE_ABC", lines)?;
+            if params.len() != 2 {
+                return Err(CellParseError::MissingBlock("LATTICE_ABC".into()));
+            }
+            let lengths = params[0] * factor;
+            let angles = params[1];
+            Ok(LatticeVectors::from_lattice_params(
+                [lengths.x, lengths.y, lengths.z],
+                [angles.x, angles.y, angles.z],
+            ))
+        } else {
+            Err(CellParseError::MissingBlock(
+                "LATTICE_CART or LATTICE_ABC".into(),
+            ))
+        }
+    }
+    /// Parse either `POSITIONS_FRAC` or `POSITIONS_ABS`.
+    /// Both the cartesian and fractional coordinates are filled.
+    /// Atom ids are assigned by the order of appearance, starting from 1.
+    /// Atoms without `SPIN=` have no initial magnetic moment, as in `castep`.
+    fn parse_atoms(
+        &self,
+        lattice_vectors: &LatticeVectors<CellModel>,
+    ) -> Result<AtomCollection<CellModel>, CellParseError> {
+        let to_cart = lattice_vectors.vectors();
+        let to_frac = to_cart
+            .try_inverse()
+            .ok_or(CellParseError::SingularLattice)?;
+        let (block_name, lines, is_frac, factor) =
+            if let Some(lines) = self.block(&["POSITIONS_FRAC"]) {
+                ("POSITIONS_FRAC", lines, true, 1.0)
+            } else if let Some(lines) = self.block(&["POSITIONS_ABS"]) {
+                let (factor, lines) = Self::block_length_unit("POSITIONS_ABS", lines)?;
+                ("POSITIONS_ABS", lines, false, factor)
+            } else {
+                return Err(CellParseError::MissingBlock(
+                    "POSITIONS_FRAC or POSITIONS_ABS".into(),
+                ));
+            };
+        let num_atom = lines.len();
+        let mut element_symbols: Vec<String> = Vec::with_capacity(num_atom);
+        let mut atomic_nums: Vec<u8> = Vec::with_capacity(num_atom);
+        let mut species_labels: Vec<Option<String>> = Vec::with_capacity(num_atom);
+        let mut xyz_coords: Vec<Point3<f64>> = Vec::with_capacity(num_atom);
+        let mut frac_xyz: Vec<Option<Point3<f64>>> = Vec::with_capacity(num_atom);
+        let mut magnetic_moments: Vec<f64> = Vec::with_capacity(num_atom);
+        lines
+            .iter()
+            .try_for_each(|line| -> Result<(), CellParseError> {
+                let invalid_line = || CellParseError::InvalidBlockLine {
+                    block: block_name.into(),
+                    line: line.to_string(),
+                };
+                let (settings, (species, coord)) =
+                    position_line(line).map_err(|_| invalid_line())?;
+                let (symbol, atomic_num, label) = parse_species(species)?;
+                let spin = match spin_setting(settings) {
+                    Some(Ok((_, spin))) => spin,
+                    Some(Err(_)) => return Err(invalid_line()),
+                    None => 0.0,
+                };
+                element_symbols.push(symbol);
+                atomic_nums.push(atomic_num);
+                species_labels.push(label);
+                magnetic_moments.push(spin);
+                if is_frac {
+                    xyz_coords.push(to_cart * coord);
+                    frac_xyz.push(Some(coord));
+                } else {
+                    let xyz = coord * factor;
+                    xyz_coords.push(xyz);
+                    frac_xyz.push(Some(to_frac * xyz));
+                }
+                Ok(())
+            })?;
+        let atom_ids: Vec<u32> = (1..=num_atom as u32).collect();
+        let species: Vec<String> = element_symbols
+            .iter()
+            .zip(species_labels.iter())
+            .map(|(symbol, label)| species_name(symbol, label.as_deref()))
+            .collect();
+        let fixed_axes = self.parse_fixed_axes(&species)?;
+        let builder = AtomCollectionBuilder::<CellModel, No>::new(num_atom);
+        Ok(builder
+            .with_element_symbols(&element_symbols)
+            .unwrap()
+            .with_atomic_nums(&atomic_nums)
+            .unwrap()
+            .with_species_labels(&species_labels)
+            .unwrap()
+            .with_xyz_coords(&xyz_coords)
+            .unwrap()
+            .with_fractional_xyz(&frac_xyz)
+            .unwrap()
+            .with_atom_ids(&atom_ids)
+            .unwrap()
+            .with_fixed_axes(&fixed_axes)
+            .unwrap()
+            .with_magnetic_moments(&magnetic_moments)
+            .unwrap()
+            .finish()
+            .unwrap()
+            .build())
+    }
+    /// The fixed axes of atoms from `IONIC_CONSTRAINTS`. The ions are matched by
+    /// the species and their order within the species.
+    /// Constraints not along a cartesian axis are ignored.
+    fn parse_fixed_axes(&self, species: &[String]) -> Result<Vec<[bool; 3]>, CellParseError> {
+        let mut fixed_axes = vec![[false; 3]; species.len()];
+        let Some(lines) = self.block(&["IONIC_CONSTRAINTS"]) else {
+            return Ok(fixed_axes);
+        };
+        lines
+            .iter()
+            .try_for_each(|line| -> Result<(), CellParseError> {
+                let invalid_line = || CellParseError::InvalidBlockLine {
+                    block: "IONIC_CONSTRAINTS".into(),
+                    line: line.to_string(),
+                };
+                let (_, (ion_species, ion_index, direction)) =
+                    constraint_line(line).map_err(|_| invalid_line())?;
+                let ion_species = normalize_species(ion_species)?;
+                let nth_in_species = ion_index.checked_sub(1).ok_or_else(invalid_line)?;
+                let atom_index = species
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, name)| **name == ion_species)
+                    .nth(nth_in_species)
+                    .map(|(i, _)| i)
+                    .ok_or_else(invalid_line)?;
+                let nonzero_axes: Vec<usize> =
+                    (0..3).filter(|&axis| direction[axis] != 0.0).collect();
+                if let [axis] = nonzero_axes[..] {
+                    fixed_axes[atom_index][axis] = true;
+                }
+                Ok(())
+            })?;
+        Ok(fixed_axes)
+    }
+    /// Parse the k-points, the constraint settings, the species blocks and the U values.
+    /// Missing entries are left as the defaults of `Settings`.
+    fn parse_settings(&self) -> Result<Settings<CellModel>, CellParseError> {
+        let mut settings = Settings::<CellModel>::default();
+        let invalid_keyword = |keyword: &str, value: &str| CellParseError::InvalidKeyword {
+            keyword: keyword.into(),
+            value: value.into(),
+        };
+        if let Some(lines) = self.block(&["KPOINTS_LIST", "KPOINT_LIST"]) {
+            let kpoints_list = lines
+                .iter()
+                .map(|line| {
+                    let (_, values) = all_consuming(terminated(
+                        tuple((
+                            number,
+                            preceded(space1, number),
+                            preceded(space1, number),
+                            preceded(space1, number),
+                        )),
+                        space0,
+                    ))(line)
+                    .map_err(|_| CellParseError::InvalidBlockLine {
+                        block: "KPOINTS_LIST".into(),
+                        line: line.to_string(),
+                    })?;
+                    let (x, y, z, weight) = values;
+                    Ok([x, y, z, weight])
+                })
+                .collect::<Result<Vec<[f64; 4]>, CellParseError>>()?;
+            settings.set_kpoints_list(kpoints_list);
+        }
+        if let Some((keyword, value)) = self.keyword(&["KPOINTS_MP_GRID", "KPOINT_MP_GRID"]) {
+            let grid: Vec<u8> = value
+                .split_whitespace()
+                .map(|num| num.parse::<u8>())
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| invalid_keyword(keyword, value))?;
+            let grid: [u8; 3] = grid
+                .try_into()
+                .map_err(|_| invalid_keyword(keyword, value))?;
+            settings.set_kpoints_grid(grid);
+        }
+        if let Some((keyword, value)) = self.keyword(&["KPOINTS_MP_SPACING", "KPOINT_MP_SPACING"]) {
+            let (_, spacing) = number(value).map_err(|_| invalid_keyword(keyword, value))?;
+            settings.set_kpoints_mp_spacing(Some(spacing));
+        }
+        if let Some((keyword, value)) = self.keyword(&["KPOINTS_MP_OFFSET", "KPOINT_MP_OFFSET"]) {
+            let (_, offset) = vector_line(value).map_err(|_| invalid_keyword(keyword, value))?;
+            settings.set_kpoints_mp_offset([offset.x, offset.y, offset.z]);
+        }
+        let fix_all_cell = match self.keyword(&["FIX_ALL_CELL"]) {
+            Some((keyword, value)) => Some(parse_bool(keyword, value)?),
+            None => None,
+        };
+        if let Some(fix_all_cell) = fix_all_cell {
+            settings
+                .set_fix_all_cell(fix_all_cell)
+                .map_err(CellParseError::InvalidCellConstraints)?;
+        }
+        if let Some(lines) = self.block(&["CELL_CONSTRAINTS"]) {
+            let invalid_block = || CellParseError::InvalidBlockLine {
+                block: "CELL_CONSTRAINTS".into(),
+                line: lines.join(" "),
+            };
+            let indices: Vec<u8> = lines
+                .iter()
+                .flat_map(|line| line.split_whitespace())
+                .map(|num| num.parse::<u8>())
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| invalid_block())?;
+            let [a, b, c, alpha, beta, gamma]: [u8; 6] =
+                indices.try_into().map_err(|_| invalid_block())?;
+            let constraints = CellConstraints::new([a, b, c], [alpha, beta, gamma])
+                .map_err(CellParseError::InvalidCellConstraints)?;
+            // Without `FIX_ALL_CELL`, the block relaxing the cell implies it is false.
+            if fix_all_cell.is_none() && !constraints.is_all_fixed() {
+                settings
+                    .set_fix_all_cell(false)
+                    .map_err(CellParseError::InvalidCellConstraints)?;
+            }
+            settings
+                .set_cell_constraints(Some(constraints))
+                .map_err(CellParseError::InvalidCellConstraints)?;
+        }
+        if let Some((keyword, value)) = self.keyword(&["FIX_COM"]) {
+            settings.set_fix_com(parse_bool(keyword, value)?);
+        }
+        if let Some(lines) = self.block(&["EXTERNAL_EFIELD"]) {
+            // Skip the optional units line, the field is kept as written.
+            let lines = match lines.split_first() {
+                Some((first, remains)) if units_line(first).is_ok() => remains,
+                _ => lines,
+            };
+            let efield = Self::block_vectors("EXTERNAL_EFIELD", lines)?;
+            let efield = efield
+                .first()
+                .ok_or_else(|| CellParseError::MissingBlock("EXTERNAL_EFIELD".into()))?;
+            settings.set_external_efield([efield.x, efield.y, efield.z]);
+        }
+        if let Some(lines) = self.block(&["EXTERNAL_PRESSURE"]) {
+            let lines = match lines.split_first() {
+                Some((first, remains)) if units_line(first).is_ok() => remains,
+                _ => lines,
+            };
+            // Upper triangle of the tensor, spreading over three lines.
+            let pressure: Vec<f64> = lines
+                .iter()
+                .flat_map(|line| line.split_whitespace())
+                .map(|num| num.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| CellParseError::InvalidBlockLine {
+                    block: "EXTERNAL_PRESSURE".into(),
+                    line: lines.join(" "),
+                })?;
+            let pressure: [f64; 6] =
+                pressure
+                    .try_into()
+                    .map_err(|_| CellParseError::InvalidBlockLine {
+                        block: "EXTERNAL_PRESSURE".into(),
+                        line: lines.join(" "),
+                    })?;
+            settings.set_external_pressure(pressure);
+        }
+        if let Some(lines) = self.block(&["SPECIES_POT"]) {
+            Self::species_entries("SPECIES_POT", lines)?
+                .into_iter()
+                .for_each(|(species, entry)| {
+                    settings.set_species_potential(&species, Pseudopotential::from_entry(entry))
+                });
+        }
+        if let Some(lines) = self.block(&["SPECIES_MASS"]) {
+            let (factor, lines) = match lines.split_first() {
+                Some((first, remains)) if units_line(first).is_ok() => {
+                    let factor = mass_to_amu(first)
+                        .ok_or_else(|| CellParseError::UnknownUnit(first.to_string()))?;
+                    (factor, remains)
+                }
+                _ => (1.0, lines),
+            };
+            Self::species_entries("SPECIES_MASS", lines)?
+                .into_iter()
+                .try_for_each(|(species, entry)| -> Result<(), CellParseError> {
+                    let mass =
+                        entry
+                            .parse::<f64>()
+                            .map_err(|_| CellParseError::InvalidBlockLine {
+                                block: "SPECIES_MASS".into(),
+                                line: format!("{} {}", species, entry),
+                            })?;
+                    settings.set_species_mass(&species, mass * factor);
+                    Ok(())
+                })?;
+        }
+        if let Some(lines) = self.block(&["SPECIES_LCAO_STATES"]) {
+            Self::species_entries("SPECIES_LCAO_STATES", lines)?
+                .into_iter()
+                .try_for_each(|(species, entry)| -> Result<(), CellParseError> {
+                    let lcao_state =
+                        entry
+                            .parse::<u8>()
+                            .map_err(|_| CellParseError::InvalidBlockLine {
+                                block: "SPECIES_LCAO_STATES".into(),
+                                line: format!("{} {}", species, entry),
+                            })?;
+                    settings.set_species_lcao_state(&species, lcao_state);
+                    Ok(())
+                })?;
+        }
+        settings.set_hubbard_u(self.hubbard_values("HUBBARD_U")?);
+        settings.set_hubbard_alpha(self.hubbard_values("HUBBARD_ALPHA")?);
+        Ok(settings)
+    }
+    /// The values in `HUBBARD_U` or `HUBBARD_ALPHA` converted to eV, with an
+    /// optional energy unit line. Empty without the block.
+    fn hubbard_values(&self, block_name: &str) -> Result<Vec<HubbardValue>, CellParseError> {
+        let Some(lines) = self.block(&[block_name]) else {
+            return Ok(Vec::new());
+        };
+        let (factor, lines) = match lines.split_first() {
+            Some((first, remains)) if units_line(first).is_ok() => {
+                let factor = energy_to_ev(first)
+                    .ok_or_else(|| CellParseError::UnknownUnit(first.to_string()))?;
+                (factor, remains)
+            }
+            _ => (1.0, lines),
+        };
+        let mut hubbard_values: Vec<HubbardValue> = Vec::new();
+        lines
+            .iter()
+            .try_for_each(|line| -> Result<(), CellParseError> {
+                let (_, (species, ion, values)) =
+                    hubbard_line(line).map_err(|_| CellParseError::InvalidBlockLine {
+                        block: block_name.into(),
+                        line: line.to_string(),
+                    })?;
+                let species = normalize_species(species)?;
+                values.into_iter().for_each(|(orbital, value)| {
+                    let value = value * factor;
+                    let hubbard_value = match ion {
+                        Some(ion) => HubbardValue::new_on_ion(&species, ion, orbital, value),
+                        None => HubbardValue::new(&species, orbital, value),
+                    };
+                    insert_hubbard_value(&mut hubbard_values, hubbard_value)
+                });
+                Ok(())
+            })?;
+        Ok(hubbard_values)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, str::FromStr};
+
+    use crate::{
+        atom::visitor::VisitCollection,
+        error::CellConstraintsError,
+        model_type::{
+            cell_constraints::CellConstraints, hubbard::HubbardOrbital,
+            pseudopotential::Pseudopotential, DefaultExport,
+        },
+        units::{BOHR_TO_ANG, ELECTRON_MASS_TO_AMU, HARTREE_TO_EV},
+        CellModel, LatticeModel,
+    };
+
+    use super::{CellContent, CellParseError};
+
+    const CELL_ABC: &str = r#"! A hand-written cell
+%block lattice_abc
+ang
+  4.0 4.0 6.0   # a b c
+  90 90 120
+%endblock lattice_abc
+
+%BLOCK Positions_Abs
+bohr
+  fe  0.0 0.0 0.0
+  O:1 1.0 0.0 0.0 SPIN=0.0
+%ENDBLOCK Positions_Abs
+
+kpoints_mp_grid : 2 2 1
+FIX_ALL_CELL = false
+fix_com true
+"#;
+
+    #[test]
+    fn parse_hand_written_cell() {
+        let model = LatticeModel::<CellModel>::from_str(CELL_ABC).unwrap();
+        let vectors = model.lattice_vectors().unwrap().vectors();
+        assert!((vectors.column(1).norm() - 4.0).abs() < 1e-10);
+        assert!((vectors.column(0).angle(&vectors.column(1)).to_degrees() - 120.0).abs() < 1e-10);
+        assert_eq!(model.atoms().element_symbols(), &["Fe", "O"]);
+        assert!((model.atoms().xyz_coords()[1].x - BOHR_TO_ANG).abs() < 1e-10);
+        assert_eq!(model.settings().kpoints_grid(), [2, 2, 1]);
+        assert!(!model.settings().fix_all_cell());
+        assert!(model.settings().fix_com());
+    }
+
+    #[test]
+    fn cell_round_trip() {
+        let model = LatticeModel::<CellModel>::from_str(CELL_ABC).unwrap();
+        let exported = model.export();
+        let parsed_back = LatticeModel::<CellModel>::from_str(&exported).unwrap();
+        model
+            .atoms()
+            .xyz_coords()
+            .iter()
+            .zip(parsed_back.atoms().xyz_coords())
+            .for_each(|(a, b)| assert!((a - b).norm() < 1e-10));
+        assert_eq!(
+            model.settings().kpoints_list(),
+            parsed_back.settings().kpoints_list()
+        );
+    }
+
+    #[test]
+    fn species_potentials() {
+        let cell = r#"%BLOCK LATTICE_CART
+  4.0 0.0 0.0
+  0.0 4.0 0.0
+  0.0 0.0 4.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+  Fe 0.0 0.0 0.0
+   O 0.5 0.5 0.5
+%ENDBLOCK POSITIONS_FRAC
+
+%BLOCK SPECIES_POT
+  fe  3|2.1|10|12|13|30U:40:31:32(qc=5.5)
+  O   O_custom.usp
+%ENDBLOCK SPECIES_POT
+"#;
+        let model = LatticeModel::<CellModel>::from_str(cell).unwrap();
+        assert_eq!(
+            model.settings().species_potential("Fe"),
+            Pseudopotential::Otfg("3|2.1|10|12|13|30U:40:31:32(qc=5.5)".into())
+        );
+        assert_eq!(
+            model.settings().species_potential("O"),
+            Pseudopotential::File("O_custom.usp".into())
+        );
+        let potentials_loc = std::env::temp_dir().join("castep_model_core_potentials");
+        fs::create_dir_all(&potentials_loc).unwrap();
+        fs::write(
+            potentials_loc.join("O_custom.usp"),
+            "  O custom\n    400 FINE\n",
+        )
+        .unwrap();
+        // The OTFG string of Fe has no file to read.
+        let cutoff = model
+            .get_final_cutoff_energy(potentials_loc.to_str().unwrap())
+            .unwrap();
+        assert_eq!(cutoff, 440.0);
+        let mut model = model;
+        fs::write(potentials_loc.join("O_no_fine.usp"), "  O custom\n").unwrap();
+        model
+            .settings_mut()
+            .set_species_potential("O", Pseudopotential::File("O_no_fine.usp".into()));
+        let error = model
+            .get_final_cutoff_energy(potentials_loc.to_str().unwrap())
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        model
+            .settings_mut()
+            .set_species_potential("O", Pseudopotential::File("O_missing.usp".into()));
+        let error = model
+            .get_final_cutoff_energy(potentials_loc.to_str().unwrap())
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn cell_constraints() {
+        let slab = CellConstraints::new([1, 1, 0], [0, 0, 0]).unwrap();
+        let block = "%BLOCK CELL_CONSTRAINTS\n  1 1 0\n  0 0 0\n%ENDBLOCK CELL_CONSTRAINTS\n";
+        let parse = |input: &str| CellContent::new(input).unwrap().parse_settings();
+        let settings = parse(&format!("FIX_ALL_CELL : false\n{}", block)).unwrap();
+        assert!(!settings.fix_all_cell());
+        assert_eq!(settings.cell_constraints(), Some(&slab));
+        // The block relaxing the cell implies `FIX_ALL_CELL : false`.
+        let settings = parse(block).unwrap();
+        assert!(!settings.fix_all_cell());
+        assert_eq!(settings.cell_constraints(), Some(&slab));
+        let all_fixed = "%BLOCK CELL_CONSTRAINTS\n  0 0 0\n  0 0 0\n%ENDBLOCK CELL_CONSTRAINTS\n";
+        assert!(parse(all_fixed).unwrap().fix_all_cell());
+        assert!(matches!(
+            parse(&format!("FIX_ALL_CELL : true\n{}", block)),
+            Err(CellParseError::InvalidCellConstraints(
+                CellConstraintsError::ConflictWithFixAllCell
+            ))
+        ));
+        let tied = "%BLOCK CELL_CONSTRAINTS\n  1 1 0\n  0 0 1\n%ENDBLOCK CELL_CONSTRAINTS\n";
+        assert!(matches!(
+            parse(tied),
+            Err(CellParseError::InvalidCellConstraints(
+                CellConstraintsError::TiedLengthAndAngle(1)
+            ))
+        ));
+    }
+
+    #[test]
+    fn labelled_species() {
+        let cell = r#"%BLOCK LATTICE_CART
+  4.0 0.0 0.0
+  0.0 4.0 0.0
+  0.0 0.0 4.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+  fe    0.0 0.0 0.0
+  fe:up 0.5 0.5 0.5
+  H:D   0.0 0.5 0.0
+  O:1   0.5 0.0 0.0
+%ENDBLOCK POSITIONS_FRAC
+
+%BLOCK IONIC_CONSTRAINTS
+  1  Fe:up  1  1.0 0.0 0.0
+  2  Fe:up  1  0.0 1.0 0.0
+  3  Fe:up  1  0.0 0.0 1.0
+%ENDBLOCK IONIC_CONSTRAINTS
+
+%BLOCK SPECIES_MASS
+me
+  H:D 3671.5
+%ENDBLOCK SPECIES_MASS
+
+%BLOCK SPECIES_LCAO_STATES
+  Fe:up 4
+%ENDBLOCK SPECIES_LCAO_STATES
+"#;
+        let model = LatticeModel::<CellModel>::from_str(cell).unwrap();
+        assert_eq!(model.atoms().species(), &["Fe", "Fe:up", "H:D", "O:1"]);
+        assert_eq!(model.atoms().element_symbols(), &["Fe", "Fe", "H", "O"]);
+        assert_eq!(model.species_set(), &["H:D", "O:1", "Fe", "Fe:up"]);
+        assert_eq!(model.element_set(), &["H", "O", "Fe"]);
+        assert!(
+            (model.settings().species_mass("H:D") - 3671.5 * ELECTRON_MASS_TO_AMU).abs() < 1e-10
+        );
+        assert_eq!(model.settings().species_lcao_state("Fe:up"), 4);
+        assert_eq!(
+            model.atoms().fixed_axes(),
+            &[[false; 3], [true; 3], [false; 3], [false; 3]]
+        );
+    }
+
+    #[test]
+    fn magnetic_moments() {
+        let cell = r#"%BLOCK LATTICE_CART
+  4.0 0.0 0.0
+  0.0 4.0 0.0
+  0.0 0.0 8.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+  Fe 0.0 0.0 0.0 SPIN=2.5
+  Fe 0.5 0.5 0.5 spin : -2.5
+   O 0.5 0.5 0.0 SPIN=0.0
+   O 0.0 0.0 0.5
+%ENDBLOCK POSITIONS_FRAC
+"#;
+        let model = LatticeModel::<CellModel>::from_str(cell).unwrap();
+        assert_eq!(model.atoms().magnetic_moments(), &[2.5, -2.5, 0.0, 0.0]);
+        assert_eq!(model.spin_total(), 0.0);
+    }
+
+    #[test]
+    fn hubbard_u() {
+        let content = CellContent::new(
+            "%BLOCK HUBBARD_U\nHa\n  fe d: 0.1 f:0.2\n  Fe 2 d: 1.0\n  O p: 0.05\n%ENDBLOCK HUBBARD_U\n",
+        )
+        .unwrap();
+        let settings = content.parse_settings().unwrap();
+        assert_eq!(settings.hubbard_u().len(), 4);
+        assert!(settings.hubbard_alpha().is_empty());
+        let in_ev =
+            |value: Option<f64>, ha: f64| (value.unwrap() - ha * HARTREE_TO_EV).abs() < 1e-10;
+        assert!(in_ev(
+            settings.species_hubbard_u("Fe", HubbardOrbital::D),
+            0.1
+        ));
+        assert!(in_ev(
+            settings.species_hubbard_u("Fe", HubbardOrbital::F),
+            0.2
+        ));
+        assert!(in_ev(
+            settings.ion_hubbard_u("Fe", 2, HubbardOrbital::D),
+            1.0
+        ));
+        assert!(in_ev(
+            settings.species_hubbard_u("O", HubbardOrbital::P),
+            0.05
+        ));
+        assert_eq!(settings.ion_hubbard_u("Fe", 1, HubbardOrbital::D), None);
+    }
+
+    #[test]
+    fn hubbard_alpha() {
+        let content = CellContent::new(
+            "%BLOCK HUBBARD_ALPHA\n  Ni 1 d: 0.1\n  Ni d: 0.05\n%ENDBLOCK HUBBARD_ALPHA\n",
+        )
+        .unwrap();
+        let settings = content.parse_settings().unwrap();
+        assert!(settings.hubbard_u().is_empty());
+        assert_eq!(
+            settings.ion_hubbard_alpha("Ni", 1, HubbardOrbital::D),
+            Some(0.1)
+        );
+        assert_eq!(
+            settings.species_hubbard_alpha("Ni", HubbardOrbital::D),
+            Some(0.05)
+        );
+        let content = CellContent::new(
+            "%BLOCK HUBBARD_ALPHA\n  Ni 99999999999 d: 0.1\n%ENDBLOCK HUBBARD_ALPHA\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            content.parse_settings(),
+            Err(CellParseError::InvalidBlockLine { block, .. }) if block == "HUBBARD_ALPHA"
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let model = LatticeModel::<CellModel>::from_str(CELL_ABC).unwrap();
+        let json = serde_json::to_string(&model).unwrap();
+        let parsed_back: LatticeModel<CellModel> = serde_json::from_str(&json).unwrap();
+        let diff = model.lattice_vectors().unwrap().vectors()
+            - parsed_back.lattice_vectors().unwrap().vectors();
+        assert!(diff.norm() < 1e-10);
+        assert_eq!(
+            model.atoms().element_symbols(),
+            parsed_back.atoms().element_symbols()
+        );
+        assert_eq!(
+            model.settings().kpoints_grid(),
+            parsed_back.settings().kpoints_grid()
+        );
+        assert!(parsed_back.settings().fix_com());
+    }
+}
@@ -0,0 +1,51 @@
+use std::{error::Error, fmt::Display};
+
+#[derive(Debug)]
+/// Error type when parsing a `cif` file.
+pub enum CifParseError {
+    /// No `data_` block is found in the file.
+    NoDataBlock,
+    /// A text field starting with `;` is not closed.
+    UnterminatedTextField,
+    /// A quoted value is not closed on the same line.
+    UnterminatedQuote(String),
+    /// The number of values in a `loop_` is not a multiple of its tags.
+    InvalidLoop(String),
+    /// A required tag is not found in the data block.
+    MissingTag(String),
+    /// The value of a tag can not be parsed as a number.
+    InvalidNumber { tag: String, value: String },
+    /// The operation in `_symmetry_equiv_pos_as_xyz` can not be parsed.
+    InvalidSymmetryOperation(String),
+    /// The species does not match any element in the periodic table.
+    UnknownElement(String),
+}
+
+impl Display for CifParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CifParseError::NoDataBlock => write!(f, "No data block is found in the cif."),
+            CifParseError::UnterminatedTextField => {
+                write!(f, "Text field is not closed by a line starting with ';'.")
+            }
+            CifParseError::UnterminatedQuote(line) => {
+                write!(f, "Unterminated quote in line: \"{}\"", line)
+            }
+            CifParseError::InvalidLoop(tag) => write!(
+                f,
+                "The number of values in the loop of {} does not match its tags.",
+                tag
+            ),
+            CifParseError::MissingTag(tag) => write!(f, "Tag {} is not found.", tag),
+            CifParseError::InvalidNumber { tag, value } => {
+                write!(f, "Invalid number for tag {}: \"{}\"", tag, value)
+            }
+            CifParseError::InvalidSymmetryOperation(op) => {
+                write!(f, "Invalid symmetry operation: \"{}\"", op)
+            }
+            CifParseError::UnknownElement(symbol) => write!(f, "Unknown element: {}", symbol),
+        }
+    }
+}
+
+impl Error for CifParseError {}
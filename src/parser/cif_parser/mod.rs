@@ -0,0 +1,525 @@
+use std::{collections::HashMap, str::FromStr};
+
+use nalgebra::Point3;
+use nom::combinator::all_consuming;
+
+use crate::{
+    atom::{AtomCollection, AtomCollectionBuilder},
+    builder_typestate::No,
+    lattice::{
+        symmetry::{wrap_fractional, SymmetryOperation},
+        LatticeModel, LatticeVectors,
+    },
+    model_type::Settings,
+    parser::{number, species_from_label},
+    CellModel,
+};
+
+pub use self::error::CifParseError;
+
+mod error;
+
+/// Default distance (Å) within which symmetry images are merged into one site.
+pub const DEFAULT_SITE_TOLERANCE: f64 = 0.05;
+
+#[derive(Debug, Clone)]
+/// A crystal structure imported from a `cif` file.
+/// The asymmetric unit has been expanded by the symmetry operations into a full
+/// `LatticeModel<CellModel>`.
+pub struct CifStructure {
+    model: LatticeModel<CellModel>,
+    space_group: Option<String>,
+    partial_occupancies: Vec<PartialOccupancy>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A site in the asymmetric unit with `_atom_site_occupancy` less than 1.
+/// The site is still included in the expanded model.
+pub struct PartialOccupancy {
+    label: String,
+    element: String,
+    occupancy: f64,
+}
+
+impl PartialOccupancy {
+    pub fn label(&self) -> &str {
+        self.label.as_ref()
+    }
+
+    pub fn element(&self) -> &str {
+        self.element.as_ref()
+    }
+
+    pub fn occupancy(&self) -> f64 {
+        self.occupancy
+    }
+}
+
+impl CifStructure {
+    /// Parse the first data block, merging symmetry images closer than `tolerance` (Å).
+    pub fn parse_with_tolerance(s: &str, tolerance: f64) -> Result<Self, CifParseError> {
+        let block = CifBlock::new(s)?;
+        let lattice_vectors = block.parse_lattice()?;
+        let symmetry_operations = block.parse_symmetry_operations()?;
+        let sites = block.parse_sites()?;
+        let partial_occupancies: Vec<PartialOccupancy> = sites
+            .iter()
+            .filter(|site| site.occupancy < 1.0 - 1e-6)
+            .map(|site| PartialOccupancy {
+                label: site.label.clone(),
+                element: site.symbol.clone(),
+                occupancy: site.occupancy,
+            })
+            .collect();
+        let atoms = expand_sites(&sites, &symmetry_operations, &lattice_vectors, tolerance);
+        let space_group = block
+            .item(&[
+                "_symmetry_space_group_name_h-m",
+                "_space_group_name_h-m_alt",
+            ])
+            .map(|name| name.to_string());
+        let mut settings = Settings::default();
+        if let Some(space_group) = space_group.as_deref() {
+            settings.set_space_group(space_group);
+        }
+        settings.set_symmetry_operations(symmetry_operations);
+        Ok(Self {
+            model: LatticeModel::new(Some(lattice_vectors), atoms, settings),
+            space_group,
+            partial_occupancies,
+        })
+    }
+
+    pub fn model(&self) -> &LatticeModel<CellModel> {
+        &self.model
+    }
+
+    pub fn into_model(self) -> LatticeModel<CellModel> {
+        self.model
+    }
+    /// The Hermann-Mauguin symbol, if given.
+    /// It is also stored in `Settings::space_group()` of the model.
+    pub fn space_group(&self) -> Option<&str> {
+        self.space_group.as_deref()
+    }
+    /// The operations used to expand the asymmetric unit.
+    /// Only the identity when the file has no symmetry operations.
+    pub fn symmetry_operations(&self) -> &[SymmetryOperation] {
+        self.model.settings().symmetry_operations()
+    }
+
+    pub fn partial_occupancies(&self) -> &[PartialOccupancy] {
+        self.partial_occupancies.as_ref()
+    }
+}
+
+impl FromStr for CifStructure {
+    type Err = CifParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with_tolerance(s, DEFAULT_SITE_TOLERANCE)
+    }
+}
+
+impl AsRef<LatticeModel<CellModel>> for CifStructure {
+    fn as_ref(&self) -> &LatticeModel<CellModel> {
+        &self.model
+    }
+}
+
+/// A site in the asymmetric unit.
+#[derive(Debug)]
+struct AsymmetricSite {
+    label: String,
+    symbol: String,
+    atomic_num: u8,
+    frac_xyz: Point3<f64>,
+    occupancy: f64,
+}
+
+/// Apply every operation to every site, skipping images which fall within
+/// `tolerance` of an existing atom of the same element.
+fn expand_sites(
+    sites: &[AsymmetricSite],
+    operations: &[SymmetryOperation],
+    lattice_vectors: &LatticeVectors<CellModel>,
+    tolerance: f64,
+) -> AtomCollection<CellModel> {
+    let to_cart = lattice_vectors.vectors();
+    let mut expanded: Vec<(&AsymmetricSite, Point3<f64>)> = Vec::new();
+    sites.iter().for_each(|site| {
+        operations.iter().for_each(|op| {
+            let image = op.apply(&site.frac_xyz);
+            let is_duplicate = expanded.iter().any(|(other, other_xyz)| {
+                if other.atomic_num != site.atomic_num {
+                    return false;
+                }
+                // Minimum image convention.
+                let diff = (image - other_xyz).map(|d| d - d.round());
+                (to_cart * diff).norm() < tolerance
+            });
+            if !is_duplicate {
+                expanded.push((site, image));
+            }
+        })
+    });
+    let num_atom = expanded.len();
+    let element_symbols: Vec<String> = expanded.iter().map(|(s, _)| s.symbol.clone()).collect();
+    let atomic_nums: Vec<u8> = expanded.iter().map(|(s, _)| s.atomic_num).collect();
+    let xyz_coords: Vec<Point3<f64>> = expanded.iter().map(|(_, f)| to_cart * f).collect();
+    let frac_xyz: Vec<Option<Point3<f64>>> = expanded.iter().map(|(_, f)| Some(*f)).collect();
+    let atom_ids: Vec<u32> = (1..=num_atom as u32).collect();
+    AtomCollectionBuilder::<CellModel, No>::new(num_atom)
+        .with_element_symbols(&element_symbols)
+        .unwrap()
+        .with_atomic_nums(&atomic_nums)
+        .unwrap()
+        .with_xyz_coords(&xyz_coords)
+        .unwrap()
+        .with_fractional_xyz(&frac_xyz)
+        .unwrap()
+        .with_atom_ids(&atom_ids)
+        .unwrap()
+        .finish()
+        .unwrap()
+        .build()
+}
+
+/// Tokens of the `cif` syntax.
+#[derive(Debug, PartialEq)]
+enum Token {
+    DataBlock,
+    Loop,
+    Tag(String),
+    Value(String),
+}
+
+/// Tag names are case-insensitive, and the `_cell.length_a` style of CIF2
+/// is treated the same as `_cell_length_a`.
+fn normalize_tag(tag: &str) -> String {
+    tag.to_lowercase().replace('.', "_")
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, CifParseError> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut lines = s.lines();
+    while let Some(line) = lines.next() {
+        // Multi-line text field, delimited by lines starting with `;`.
+        if let Some(first) = line.strip_prefix(';') {
+            let mut text = vec![first];
+            loop {
+                match lines.next() {
+                    Some(text_line) if text_line.starts_with(';') => break,
+                    Some(text_line) => text.push(text_line),
+                    None => return Err(CifParseError::UnterminatedTextField),
+                }
+            }
+            tokens.push(Token::Value(text.join("\n").trim().to_string()));
+            continue;
+        }
+        let mut rest = line.trim_start();
+        while !rest.is_empty() && !rest.starts_with('#') {
+            let (token, remaining) = match rest.chars().next() {
+                Some(quote @ ('\'' | '"')) => {
+                    let body = &rest[1..];
+                    // A quote only closes the value when followed by a whitespace.
+                    let end = body
+                        .char_indices()
+                        .find(|(i, c)| {
+                            *c == quote
+                                && body[i + 1..].chars().next().is_none_or(char::is_whitespace)
+                        })
+                        .map(|(i, _)| i)
+                        .ok_or_else(|| CifParseError::UnterminatedQuote(line.into()))?;
+                    (Token::Value(body[..end].into()), &body[end + 1..])
+                }
+                _ => {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    let word = &rest[..end];
+                    let lowercase = word.to_lowercase();
+                    let token = if word.starts_with('_') {
+                        Token::Tag(normalize_tag(word))
+                    } else if lowercase == "loop_" {
+                        Token::Loop
+                    } else if lowercase.starts_with("data_") {
+                        Token::DataBlock
+                    } else {
+                        Token::Value(word.into())
+                    };
+                    (token, &rest[end..])
+                }
+            };
+            tokens.push(token);
+            rest = remaining.trim_start();
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+struct CifLoop {
+    tags: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl CifLoop {
+    fn column(&self, tags: &[&str]) -> Option<usize> {
+        self.tags
+            .iter()
+            .position(|tag| tags.iter().any(|candidate| tag == candidate))
+    }
+}
+
+/// The tags and loops in the first data block of a `cif` file.
+#[derive(Debug)]
+struct CifBlock {
+    items: HashMap<String, String>,
+    loops: Vec<CifLoop>,
+}
+
+/// Numbers in `cif` may carry the standard uncertainty, e.g. `5.4307(2)`.
+fn cif_number(value: &str) -> Option<f64> {
+    let value = value.split('(').next().unwrap_or(value);
+    all_consuming(number)(value).ok().map(|(_, v)| v)
+}
+
+impl CifBlock {
+    fn new(s: &str) -> Result<Self, CifParseError> {
+        let tokens = tokenize(s)?;
+        if !tokens.contains(&Token::DataBlock) {
+            return Err(CifParseError::NoDataBlock);
+        }
+        let mut tokens = tokens
+            .into_iter()
+            .skip_while(|token| *token != Token::DataBlock)
+            .skip(1)
+            .take_while(|token| *token != Token::DataBlock)
+            .peekable();
+        let mut items = HashMap::new();
+        let mut loops = Vec::new();
+        while let Some(token) = tokens.next() {
+            match token {
+                Token::Tag(tag) => {
+                    if let Some(Token::Value(_)) = tokens.peek() {
+                        if let Some(Token::Value(value)) = tokens.next() {
+                            items.insert(tag, value);
+                        }
+                    }
+                }
+                Token::Loop => {
+                    let mut tags = Vec::new();
+                    while let Some(Token::Tag(_)) = tokens.peek() {
+                        if let Some(Token::Tag(tag)) = tokens.next() {
+                            tags.push(tag);
+                        }
+                    }
+                    let mut values = Vec::new();
+                    while let Some(Token::Value(_)) = tokens.peek() {
+                        if let Some(Token::Value(value)) = tokens.next() {
+                            values.push(value);
+                        }
+                    }
+                    if tags.is_empty() || values.len() % tags.len() != 0 {
+                        return Err(CifParseError::InvalidLoop(
+                            tags.first().cloned().unwrap_or_default(),
+                        ));
+                    }
+                    let rows = values.chunks(tags.len()).map(|row| row.to_vec()).collect();
+                    loops.push(CifLoop { tags, rows });
+                }
+                Token::Value(_) | Token::DataBlock => (),
+            }
+        }
+        Ok(Self { items, loops })
+    }
+
+    fn item(&self, tags: &[&str]) -> Option<&str> {
+        tags.iter()
+            .find_map(|tag| self.items.get(*tag))
+            .map(|value| value.as_str())
+            .filter(|value| *value != "?" && *value != ".")
+    }
+
+    fn number(&self, tag: &str) -> Result<f64, CifParseError> {
+        let value = self
+            .item(&[tag])
+            .ok_or_else(|| CifParseError::MissingTag(tag.into()))?;
+        cif_number(value).ok_or_else(|| CifParseError::InvalidNumber {
+            tag: tag.into(),
+            value: value.into(),
+        })
+    }
+
+    fn find_loop(&self, tags: &[&str]) -> Option<&CifLoop> {
+        self.loops.iter().find(|l| l.column(tags).is_some())
+    }
+
+    fn parse_lattice(&self) -> Result<LatticeVectors<CellModel>, CifParseError> {
+        let lengths = [
+            self.number("_cell_length_a")?,
+            self.number("_cell_length_b")?,
+            self.number("_cell_length_c")?,
+        ];
+        let angles = [
+            self.number("_cell_angle_alpha")?,
+            self.number("_cell_angle_beta")?,
+            self.number("_cell_angle_gamma")?,
+        ];
+        Ok(LatticeVectors::from_lattice_params(lengths, angles))
+    }
+
+    fn parse_symmetry_operations(&self) -> Result<Vec<SymmetryOperation>, CifParseError> {
+        const TAGS: [&str; 2] = [
+            "_symmetry_equiv_pos_as_xyz",
+            "_space_group_symop_operation_xyz",
+        ];
+        let operations: Vec<&str> = match self.find_loop(&TAGS) {
+            Some(op_loop) => {
+                let column = op_loop.column(&TAGS).unwrap();
+                op_loop
+                    .rows
+                    .iter()
+                    .map(|row| row[column].as_str())
+                    .collect()
+            }
+            None => self.item(&TAGS).into_iter().collect(),
+        };
+        if operations.is_empty() {
+            return Ok(vec![SymmetryOperation::identity()]);
+        }
+        operations
+            .into_iter()
+            .map(|op| {
+                SymmetryOperation::from_str(op)
+                    .map_err(|_| CifParseError::InvalidSymmetryOperation(op.into()))
+            })
+            .collect()
+    }
+
+    fn parse_sites(&self) -> Result<Vec<AsymmetricSite>, CifParseError> {
+        const FRACT: [&str; 3] = [
+            "_atom_site_fract_x",
+            "_atom_site_fract_y",
+            "_atom_site_fract_z",
+        ];
+        let site_loop = self
+            .find_loop(&FRACT[..1])
+            .ok_or_else(|| CifParseError::MissingTag(FRACT[0].into()))?;
+        let columns: Vec<usize> = FRACT
+            .iter()
+            .map(|tag| {
+                site_loop
+                    .column(&[tag])
+                    .ok_or_else(|| CifParseError::MissingTag(tag.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        let label_col = site_loop.column(&["_atom_site_label"]);
+        let symbol_col = site_loop.column(&["_atom_site_type_symbol"]);
+        let occupancy_col = site_loop.column(&["_atom_site_occupancy"]);
+        let species_col = symbol_col
+            .or(label_col)
+            .ok_or_else(|| CifParseError::MissingTag("_atom_site_type_symbol".into()))?;
+        site_loop
+            .rows
+            .iter()
+            .map(|row| {
+                let species = row[species_col].as_str();
+                let (symbol, atomic_num) = species_from_label(species)
+                    .ok_or_else(|| CifParseError::UnknownElement(species.into()))?;
+                let coords: Vec<f64> = columns
+                    .iter()
+                    .zip(FRACT)
+                    .map(|(&col, tag)| {
+                        cif_number(&row[col]).ok_or_else(|| CifParseError::InvalidNumber {
+                            tag: tag.into(),
+                            value: row[col].clone(),
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+                let occupancy = occupancy_col
+                    .and_then(|col| cif_number(&row[col]))
+                    .unwrap_or(1.0);
+                Ok(AsymmetricSite {
+                    label: label_col.map_or_else(|| species.into(), |col| row[col].clone()),
+                    symbol,
+                    atomic_num,
+                    frac_xyz: Point3::new(coords[0], coords[1], coords[2]).map(wrap_fractional),
+                    occupancy,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::model_type::CifExport;
+
+    use super::CifStructure;
+
+    const NACL_CIF: &str = r#"# Rock salt, reduced to the asymmetric unit
+data_NaCl
+_symmetry_space_group_name_H-M   'F m -3 m'
+_cell_length_a    5.6402(3)
+_cell_length_b    5.6402(3)
+_cell_length_c    5.6402(3)
+_cell_angle_alpha 90
+_cell_angle_beta  90
+_cell_angle_gamma 90
+_publ_section_title
+;
+Sodium chloride
+;
+loop_
+_symmetry_equiv_pos_as_xyz
+  'x, y, z'  'x, y+1/2, z+1/2'  'x+1/2, y, z+1/2'  'x+1/2, y+1/2, z'
+  '-x, -y, -z'  '-x, -y+1/2, -z+1/2'  '-x+1/2, -y, -z+1/2'  '-x+1/2, -y+1/2, -z'
+loop_
+_atom_site_label
+_atom_site_type_symbol
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+_atom_site_occupancy
+Na1 Na+ 0.0 0.0 0.0 1.0
+Cl1 Cl- 0.5 0.5 0.5 0.95
+"#;
+
+    #[test]
+    fn expand_nacl() {
+        let structure = CifStructure::from_str(NACL_CIF).unwrap();
+        let atoms = structure.model().atoms();
+        assert_eq!(atoms.size(), 8);
+        assert_eq!(
+            atoms
+                .element_symbols()
+                .iter()
+                .filter(|s| s.as_str() == "Cl")
+                .count(),
+            4
+        );
+        assert_eq!(structure.space_group(), Some("F m -3 m"));
+        assert_eq!(structure.partial_occupancies().len(), 1);
+        assert_eq!(structure.partial_occupancies()[0].label(), "Cl1");
+        let vectors = structure.model().lattice_vectors().unwrap().vectors();
+        assert!((vectors.column(2).norm() - 5.6402).abs() < 1e-10);
+    }
+
+    #[test]
+    fn cif_round_trip() {
+        let structure = CifStructure::from_str(NACL_CIF).unwrap();
+        let p1 = CifStructure::from_str(&structure.export_cif().unwrap()).unwrap();
+        assert_eq!(p1.model().atoms().size(), 8);
+        assert_eq!(p1.symmetry_operations().len(), 1);
+        let symmetrized = structure.export_symmetrized_cif(0.01).unwrap();
+        let parsed_back = CifStructure::from_str(&symmetrized).unwrap();
+        assert_eq!(parsed_back.space_group(), Some("F m -3 m"));
+        assert_eq!(parsed_back.model().atoms().size(), 8);
+        assert!(p1.export_symmetrized_cif(0.01).is_none());
+        let mut model = p1.model().clone();
+        *model.lattice_vectors_mut() = None;
+        assert!(model.export_cif().is_none());
+    }
+}
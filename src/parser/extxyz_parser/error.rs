@@ -0,0 +1,48 @@
+use std::{error::Error, fmt::Display};
+
+#[derive(Debug)]
+/// Error type when parsing an extended `xyz` file.
+pub enum ExtXyzParseError {
+    /// The first line is not the number of atoms.
+    InvalidAtomCount(String),
+    /// The file ends before all atoms are read.
+    MissingAtoms { expected: usize, found: usize },
+    /// `Lattice=` does not contain nine numbers.
+    InvalidLattice(String),
+    /// `Properties=` is not a list of `name:type:columns` triplets.
+    InvalidProperties(String),
+    /// A required column (`species` or `pos`) is not declared in `Properties=`.
+    MissingProperty(String),
+    /// A line of atom does not match the columns declared in `Properties=`.
+    InvalidAtomLine(String),
+    /// The species does not match any element in the periodic table.
+    UnknownElement(String),
+}
+
+impl Display for ExtXyzParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtXyzParseError::InvalidAtomCount(line) => {
+                write!(f, "Invalid number of atoms: \"{}\"", line)
+            }
+            ExtXyzParseError::MissingAtoms { expected, found } => write!(
+                f,
+                "Expected {} atoms, only {} found before the end of file.",
+                expected, found
+            ),
+            ExtXyzParseError::InvalidLattice(lattice) => {
+                write!(f, "Invalid Lattice: \"{}\"", lattice)
+            }
+            ExtXyzParseError::InvalidProperties(properties) => {
+                write!(f, "Invalid Properties: \"{}\"", properties)
+            }
+            ExtXyzParseError::MissingProperty(name) => {
+                write!(f, "Property {} is not declared.", name)
+            }
+            ExtXyzParseError::InvalidAtomLine(line) => write!(f, "Invalid atom line: \"{}\"", line),
+            ExtXyzParseError::UnknownElement(symbol) => write!(f, "Unknown element: {}", symbol),
+        }
+    }
+}
+
+impl Error for ExtXyzParseError {}
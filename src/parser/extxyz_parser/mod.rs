@@ -0,0 +1,218 @@
+use std::str::FromStr;
+
+use nalgebra::{Matrix3, Point3};
+use nom::{
+    branch::alt,
+    bytes::complete::{take_till, take_till1},
+    character::complete::{char, space0, space1},
+    combinator::{all_consuming, opt},
+    multi::separated_list0,
+    sequence::{delimited, pair, preceded, terminated},
+    IResult,
+};
+
+use crate::{
+    atom::{AtomCollection, AtomCollectionBuilder},
+    builder_typestate::No,
+    lattice::{LatticeModel, LatticeVectors},
+    model_type::{extxyz::ExtXyzModel, Settings},
+    parser::{lookup_species, number},
+};
+
+pub use self::error::ExtXyzParseError;
+
+mod error;
+
+/// `key=value`, `key="quoted value"` or a bare `key` in the comment line.
+fn header_entry(input: &str) -> IResult<&str, (&str, Option<&str>)> {
+    pair(
+        take_till1(|c: char| c == '=' || c.is_whitespace()),
+        opt(preceded(
+            char('='),
+            alt((
+                delimited(char('"'), take_till(|c| c == '"'), char('"')),
+                take_till1(|c: char| c.is_whitespace()),
+            )),
+        )),
+    )(input)
+}
+
+fn header_entries(input: &str) -> IResult<&str, Vec<(&str, Option<&str>)>> {
+    all_consuming(delimited(
+        space0,
+        separated_list0(space1, header_entry),
+        space0,
+    ))(input)
+}
+
+/// Nine numbers, vectors a, b and c in order.
+fn parse_lattice(lattice: &str) -> Result<LatticeVectors<ExtXyzModel>, ExtXyzParseError> {
+    let values: Vec<f64> = lattice
+        .split_whitespace()
+        .map(|v| all_consuming(number)(v).map(|(_, value)| value))
+        .collect::<Result<Vec<f64>, _>>()
+        .map_err(|_| ExtXyzParseError::InvalidLattice(lattice.into()))?;
+    if values.len() != 9 {
+        return Err(ExtXyzParseError::InvalidLattice(lattice.into()));
+    }
+    Ok(LatticeVectors::new(Matrix3::from_column_slice(&values)))
+}
+
+/// The column offsets of `species` and `pos`, and the total number of columns.
+fn parse_properties(properties: &str) -> Result<(usize, usize, usize), ExtXyzParseError> {
+    let invalid = || ExtXyzParseError::InvalidProperties(properties.into());
+    let fields: Vec<&str> = properties.split(':').collect();
+    if !fields.len().is_multiple_of(3) {
+        return Err(invalid());
+    }
+    let mut species_col = None;
+    let mut pos_col = None;
+    let mut total_cols = 0;
+    for triplet in fields.chunks(3) {
+        let (name, data_type) = (triplet[0], triplet[1]);
+        let n_cols: usize = triplet[2].parse().map_err(|_| invalid())?;
+        match (name.to_lowercase().as_str(), data_type) {
+            ("species", "S") if n_cols == 1 => species_col = Some(total_cols),
+            ("pos", "R") if n_cols == 3 => pos_col = Some(total_cols),
+            ("species" | "pos", _) => return Err(invalid()),
+            _ => (),
+        }
+        total_cols += n_cols;
+    }
+    let species_col =
+        species_col.ok_or_else(|| ExtXyzParseError::MissingProperty("species".into()))?;
+    let pos_col = pos_col.ok_or_else(|| ExtXyzParseError::MissingProperty("pos".into()))?;
+    Ok((species_col, pos_col, total_cols))
+}
+
+/// Parse the first frame of an extended `xyz` file.
+/// Only `Lattice=` and `Properties=` in the comment line are interpreted, and
+/// only the `species` and `pos` columns are read. A plain `xyz` file is accepted
+/// as well, and results in a model without lattice vectors.
+impl FromStr for LatticeModel<ExtXyzModel> {
+    type Err = ExtXyzParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        let count_line = lines.next().unwrap_or("");
+        let num_atom: usize = terminated(number, space0)(count_line.trim())
+            .ok()
+            .filter(|(rest, n)| rest.is_empty() && n.fract() == 0.0 && *n >= 0.0)
+            .map(|(_, n)| n as usize)
+            .ok_or_else(|| ExtXyzParseError::InvalidAtomCount(count_line.into()))?;
+        // A comment line which is not made of `key=value` is treated as plain `xyz`.
+        let entries = header_entries(lines.next().unwrap_or(""))
+            .map(|(_, entries)| entries)
+            .unwrap_or_default();
+        let lattice_vectors = entries
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Lattice"))
+            .and_then(|(_, value)| *value)
+            .map(parse_lattice)
+            .transpose()?;
+        let (species_col, pos_col, total_cols) = entries
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Properties"))
+            .and_then(|(_, value)| *value)
+            .map(parse_properties)
+            .transpose()?
+            .unwrap_or((0, 1, 4));
+        let to_frac = lattice_vectors
+            .as_ref()
+            .and_then(|lat_vec| lat_vec.vectors().try_inverse());
+        let mut element_symbols: Vec<String> = Vec::with_capacity(num_atom);
+        let mut atomic_nums: Vec<u8> = Vec::with_capacity(num_atom);
+        let mut xyz_coords: Vec<Point3<f64>> = Vec::with_capacity(num_atom);
+        let mut frac_xyz: Vec<Option<Point3<f64>>> = Vec::with_capacity(num_atom);
+        for found in 0..num_atom {
+            let line = lines.next().ok_or(ExtXyzParseError::MissingAtoms {
+                expected: num_atom,
+                found,
+            })?;
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            if columns.len() < total_cols {
+                return Err(ExtXyzParseError::InvalidAtomLine(line.into()));
+            }
+            let species = columns[species_col];
+            let (symbol, atomic_num) = lookup_species(species)
+                .ok_or_else(|| ExtXyzParseError::UnknownElement(species.into()))?;
+            let coord: Vec<f64> = columns[pos_col..pos_col + 3]
+                .iter()
+                .map(|v| all_consuming(number)(v).map(|(_, value)| value))
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| ExtXyzParseError::InvalidAtomLine(line.into()))?;
+            let xyz = Point3::new(coord[0], coord[1], coord[2]);
+            element_symbols.push(symbol);
+            atomic_nums.push(atomic_num);
+            xyz_coords.push(xyz);
+            frac_xyz.push(to_frac.map(|to_frac| to_frac * xyz));
+        }
+        let atom_ids: Vec<u32> = (1..=num_atom as u32).collect();
+        let atoms: AtomCollection<ExtXyzModel> =
+            AtomCollectionBuilder::<ExtXyzModel, No>::new(num_atom)
+                .with_element_symbols(&element_symbols)
+                .unwrap()
+                .with_atomic_nums(&atomic_nums)
+                .unwrap()
+                .with_xyz_coords(&xyz_coords)
+                .unwrap()
+                .with_fractional_xyz(&frac_xyz)
+                .unwrap()
+                .with_atom_ids(&atom_ids)
+                .unwrap()
+                .finish()
+                .unwrap()
+                .build();
+        Ok(LatticeModel::new(
+            lattice_vectors,
+            atoms,
+            Settings::default(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{
+        model_type::{extxyz::ExtXyzModel, DefaultExport},
+        CellModel, LatticeModel, MsiModel,
+    };
+
+    const EXTXYZ: &str = r#"2
+Lattice="4.0 0.0 0.0 0.0 5.0 0.0 0.0 0.0 6.0" Properties=species:S:1:tags:I:1:pos:R:3:forces:R:3 energy=-10.5 pbc="T T T"
+Fe  0   1.0 2.5 3.0   0.0 0.0 0.1
+o   1   2.0 0.0 1.5   0.0 0.0 -0.1
+"#;
+
+    #[test]
+    fn parse_extxyz() {
+        let model = LatticeModel::<ExtXyzModel>::from_str(EXTXYZ).unwrap();
+        assert_eq!(model.atoms().element_symbols(), &["Fe", "O"]);
+        assert!((model.lattice_vectors().unwrap().vectors().column(1).norm() - 5.0).abs() < 1e-10);
+        let cell_model: LatticeModel<CellModel> = model.clone().into();
+        let frac = cell_model.atoms().fractional_xyz()[0].unwrap();
+        assert!((frac.y - 0.5).abs() < 1e-10);
+        let msi_model: LatticeModel<MsiModel> = model.clone().into();
+        assert_eq!(msi_model.atoms().size(), 2);
+        let plain = LatticeModel::<ExtXyzModel>::from_str("1\nwater molecule\nH 0 0 0\n").unwrap();
+        assert!(plain.lattice_vectors().is_none());
+    }
+
+    #[test]
+    fn extxyz_round_trip() {
+        let model = LatticeModel::<ExtXyzModel>::from_str(EXTXYZ).unwrap();
+        let parsed_back = LatticeModel::<ExtXyzModel>::from_str(&model.export()).unwrap();
+        assert_eq!(
+            model.lattice_vectors().unwrap().vectors(),
+            parsed_back.lattice_vectors().unwrap().vectors()
+        );
+        model
+            .atoms()
+            .xyz_coords()
+            .iter()
+            .zip(parsed_back.atoms().xyz_coords())
+            .for_each(|(a, b)| assert!((a - b).norm() < 1e-8));
+    }
+}
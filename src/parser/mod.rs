@@ -1,13 +1,23 @@
+use castep_periodic_table::{data::ELEMENT_TABLE, element::LookupElement};
 use nom::{
     branch::alt,
-    character::complete::{char, one_of},
-    combinator::{opt, recognize},
+    bytes::complete::{tag_no_case, take_till1},
+    character::complete::{char, one_of, space0, space1},
+    combinator::{map_res, opt, recognize, rest},
     multi::many1,
-    sequence::{preceded, tuple},
+    sequence::{pair, preceded, tuple},
     IResult,
 };
 
+pub mod castep_parser;
+pub mod cell_parser;
+pub mod cif_parser;
+pub mod extxyz_parser;
 pub mod msi_parser;
+pub mod param_parser;
+pub mod poscar_parser;
+pub mod trajectory_parser;
+pub mod xsd_parser;
 
 pub fn decimal(input: &str) -> IResult<&str, &str> {
     recognize(many1(one_of("0123456789")))(input)
@@ -40,6 +50,83 @@ pub fn float(input: &str) -> IResult<&str, &str> {
     ))(input)
 }
 
+/// Parse a signed real number, written either as a float or as an integer,
+/// into `f64`.
+pub fn number(input: &str) -> IResult<&str, f64> {
+    map_res(
+        recognize(tuple((opt(one_of("+-")), alt((float, decimal))))),
+        |num_str: &str| num_str.parse::<f64>(),
+    )(input)
+}
+
+/// Look up the element symbol and atomic number of a species.
+/// The species may carry a label (`Fe:1`) or be given as its atomic number.
+/// Symbols are matched case-insensitively.
+pub(crate) fn lookup_species(species: &str) -> Option<(String, u8)> {
+    let element = species.split(':').next().unwrap_or(species);
+    let found = if let Ok(atomic_number) = element.parse::<u8>() {
+        ELEMENT_TABLE.get_by_atomic_number(atomic_number)
+    } else {
+        // Normalize to "Fe" style.
+        let mut chars = element.chars();
+        let normalized: String = match chars.next() {
+            Some(first) => first
+                .to_uppercase()
+                .chain(chars.flat_map(|c| c.to_lowercase()))
+                .collect(),
+            None => String::new(),
+        };
+        ELEMENT_TABLE.get_by_symbol(&normalized)
+    };
+    found.map(|elm| (elm.symbol().to_string(), elm.atomic_number()))
+}
+
+/// Guess the element from a label like `Fe1`, `C1'`, `Fe-a` or `Fe2+`. The leading
+/// letters are tried as a two-letter symbol first, then as a one-letter symbol.
+pub(crate) fn species_from_label(label: &str) -> Option<(String, u8)> {
+    let element: String = label
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .take(2)
+        .collect();
+    lookup_species(&element).or_else(|| lookup_species(element.get(..1)?))
+}
+
+/// Remove the comment after `!` or `#` in a line of `cell` or `param`.
+pub(crate) fn strip_comment(line: &str) -> &str {
+    line.split(['!', '#']).next().unwrap_or("")
+}
+
+/// `%BLOCK name`
+pub(crate) fn block_start(line: &str) -> IResult<&str, &str> {
+    preceded(
+        pair(tag_no_case("%BLOCK"), space1),
+        take_till1(|c: char| c.is_whitespace()),
+    )(line)
+}
+
+/// `%ENDBLOCK name`
+pub(crate) fn block_end(line: &str) -> IResult<&str, &str> {
+    preceded(
+        pair(tag_no_case("%ENDBLOCK"), space1),
+        take_till1(|c: char| c.is_whitespace()),
+    )(line)
+}
+
+/// `keyword : value`, `keyword = value` or `keyword value`.
+/// The value is empty when the keyword stands alone.
+pub(crate) fn keyword_line(line: &str) -> IResult<&str, (&str, &str)> {
+    let (rest_input, (keyword, value)) = pair(
+        take_till1(|c: char| c.is_whitespace() || c == ':' || c == '='),
+        opt(preceded(
+            alt((recognize(tuple((space0, one_of(":="), space0))), space1)),
+            rest,
+        )),
+    )(line)?;
+    Ok((rest_input, (keyword, value.unwrap_or("").trim())))
+}
+
 #[test]
 fn test_float() {
     let number = "-2.865153883599e-05";
@@ -1,22 +1,39 @@
 use std::str::FromStr;
 
-use nom::Err;
-
 use crate::lattice::LatticeModel;
 use crate::model_type::msi::MsiModel;
 
 use self::state_machine::MsiParser;
 
+pub use self::state_machine::{AttributeMatchError, MsiParseError, MsiParseErrorKind, StateError};
+
 extern crate nom;
 
 mod state_machine;
 
+/// Parse the first `Model` object in the `msi` file.
 impl FromStr for LatticeModel<MsiModel> {
-    type Err = Err<&'static str>;
+    type Err = MsiParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(MsiParser::new(s).starts().analyze().build_lattice_model())
+        let parser = MsiParser::new(s)
+            .starts()
+            .ok_or_else(|| MsiParseError::new(s, s, MsiParseErrorKind::NoModel))?;
+        parser.analyze()?.build_lattice_model()
+    }
+}
+
+/// Parse all `Model` objects in the `msi` file, in the order of appearance.
+/// Models are located by the object type, whatever their object numbers are.
+pub fn parse_msi_models(s: &str) -> Result<Vec<LatticeModel<MsiModel>>, MsiParseError> {
+    let mut models = Vec::new();
+    let mut parser = MsiParser::new(s);
+    while let Some(model_parser) = parser.starts() {
+        let analyzed = model_parser.analyze()?;
+        models.push(analyzed.build_lattice_model()?);
+        parser = analyzed.next_model();
     }
+    Ok(models)
 }
 
 #[cfg(test)]
@@ -28,3 +45,318 @@ fn test_parser() {
     let model: LatticeModel<MsiModel> = LatticeModel::from_str(&file_content).unwrap();
     println!("{:?}", model);
 }
+
+#[cfg(test)]
+#[test]
+fn test_multiple_models() {
+    let file_content = r#"# MSI CERIUS2 DataModel File Version 4 0
+(1 Model
+  (2 Atom
+    (A C ACL "6 C")
+    (A C Label "C")
+    (A D XYZ (0 0 0))
+    (A I Id 1)
+  )
+  (3 Atom
+    (A C ACL "8 O")
+    (A C Label "O")
+    (A D XYZ (0 0 1.128))
+    (A I Id 2)
+  )
+)
+(4 Model
+  (5 Atom
+    (A C ACL "1 H")
+    (A C Label "H")
+    (A D XYZ (0.5 0 0))
+    (A I Id 1)
+  )
+)
+"#;
+    let models = parse_msi_models(file_content).unwrap();
+    assert_eq!(models.len(), 2);
+    assert_eq!(models[0].atoms().size(), 2);
+    assert_eq!(models[1].atoms().element_symbols(), &["H".to_string()]);
+    let first = LatticeModel::<MsiModel>::from_str(file_content).unwrap();
+    assert_eq!(first.atoms().element_symbols()[1], "O");
+    assert!(LatticeModel::<MsiModel>::from_str("# empty\n").is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_bonds() {
+    use crate::{bond::BondType, model_type::DefaultExport};
+
+    let file_content = r#"# MSI CERIUS2 DataModel File Version 4 0
+(1 Model
+  (3 Atom
+    (A C ACL "6 C")
+    (A D XYZ (0 0 0))
+    (A I Id 1)
+  )
+  (5 Bond
+    (A O Atom1 3)
+    (A O Atom2 4)
+    (A I Type 2)
+  )
+  (4 Atom
+    (A C ACL "8 O")
+    (A D XYZ (0 0 1.2))
+    (A I Id 2)
+  )
+)
+"#;
+    let model = LatticeModel::<MsiModel>::from_str(file_content).unwrap();
+    assert_eq!(model.bonds().bonds()[0].atom_ids(), (1, 2));
+    assert_eq!(model.bonds().bond_types(), &[BondType::Double]);
+    let exported = model.export();
+    assert!(exported
+        .contains("  (4 Bond\n    (A O Atom1 2)\n    (A O Atom2 3)\n    (A I Type 2)\n  )\n)"));
+    let parsed_back = LatticeModel::<MsiModel>::from_str(&exported).unwrap();
+    assert_eq!(parsed_back.bonds().bonds(), model.bonds().bonds());
+}
+
+#[cfg(test)]
+#[test]
+fn test_unknown_attributes() {
+    use crate::{model_type::DefaultExport, CellModel};
+
+    let file_content = r#"# MSI CERIUS2 DataModel File Version 4 0
+(1 Model
+  (A I CRY/DISPLAY (192 256))
+  (A I PeriodicType 100)
+  (A C SpaceGroup "1 1")
+  (A D A3 (10 0 0))
+  (A D B3 (0 10 0))
+  (A D C3 (0 0 10))
+  (A D CRY/TOLERANCE 0.05)
+  (A C Label "slab")
+  (2 Atom
+    (A C ACL "26 Fe")
+    (A C Label "Fe_top")
+    (A D XYZ (0 0 0))
+    (A I Id 1)
+    (A I FormalCharge 2)
+    (A F Charge 0.35)
+    (A I Visible 0)
+  )
+)
+"#;
+    let model = LatticeModel::<MsiModel>::from_str(file_content).unwrap();
+    let model_attrs = model.settings().unknown_attributes();
+    assert_eq!(model_attrs.len(), 1);
+    assert_eq!(model_attrs[0].value(), "\"slab\"");
+    assert_eq!(model.atoms().unknown_attributes()[0].len(), 4);
+    let exported = model.export();
+    assert!(exported.contains("  (A C Label \"slab\")\n"));
+    assert!(exported.contains("    (A C Label \"Fe_top\")\n"));
+    assert!(!exported.contains("(A C Label \"Fe\")"));
+    assert!(exported.contains("    (A F Charge 0.35)\n    (A I Visible 0)\n"));
+    let parsed_back = LatticeModel::<MsiModel>::from_str(&exported).unwrap();
+    assert_eq!(
+        parsed_back.settings().unknown_attributes(),
+        model.settings().unknown_attributes()
+    );
+    assert_eq!(
+        parsed_back.atoms().unknown_attributes(),
+        model.atoms().unknown_attributes()
+    );
+    // The attributes move with the atom through other formats.
+    let cell_model: LatticeModel<CellModel> = parsed_back.into();
+    let msi_back: LatticeModel<MsiModel> = cell_model.into();
+    assert_eq!(
+        msi_back.atoms().unknown_attributes(),
+        model.atoms().unknown_attributes()
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_fixed_atoms() {
+    use crate::{model_type::DefaultExport, CellModel};
+
+    let file_content = r#"# MSI CERIUS2 DataModel File Version 4 0
+(1 Model
+  (A I PeriodicType 100)
+  (A C SpaceGroup "1 1")
+  (A D A3 (10 0 0))
+  (A D B3 (0 10 0))
+  (A D C3 (0 0 10))
+  (A D CRY/TOLERANCE 0.05)
+  (2 Atom
+    (A C ACL "26 Fe")
+    (A D XYZ (0 0 0))
+    (A I Id 1)
+    (A I IsMotionFixed 1)
+  )
+  (3 Atom
+    (A C ACL "8 O")
+    (A D XYZ (0 0 2))
+    (A I Id 2)
+  )
+  (4 Atom
+    (A C ACL "26 Fe")
+    (A D XYZ (0 0 4))
+    (A I Id 3)
+    (A I IsMotionFixed 1)
+  )
+)
+"#;
+    let model = LatticeModel::<MsiModel>::from_str(file_content).unwrap();
+    assert_eq!(
+        model.atoms().fixed_axes(),
+        &[[true; 3], [false; 3], [true; 3]]
+    );
+    assert!(model.atoms().unknown_attributes()[0].is_empty());
+    let cell_model: LatticeModel<CellModel> = model.into();
+    let cell_text = DefaultExport::<CellModel>::export(&cell_model);
+    assert!(cell_text.contains(&format!(
+        "{:>6} {:>4}{:>6}{:16.10}{:16.10}{:16.10}\n",
+        6, "Fe", 2, 0.0, 0.0, 1.0
+    )));
+    let cell_back = LatticeModel::<CellModel>::from_str(&cell_text).unwrap();
+    assert_eq!(
+        cell_back.atoms().fixed_axes(),
+        cell_model.atoms().fixed_axes()
+    );
+    let msi_back: LatticeModel<MsiModel> = cell_back.into();
+    assert_eq!(
+        msi_back.export().matches("(A I IsMotionFixed 1)").count(),
+        2
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_partially_fixed_atoms() {
+    use crate::{model_type::DefaultExport, CellModel};
+
+    let cell_content = r#"%BLOCK LATTICE_CART
+  10.0   0.0   0.0
+   0.0  10.0   0.0
+   0.0   0.0  10.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+  Fe   0.0   0.0   0.0
+   O   0.0   0.0   0.2
+   C   0.0   0.0   0.4
+%ENDBLOCK POSITIONS_FRAC
+
+%BLOCK IONIC_CONSTRAINTS
+  1  Fe  1  1.0  0.0  0.0
+  2  Fe  1  0.0  0.0  1.0
+  3   O  1  1.0  0.0  0.0
+  4   O  1  0.0  1.0  0.0
+  5   O  1  0.0  0.0  1.0
+%ENDBLOCK IONIC_CONSTRAINTS
+"#;
+    let cell_model = LatticeModel::<CellModel>::from_str(cell_content).unwrap();
+    let expected = [[true, false, true], [true; 3], [false; 3]];
+    assert_eq!(cell_model.atoms().fixed_axes(), &expected);
+    let msi_model: LatticeModel<MsiModel> = cell_model.into();
+    // The partial flags stay in the model.
+    assert_eq!(msi_model.atoms().fixed_axes(), &expected);
+    let cell_back: LatticeModel<CellModel> = msi_model.clone().into();
+    let cell_text = DefaultExport::<CellModel>::export(&cell_back);
+    let cell_again = LatticeModel::<CellModel>::from_str(&cell_text).unwrap();
+    assert_eq!(cell_again.atoms().fixed_axes(), &expected);
+    // Only the fully fixed atom is fixed in `msi`.
+    let msi_text = msi_model.export();
+    assert!(!msi_text.contains("FixedAxes"));
+    assert_eq!(msi_text.matches("(A I IsMotionFixed 1)").count(), 1);
+    let msi_back = LatticeModel::<MsiModel>::from_str(&msi_text).unwrap();
+    assert_eq!(
+        msi_back.atoms().fixed_axes(),
+        &[[false; 3], [true; 3], [false; 3]]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_error() {
+    let file_content = r#"# MSI CERIUS2 DataModel File Version 4 0
+(1 Model
+  (2 Atom
+    (A C ACL "6 C")
+    (A D XYZ (0 0))
+    (A I Id 1)
+  )
+)
+"#;
+    let error = LatticeModel::<MsiModel>::from_str(file_content).unwrap_err();
+    assert_eq!((error.line(), error.column()), (5, 8));
+    assert!(matches!(error.kind(), MsiParseErrorKind::AttributeMatch(_)));
+    let missing_xyz = file_content.replace("    (A D XYZ (0 0))\n", "");
+    let error = LatticeModel::<MsiModel>::from_str(&missing_xyz).unwrap_err();
+    assert!(matches!(
+        error.kind(),
+        MsiParseErrorKind::MissingAttribute { attribute, .. } if attribute == "XYZ"
+    ));
+    let unclosed = "(1 Model\n  (A I PeriodicType 100)\n";
+    let error = LatticeModel::<MsiModel>::from_str(unclosed).unwrap_err();
+    assert!(matches!(error.kind(), MsiParseErrorKind::UnclosedModel));
+    assert_eq!(error.line(), 3);
+    let overflowing_object = file_content.replace("(2 Atom", "(99999999999 Atom");
+    let error = LatticeModel::<MsiModel>::from_str(&overflowing_object).unwrap_err();
+    assert_eq!((error.line(), error.column()), (3, 4));
+    assert!(matches!(error.kind(), MsiParseErrorKind::AttributeMatch(_)));
+}
+
+#[cfg(test)]
+#[test]
+fn test_bond_parse_error() {
+    let file_content = r#"# MSI CERIUS2 DataModel File Version 4 0
+(1 Model
+  (2 Atom
+    (A C ACL "6 C")
+    (A D XYZ (0 0 0))
+  )
+  (3 Atom
+    (A C ACL "6 C")
+    (A D XYZ (0 0 1.5))
+  )
+  (4 Bond
+    (A O Atom1 2)
+    (A O Atom2 99999999999)
+  )
+)
+"#;
+    let error = LatticeModel::<MsiModel>::from_str(file_content).unwrap_err();
+    assert_eq!((error.line(), error.column()), (13, 8));
+    assert!(matches!(error.kind(), MsiParseErrorKind::AttributeMatch(_)));
+    let model =
+        LatticeModel::<MsiModel>::from_str(&file_content.replace("99999999999", "3")).unwrap();
+    assert_eq!(model.bonds().len(), 1);
+}
+
+#[cfg(test)]
+#[test]
+fn test_relaxed_atoms() {
+    let file_content = "# MSI CERIUS2 DataModel File Version 4 0
+(1 Model
+ (2 Atom
+  (A C ACL \"6 C.3\")
+  (A C Label \"C1'\")
+  (A D XYZ (0 0 -1))
+ )
+\t(3 Atom
+\t\t(A C Label \"Fe-a (top)\")
+\t\t(A D XYZ (0 0 1.5))
+\t)
+)
+";
+    let model = LatticeModel::<MsiModel>::from_str(file_content).unwrap();
+    let atoms = model.atoms();
+    assert_eq!(
+        atoms.element_symbols(),
+        &["C".to_string(), "Fe".to_string()]
+    );
+    assert_eq!(atoms.atomic_nums(), &[6, 26]);
+    assert_eq!(atoms.atom_ids(), &[1, 2]);
+    assert_eq!(atoms.xyz_coords()[0].z, -1.0);
+    assert_eq!(
+        model.atoms().unknown_attributes()[1][0].value(),
+        "\"Fe-a (top)\""
+    );
+}
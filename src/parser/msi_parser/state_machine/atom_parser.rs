@@ -1,52 +1,67 @@
 use nalgebra::Point3;
 use nom::{
-    branch::alt,
-    bytes::complete::tag,
-    character::complete::{alpha1, alphanumeric1, char, space1},
-    combinator::opt,
+    bytes::complete::{tag, take_till},
+    character::complete::{alpha1, char, space1},
+    combinator::map_res,
     multi::separated_list1,
-    sequence::{delimited, preceded, separated_pair, tuple},
+    sequence::{delimited, preceded, separated_pair, terminated, tuple},
     IResult,
 };
 
-use crate::parser::{decimal, float};
+use crate::parser::{decimal, number};
 
+/// `C ACL "6 C"`. Tokens after the element symbol are ignored, e.g. `"6 C.3"`.
 pub fn parse_acl(input: &str) -> IResult<&str, (u8, &str)> {
-    let (rest, (num, symbol)) = preceded(
+    preceded(
         tuple((tag("C"), space1, tag("ACL"), space1)),
         delimited(
             char('"'),
-            separated_pair(decimal, space1, alpha1),
+            terminated(
+                separated_pair(
+                    map_res(decimal, |num: &str| num.parse::<u8>()),
+                    space1,
+                    alpha1,
+                ),
+                take_till(|c| c == '"'),
+            ),
             char('"'),
         ),
-    )(input)?;
-    Ok((rest, (num.parse::<u8>().unwrap(), symbol)))
+    )(input)
 }
 
-pub fn parse_label(input: &str) -> IResult<&str, Option<&str>> {
-    opt(preceded(
+/// `C Label "C1'"`. Any characters except the double quote are allowed.
+pub fn parse_label(input: &str) -> IResult<&str, &str> {
+    preceded(
         tuple((tag("C"), space1, tag("Label"), space1)),
-        delimited(char('"'), alphanumeric1, char('"')),
-    ))(input)
+        delimited(char('"'), take_till(|c| c == '"'), char('"')),
+    )(input)
 }
 
 pub fn parse_xyz(input: &str) -> IResult<&str, Point3<f64>> {
-    let (rest, xyz_str) = preceded(
+    let (rest, xyz_vec) = preceded(
         tuple((tag("D"), space1, tag("XYZ"), space1)),
-        delimited(
-            tag("("),
-            separated_list1(space1, alt((float, decimal))),
-            tag(")"),
-        ),
+        delimited(tag("("), separated_list1(space1, number), tag(")")),
     )(input)?;
-    let xyz_vec: Vec<f64> = xyz_str
-        .iter()
-        .map(|num| num.parse::<f64>().unwrap())
-        .collect();
+    if xyz_vec.len() != 3 {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Count,
+        )));
+    }
     Ok((rest, Point3::from_slice(&xyz_vec)))
 }
 
 pub fn parse_id(input: &str) -> IResult<&str, u32> {
-    let (rest, id_str) = preceded(tuple((tag("I"), space1, tag("Id"), space1)), decimal)(input)?;
-    Ok((rest, id_str.parse::<u32>().unwrap()))
+    preceded(
+        tuple((tag("I"), space1, tag("Id"), space1)),
+        map_res(decimal, |num: &str| num.parse::<u32>()),
+    )(input)
+}
+
+/// `I IsMotionFixed 1`. Any non-zero value fixes the atom along all three axes.
+pub fn parse_is_motion_fixed(input: &str) -> IResult<&str, bool> {
+    preceded(
+        tuple((tag("I"), space1, tag("IsMotionFixed"), space1)),
+        map_res(decimal, |num: &str| num.parse::<u32>().map(|num| num != 0)),
+    )(input)
 }
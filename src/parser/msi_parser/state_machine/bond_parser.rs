@@ -0,0 +1,36 @@
+use nom::{
+    bytes::complete::tag,
+    character::complete::space1,
+    combinator::map_res,
+    sequence::{preceded, tuple},
+    IResult,
+};
+
+use crate::{bond::BondType, parser::decimal};
+
+/// The object number of the first atom, e.g. `O Atom1 3`
+pub fn parse_atom1(input: &str) -> IResult<&str, u32> {
+    preceded(
+        tuple((tag("O"), space1, tag("Atom1"), space1)),
+        map_res(decimal, |num: &str| num.parse::<u32>()),
+    )(input)
+}
+
+/// The object number of the second atom, e.g. `O Atom2 4`
+pub fn parse_atom2(input: &str) -> IResult<&str, u32> {
+    preceded(
+        tuple((tag("O"), space1, tag("Atom2"), space1)),
+        map_res(decimal, |num: &str| num.parse::<u32>()),
+    )(input)
+}
+
+/// The bond order, e.g. `I Type 2`. A bond without `Type` is single.
+pub fn parse_bond_type(input: &str) -> IResult<&str, BondType> {
+    let (rest, num) = preceded(tuple((tag("I"), space1, tag("Type"), space1)), decimal)(input)?;
+    let bond_type = match num {
+        "2" => BondType::Double,
+        "3" => BondType::Triple,
+        _ => BondType::Single,
+    };
+    Ok((rest, bond_type))
+}
@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{error::Error, fmt::Display};
 
 #[derive(Debug)]
 pub enum StateError {
@@ -17,12 +17,31 @@ impl Display for StateError {
     }
 }
 
+impl Error for StateError {}
+
 #[derive(Debug)]
 pub struct AttributeMatchError {
     current: String,
     expect: String,
 }
 
+impl AttributeMatchError {
+    pub fn new(current: &str, expect: &str) -> Self {
+        Self {
+            current: current.into(),
+            expect: expect.into(),
+        }
+    }
+
+    pub fn current(&self) -> &str {
+        self.current.as_ref()
+    }
+
+    pub fn expect(&self) -> &str {
+        self.expect.as_ref()
+    }
+}
+
 impl Display for AttributeMatchError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -32,3 +51,84 @@ impl Display for AttributeMatchError {
         )
     }
 }
+
+impl Error for AttributeMatchError {}
+
+#[derive(Debug)]
+/// What went wrong when parsing a `msi` file.
+pub enum MsiParseErrorKind {
+    /// No `Model` object is found in the input.
+    NoModel,
+    /// The input ends before the closing `)` of the model.
+    UnclosedModel,
+    /// The content is neither an attribute nor an object.
+    State(StateError),
+    /// The value of an attribute is not in the expected form.
+    AttributeMatch(AttributeMatchError),
+    /// A required attribute is missing in the object.
+    MissingAttribute { object: String, attribute: String },
+}
+
+impl Display for MsiParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MsiParseErrorKind::NoModel => write!(f, "No `Model` object found."),
+            MsiParseErrorKind::UnclosedModel => write!(f, "The model is not closed by `)`."),
+            MsiParseErrorKind::State(e) => write!(f, "{}", e),
+            MsiParseErrorKind::AttributeMatch(e) => write!(f, "{}", e),
+            MsiParseErrorKind::MissingAttribute { object, attribute } => {
+                write!(
+                    f,
+                    "Attribute {} is missing in the {} object.",
+                    attribute, object
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Error type when parsing a `msi` file, located by the line and column
+/// (both starting from 1) in the input.
+pub struct MsiParseError {
+    line: usize,
+    column: usize,
+    kind: MsiParseErrorKind,
+}
+
+impl MsiParseError {
+    /// Locate the `fragment` in the `input` it is sliced from.
+    pub(crate) fn new(input: &str, fragment: &str, kind: MsiParseErrorKind) -> Self {
+        let offset = (fragment.as_ptr() as usize)
+            .saturating_sub(input.as_ptr() as usize)
+            .min(input.len());
+        let consumed = input.get(..offset).unwrap_or(input);
+        let line = consumed.matches('\n').count() + 1;
+        let column = consumed.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        Self { line, column, kind }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn kind(&self) -> &MsiParseErrorKind {
+        &self.kind
+    }
+}
+
+impl Display for MsiParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.kind
+        )
+    }
+}
+
+impl Error for MsiParseError {}
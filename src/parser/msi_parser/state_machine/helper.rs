@@ -1,2 +1,46 @@
+use nom::{
+    bytes::complete::take_till1,
+    character::complete::{one_of, space1},
+    combinator::{map_opt, rest},
+    error::{Error, ErrorKind},
+    sequence::{terminated, tuple},
+    IResult,
+};
 
+use crate::model_type::msi::{MsiAttribute, MsiAttributeType};
 
+/// Parse any attribute into its type, name and raw value.
+/// # Example:
+/// `I FormalCharge 1`, `D XYZ (0 0 1.2)`, `C Label "C1"`
+pub fn parse_msi_attribute(input: &str) -> IResult<&str, MsiAttribute> {
+    let (rest, (attribute_type, name, value)) = tuple((
+        terminated(map_opt(one_of("CDIFO"), MsiAttributeType::from_tag), space1),
+        terminated(take_till1(|c: char| c.is_whitespace()), space1),
+        rest,
+    ))(input)?;
+    Ok((
+        rest,
+        MsiAttribute::new(attribute_type, name, value.trim_end()),
+    ))
+}
+
+/// Take until the `)` closing the current parenthesis, skipping nested
+/// parentheses and quoted strings. The closing `)` is not consumed.
+pub fn take_until_closing(input: &str) -> IResult<&str, &str> {
+    let mut depth = 0_usize;
+    let mut quoted = false;
+    for (i, c) in input.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => {
+                if depth == 0 {
+                    return Ok((&input[i..], &input[..i]));
+                }
+                depth -= 1;
+            }
+            _ => (),
+        }
+    }
+    Err(nom::Err::Error(Error::new(input, ErrorKind::TakeUntil)))
+}
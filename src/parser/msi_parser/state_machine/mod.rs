@@ -1,51 +1,73 @@
-use std::{fmt::Debug, marker::PhantomData};
+use std::{collections::HashMap, fmt::Debug, marker::PhantomData};
 
 use nalgebra::{Matrix3, Point3};
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_until},
-    character::complete::{alpha1, alphanumeric1, line_ending, space0, space1},
-    combinator::{peek, recognize},
-    multi::{many0, many1},
+    bytes::complete::{tag, take_till1, take_until},
+    character::complete::{alpha1, line_ending, space0, space1},
+    combinator::{map_res, peek, recognize},
+    multi::many0,
     sequence::{delimited, preceded, terminated, tuple},
     IResult,
 };
 
 use crate::{
     atom::{AtomCollection, AtomCollectionBuilder},
+    bond::{Bond, BondType, Bonds},
     builder_typestate::No,
     lattice::LatticeVectors,
-    model_type::Settings,
+    model_type::{msi::MsiAttribute, Settings},
     parser::{
         decimal,
         msi_parser::state_machine::model_attributes_parser::{
             parse_cry_tolerance, parse_space_group,
         },
+        species_from_label,
     },
     LatticeModel, MsiModel,
 };
 
 use self::{
-    atom_parser::{parse_acl, parse_id, parse_xyz},
+    atom_parser::{parse_acl, parse_id, parse_is_motion_fixed, parse_label, parse_xyz},
+    bond_parser::{parse_atom1, parse_atom2, parse_bond_type},
+    helper::{parse_msi_attribute, take_until_closing},
     model_attributes_parser::{hashmap_attrs, parse_periodic_type, parse_vector},
 };
 
+pub use self::error::{AttributeMatchError, MsiParseError, MsiParseErrorKind, StateError};
+
 mod atom_parser;
+mod bond_parser;
+mod error;
 mod helper;
 mod model_attributes_parser;
 
+/// Model attributes parsed into `Settings<MsiModel>` and `LatticeVectors<MsiModel>`,
+/// which are written by `DefaultExport<MsiModel>`.
+const KNOWN_MODEL_ATTRIBUTES: [&str; 7] = [
+    "CRY/DISPLAY",
+    "PeriodicType",
+    "SpaceGroup",
+    "A3",
+    "B3",
+    "C3",
+    "CRY/TOLERANCE",
+];
+
 pub trait ParserState: Debug {}
 
 #[derive(Debug)]
 /// A parser changes its state line by line.
 pub struct MsiParser<'a, S: ParserState> {
+    // The whole input, to locate the errors.
+    input: &'a str,
     // To denote the state that the input
     // has been completly consumed, use `None`
     to_parse: Option<&'a str>,
     // Vec storing string slices of model attributes.
     model_attributes: Vec<&'a str>,
-    // Vec storing string slices of atom objects.
-    atoms: Vec<&'a str>,
+    // Vec storing object numbers and string slices of atom objects.
+    atoms: Vec<(u32, &'a str)>,
     // Vec storing string slices of bonds
     bonds: Vec<&'a str>,
     // Counter of the attributes.
@@ -85,22 +107,26 @@ impl<'a, S: ParserState> MsiParser<'a, S> {
     /// Parser to extract the whole content of an object.
     /// It matches when an decimal integral number immediately
     /// follows a left parenthesis, and will take everything
-    /// until it finds spaces following with a single `)` and line ending.
+    /// until the matching `)` followed by a line ending, whatever the indentation.
+    /// The object number is kept in the returned content.
     /// Both unix-style (`\n`) and Windows/DOS-style (`\r\n`) line endings
     /// are supported.
     fn take_object(input: &str) -> IResult<&str, &str> {
         delimited(
-            tuple((space0, tag("("), decimal, space1)),
-            take_until("  )"),
-            tuple((space0, tag(")"), line_ending)),
+            tuple((space0, tag("("))),
+            recognize(tuple((decimal, space1, take_until_closing))),
+            tuple((tag(")"), space0, line_ending)),
         )(input)
     }
-    /// Parser to extract the type tag of an object.
+    /// Parser to extract the object number and the type tag of an object.
     /// # Note:
     /// **It will consume the line of the tag and move
     /// on to the attributes lines!**
-    fn get_object_type(object_input: &str) -> IResult<&str, &str> {
-        terminated(alpha1, line_ending)(object_input)
+    fn get_object_type(object_input: &str) -> IResult<&str, (u32, &str)> {
+        tuple((
+            terminated(map_res(decimal, |num: &str| num.parse::<u32>()), space1),
+            terminated(alpha1, line_ending),
+        ))(object_input)
     }
     /// Parser to extract the type tag of an attribute.
     /// # Note:
@@ -109,7 +135,7 @@ impl<'a, S: ParserState> MsiParser<'a, S> {
     fn get_attribute_type(attr_input: &str) -> IResult<&str, &str> {
         peek(delimited(
             tuple((alpha1, space1)),
-            recognize(many1(alt((alphanumeric1, tag("/"))))),
+            take_till1(|c: char| c.is_whitespace()),
             space1,
         ))(attr_input)
     }
@@ -122,6 +148,42 @@ impl<'a, S: ParserState> MsiParser<'a, S> {
     fn model_end(input: &str) -> IResult<&str, &str> {
         tag(")")(input)
     }
+    /// Error located at the `fragment` of the input.
+    fn error_at(&self, fragment: &str, kind: MsiParseErrorKind) -> MsiParseError {
+        MsiParseError::new(self.input, fragment, kind)
+    }
+    /// Parse the value of an attribute. `expect` describes the expected form
+    /// in the error.
+    fn attribute_value<T, F>(
+        &self,
+        item: &'a str,
+        parser: F,
+        expect: &str,
+    ) -> Result<T, MsiParseError>
+    where
+        F: Fn(&'a str) -> IResult<&'a str, T>,
+    {
+        parser(item).map(|(_, value)| value).map_err(|_| {
+            self.error_at(
+                item,
+                MsiParseErrorKind::AttributeMatch(AttributeMatchError::new(item, expect)),
+            )
+        })
+    }
+    /// The attributes of an object, the content after the object type line.
+    fn object_attributes(&self, object_fields: &'a str) -> Result<Vec<&'a str>, MsiParseError> {
+        many0(Self::take_attribute)(object_fields)
+            .map(|(_, attrs)| attrs)
+            .map_err(|_| {
+                self.error_at(
+                    object_fields,
+                    MsiParseErrorKind::AttributeMatch(AttributeMatchError::new(
+                        object_fields,
+                        "(A <type> <name> <value>)",
+                    )),
+                )
+            })
+    }
 }
 
 #[derive(Debug)]
@@ -136,6 +198,7 @@ impl<'a> MsiParser<'a, Loaded> {
     /// which has been read into string.
     pub fn new(input: &'a str) -> Self {
         Self {
+            input,
             to_parse: Some(input),
             num_atom: 0,
             num_bond: 0,
@@ -146,20 +209,36 @@ impl<'a> MsiParser<'a, Loaded> {
             num_attr: 0,
         }
     }
-    /// The file may have one to many comment lines.
-    /// Skip to the beginning of the actual content.
-    fn get_to_model(input: &str) -> IResult<&str, &str> {
-        take_until("(1 Model")(input)
-    }
-    /// Enter the scope of the model.
+    /// The opening line of a model object, e.g. `(1 Model`.
+    /// The object number is not necessarily 1.
     fn enter_model(input: &str) -> IResult<&str, &str> {
-        recognize(tuple((tag("(1 Model"), line_ending)))(input)
+        recognize(tuple((
+            space0,
+            tag("("),
+            decimal,
+            space1,
+            tag("Model"),
+            space0,
+            line_ending,
+        )))(input)
     }
-    /// Transits state into `Start` by entering the scope of model.
-    pub fn starts(self) -> MsiParser<'a, Start> {
-        let (rest, _): (&'a str, &'a str) = Self::get_to_model(self.to_parse.unwrap()).unwrap();
-        let (rest, _) = Self::enter_model(rest).unwrap();
-        MsiParser {
+    /// The file may have one to many comment lines, or objects of other types.
+    /// Skip line by line to the beginning of the next model.
+    fn get_to_model(input: &str) -> Option<&str> {
+        let mut rest = input;
+        while Self::enter_model(rest).is_err() {
+            let next_line = rest.find('\n')?;
+            rest = &rest[next_line + 1..];
+        }
+        Some(rest)
+    }
+    /// Transits state into `Start` by entering the scope of the next model.
+    /// Returns `None` when there are no more models in the input.
+    pub fn starts(self) -> Option<MsiParser<'a, Start>> {
+        let rest = Self::get_to_model(self.to_parse?)?;
+        let (rest, _) = Self::enter_model(rest).ok()?;
+        Some(MsiParser {
+            input: self.input,
             to_parse: Some(rest),
             model_attributes: self.model_attributes,
             atoms: self.atoms,
@@ -168,7 +247,7 @@ impl<'a> MsiParser<'a, Loaded> {
             num_atom: 0,
             num_bond: 0,
             state: PhantomData,
-        }
+        })
     }
 }
 
@@ -196,9 +275,9 @@ pub(crate) struct Start {}
 impl ParserState for Start {}
 
 impl<'a> MsiParser<'a, Start> {
-    /// Push the content to `self.atoms`, increment the counter by 1.
-    fn push_atom(&mut self, atom_fields: &'a str) {
-        self.atoms.push(atom_fields);
+    /// Push the object number and the content to `self.atoms`, increment the counter by 1.
+    fn push_atom(&mut self, object_number: u32, atom_fields: &'a str) {
+        self.atoms.push((object_number, atom_fields));
         self.num_atom += 1;
     }
     /// Push the content to `self.bonds`, increment the counter by 1.
@@ -214,15 +293,26 @@ impl<'a> MsiParser<'a, Start> {
     /// Loop over the input to parse attributes or objects,
     /// store the parsed contents into corresponding fields,
     /// finished with state transisted to `Analyzed`
-    pub fn analyze(mut self) -> MsiParser<'a, Analyzed> {
+    pub fn analyze(mut self) -> Result<MsiParser<'a, Analyzed>, MsiParseError> {
         // While we have fields
         while let Ok((rest, parsed_field)) = Self::get_field(self.to_parse.unwrap()) {
-            // Check if it is an object.
-            if let Ok((object_fields, object_type)) = Self::get_object_type(parsed_field) {
-                if object_type == "Atom" {
-                    self.push_atom(object_fields);
-                } else {
-                    self.push_bond(object_fields);
+            // Check if it is an object, which starts with its object number.
+            if parsed_field.starts_with(|c: char| c.is_ascii_digit()) {
+                let (object_fields, (object_number, object_type)) =
+                    Self::get_object_type(parsed_field).map_err(|_| {
+                        self.error_at(
+                            parsed_field,
+                            MsiParseErrorKind::AttributeMatch(AttributeMatchError::new(
+                                parsed_field.lines().next().unwrap_or(parsed_field),
+                                "(<object number> <object type>",
+                            )),
+                        )
+                    })?;
+                match object_type {
+                    "Atom" => self.push_atom(object_number, object_fields),
+                    "Bond" => self.push_bond(object_fields),
+                    // Other objects are not used for now.
+                    _ => (),
                 }
             } else {
                 // It is a model attribute.
@@ -231,12 +321,23 @@ impl<'a> MsiParser<'a, Start> {
             // Update `self.to_parse` to continue the loop.
             self.to_parse = Some(rest);
         }
-        // Fields have been consumed entirely.
-        let (_, _model_end) =
-            Self::model_end(self.to_parse.unwrap()).expect("Error: end of model not found!");
-        // Assume the file has only one model...
-        self.to_parse = None;
+        // Fields have been consumed entirely, unless the content is
+        // neither an attribute nor an object.
+        let unparsed = self.to_parse.unwrap().trim_start();
+        let (rest, _model_end) = Self::model_end(unparsed).map_err(|_| {
+            let kind = match unparsed.lines().next() {
+                None => MsiParseErrorKind::UnclosedModel,
+                Some(line) if line.starts_with("(A") => {
+                    MsiParseErrorKind::State(StateError::NotAttribute(line.into()))
+                }
+                Some(line) => MsiParseErrorKind::State(StateError::NotObject(line.into())),
+            };
+            self.error_at(unparsed, kind)
+        })?;
+        // Keep the remaining input for the following models.
+        self.to_parse = Some(rest);
         let Self {
+            input,
             to_parse,
             model_attributes: attributes,
             atoms,
@@ -247,7 +348,8 @@ impl<'a> MsiParser<'a, Start> {
             state: _,
         } = self;
         // Let's go to the `Analyzed` state.
-        MsiParser {
+        Ok(MsiParser {
+            input,
             to_parse,
             model_attributes: attributes,
             atoms,
@@ -256,7 +358,7 @@ impl<'a> MsiParser<'a, Start> {
             num_atom,
             num_bond,
             state: PhantomData,
-        }
+        })
     }
 }
 
@@ -265,58 +367,192 @@ pub(crate) struct Analyzed {}
 impl ParserState for Analyzed {}
 
 impl<'a> MsiParser<'a, Analyzed> {
-    fn parse_attributes(&self) -> Settings<MsiModel> {
-        if self.model_attributes.is_empty() {
-            Settings::default()
-        } else {
-            let attr_table = hashmap_attrs(self.model_attributes.as_ref());
-            let (_, periodic_type) =
-                parse_periodic_type(attr_table.get("PeriodicType").expect("No `PeriodicType`"))
-                    .unwrap();
-            let (_, cry_tolerance) =
-                parse_cry_tolerance(attr_table.get("CRY/TOLERANCE").expect("No `CRY/TOLERANCE`"))
-                    .unwrap();
-            let (_, space_group) =
-                parse_space_group(attr_table.get("SpaceGroup").expect("No `SpaceGroup`")).unwrap();
-            Settings::new_msi_settings(periodic_type, space_group, cry_tolerance)
-        }
+    /// Model attributes by their names.
+    fn attribute_table(&self) -> Result<HashMap<String, &'a str>, MsiParseError> {
+        hashmap_attrs(self.model_attributes.as_ref()).map_err(|item| {
+            self.error_at(
+                item,
+                MsiParseErrorKind::State(StateError::NotAttribute(item.into())),
+            )
+        })
     }
-    fn parse_lattice_vectors(&self) -> Option<LatticeVectors<MsiModel>> {
-        if self.model_attributes.is_empty() {
-            None
+    /// Parse a required attribute of the model.
+    fn model_attribute<T, F>(
+        &self,
+        attr_table: &HashMap<String, &'a str>,
+        name: &str,
+        parser: F,
+        expect: &str,
+    ) -> Result<T, MsiParseError>
+    where
+        F: Fn(&'a str) -> IResult<&'a str, T>,
+    {
+        let item = attr_table.get(name).ok_or_else(|| {
+            let model_start = self.model_attributes.first().copied().unwrap_or(self.input);
+            self.error_at(
+                model_start,
+                MsiParseErrorKind::MissingAttribute {
+                    object: "Model".into(),
+                    attribute: name.into(),
+                },
+            )
+        })?;
+        self.attribute_value(item, parser, expect)
+    }
+    /// A model without `PeriodicType` is not periodic, and takes the default settings.
+    fn parse_attributes(&self) -> Result<Settings<MsiModel>, MsiParseError> {
+        let attr_table = self.attribute_table()?;
+        let mut settings = if attr_table.contains_key("PeriodicType") {
+            let periodic_type = self.model_attribute(
+                &attr_table,
+                "PeriodicType",
+                parse_periodic_type,
+                "I PeriodicType <integer>",
+            )?;
+            let cry_tolerance = self.model_attribute(
+                &attr_table,
+                "CRY/TOLERANCE",
+                parse_cry_tolerance,
+                "D CRY/TOLERANCE <number>",
+            )?;
+            let space_group = self.model_attribute(
+                &attr_table,
+                "SpaceGroup",
+                parse_space_group,
+                "C SpaceGroup \"<integer> <integer>\"",
+            )?;
+            Settings::new_msi_settings(periodic_type, space_group, cry_tolerance)
         } else {
-            let attr_table = hashmap_attrs(self.model_attributes.as_ref());
-            let (_, vec_a) = parse_vector(attr_table.get("A3").unwrap()).unwrap();
-            let (_, vec_b) = parse_vector(attr_table.get("B3").unwrap()).unwrap();
-            let (_, vec_c) = parse_vector(attr_table.get("C3").unwrap()).unwrap();
-            let lattice_vector = Matrix3::from_columns(&[vec_a, vec_b, vec_c]);
-            Some(LatticeVectors::new(lattice_vector))
+            Settings::default()
+        };
+        settings.set_unknown_attributes(self.parse_unknown_attributes());
+        Ok(settings)
+    }
+    /// Model attributes other than those written by `DefaultExport<MsiModel>`.
+    fn parse_unknown_attributes(&self) -> Vec<MsiAttribute> {
+        self.model_attributes
+            .iter()
+            .filter_map(|item| parse_msi_attribute(item).ok())
+            .map(|(_, attr)| attr)
+            .filter(|attr| !KNOWN_MODEL_ATTRIBUTES.contains(&attr.name()))
+            .collect()
+    }
+    /// The model is periodic when any of `A3`, `B3` and `C3` is present,
+    /// and all of them are required then.
+    fn parse_lattice_vectors(&self) -> Result<Option<LatticeVectors<MsiModel>>, MsiParseError> {
+        let attr_table = self.attribute_table()?;
+        if ["A3", "B3", "C3"]
+            .iter()
+            .all(|name| !attr_table.contains_key(*name))
+        {
+            return Ok(None);
         }
+        let vectors = ["A3", "B3", "C3"]
+            .iter()
+            .map(|name| {
+                self.model_attribute(
+                    &attr_table,
+                    name,
+                    parse_vector,
+                    &format!("D {} (<x> <y> <z>)", name),
+                )
+            })
+            .collect::<Result<Vec<_>, MsiParseError>>()?;
+        let lattice_vector = Matrix3::from_columns(&vectors);
+        Ok(Some(LatticeVectors::new(lattice_vector)))
     }
-    fn parse_atoms(&self) -> AtomCollection<MsiModel> {
+    /// `XYZ` is required. The element comes from `ACL`, or from the `Label` when
+    /// `ACL` is absent. Without `Id`, the atom id is the index of the atom object
+    /// in the model, starting from 1. A non-zero `IsMotionFixed` fixes the atom
+    /// along all three axes.
+    fn parse_atoms(&self) -> Result<AtomCollection<MsiModel>, MsiParseError> {
         let mut element_symbols: Vec<String> = Vec::with_capacity(self.num_atom);
         let mut atomic_numbers: Vec<u8> = Vec::with_capacity(self.num_atom);
         let mut xyz_coords: Vec<Point3<f64>> = Vec::with_capacity(self.num_atom);
         let mut atom_ids: Vec<u32> = Vec::with_capacity(self.num_atom);
-        let frac_xyz: Vec<Option<Point3<f64>>> =
-            (0..self.num_atom).into_iter().map(|_| None).collect();
-        self.atoms.iter().for_each(|atom_fields| {
-            let (_, atom_attrs) = many0(Self::take_attribute)(atom_fields).unwrap();
-            atom_attrs.iter().for_each(|item| {
-                if let Ok((_, acl)) = parse_acl(item) {
-                    let (num, symbol) = acl;
-                    atomic_numbers.push(num);
-                    element_symbols.push(symbol.into());
-                } else if let Ok((_, xyz)) = parse_xyz(item) {
-                    xyz_coords.push(xyz);
-                } else if let Ok((_, id)) = parse_id(item) {
-                    atom_ids.push(id);
-                } else {
+        let frac_xyz: Vec<Option<Point3<f64>>> = (0..self.num_atom).map(|_| None).collect();
+        let mut fixed_axes: Vec<[bool; 3]> = Vec::with_capacity(self.num_atom);
+        self.atoms.iter().enumerate().try_for_each(
+            |(i, (_, atom_fields))| -> Result<(), MsiParseError> {
+                let atom_attrs = self.object_attributes(atom_fields)?;
+                let mut acl: Option<(u8, &str)> = None;
+                let mut label: Option<(&str, &str)> = None;
+                let mut xyz: Option<Point3<f64>> = None;
+                let mut id: Option<u32> = None;
+                let mut is_motion_fixed = false;
+                for item in atom_attrs {
+                    let (_, name) = Self::get_attribute_type(item).map_err(|_| {
+                        self.error_at(
+                            item,
+                            MsiParseErrorKind::State(StateError::NotAttribute(item.into())),
+                        )
+                    })?;
+                    match name {
+                        "ACL" => {
+                            acl = Some(self.attribute_value(
+                                item,
+                                parse_acl,
+                                "C ACL \"<atomic number> <element>\"",
+                            )?)
+                        }
+                        "Label" => {
+                            label = Some((
+                                item,
+                                self.attribute_value(item, parse_label, "C Label \"<label>\"")?,
+                            ))
+                        }
+                        "XYZ" => {
+                            xyz = Some(self.attribute_value(
+                                item,
+                                parse_xyz,
+                                "D XYZ (<x> <y> <z>)",
+                            )?)
+                        }
+                        "Id" => {
+                            id = Some(self.attribute_value(item, parse_id, "I Id <integer>")?)
+                        }
+                        "IsMotionFixed" => {
+                            is_motion_fixed = self.attribute_value(
+                                item,
+                                parse_is_motion_fixed,
+                                "I IsMotionFixed <integer>",
+                            )?
+                        }
+                        _ => (),
+                    }
                 }
-            })
-        });
+                let missing = |attribute: &str| {
+                    self.error_at(
+                        atom_fields,
+                        MsiParseErrorKind::MissingAttribute {
+                            object: "Atom".into(),
+                            attribute: attribute.into(),
+                        },
+                    )
+                };
+                let (symbol, num) = match (acl, label) {
+                    (Some((num, symbol)), _) => (symbol.to_string(), num),
+                    (None, Some((item, label))) => species_from_label(label).ok_or_else(|| {
+                        self.error_at(
+                            item,
+                            MsiParseErrorKind::AttributeMatch(AttributeMatchError::new(
+                                item,
+                                "C Label \"<element symbol><suffix>\"",
+                            )),
+                        )
+                    })?,
+                    (None, None) => return Err(missing("ACL")),
+                };
+                atomic_numbers.push(num);
+                element_symbols.push(symbol);
+                xyz_coords.push(xyz.ok_or_else(|| missing("XYZ"))?);
+                atom_ids.push(id.unwrap_or(i as u32 + 1));
+                fixed_axes.push([is_motion_fixed; 3]);
+                Ok(())
+            },
+        )?;
         let builder = AtomCollectionBuilder::<MsiModel, No>::new(self.num_atom);
-        builder
+        Ok(builder
             .with_atom_ids(&atom_ids)
             .unwrap()
             .with_element_symbols(&element_symbols)
@@ -327,20 +563,96 @@ impl<'a> MsiParser<'a, Analyzed> {
             .unwrap()
             .with_fractional_xyz(&frac_xyz)
             .unwrap()
+            .with_fixed_axes(&fixed_axes)
+            .unwrap()
+            .with_unknown_attributes(&self.parse_unknown_atom_attributes()?)
+            .unwrap()
             .finish()
             .unwrap()
-            .build()
+            .build())
     }
-    pub fn build_lattice_model(&self) -> LatticeModel<MsiModel> {
-        let settings = self.parse_attributes();
-        let lattice_vector = self.parse_lattice_vectors();
-        let atoms = self.parse_atoms();
-        LatticeModel::new(lattice_vector, atoms, settings)
+    /// Atom attributes other than `ACL`, `XYZ`, `Id` and `IsMotionFixed`,
+    /// in the order of atoms.
+    fn parse_unknown_atom_attributes(&self) -> Result<Vec<Vec<MsiAttribute>>, MsiParseError> {
+        self.atoms
+            .iter()
+            .map(|(_, atom_fields)| {
+                let atom_attrs = self.object_attributes(atom_fields)?;
+                Ok(atom_attrs
+                    .iter()
+                    .filter_map(|item| parse_msi_attribute(item).ok())
+                    .map(|(_, attr)| attr)
+                    .filter(|attr| !["ACL", "XYZ", "Id", "IsMotionFixed"].contains(&attr.name()))
+                    .collect())
+            })
+            .collect()
+    }
+    /// The atoms of a bond are referred by their object numbers,
+    /// which are resolved to the atom ids.
+    /// Bonds to atoms not in the model are dropped.
+    fn parse_bonds(
+        &self,
+        atoms: &AtomCollection<MsiModel>,
+    ) -> Result<Bonds<MsiModel>, MsiParseError> {
+        let id_table: HashMap<u32, u32> = self
+            .atoms
+            .iter()
+            .map(|(object_number, _)| *object_number)
+            .zip(atoms.atom_ids().iter().copied())
+            .collect();
+        let mut bonds: Bonds<MsiModel> = Bonds::default();
+        self.bonds
+            .iter()
+            .try_for_each(|bond_fields| -> Result<(), MsiParseError> {
+                let bond_attrs = self.object_attributes(bond_fields)?;
+                let mut atom_1: Option<u32> = None;
+                let mut atom_2: Option<u32> = None;
+                let mut bond_type = BondType::default();
+                for item in bond_attrs {
+                    match Self::get_attribute_type(item).map(|(_, name)| name) {
+                        Ok("Atom1") => {
+                            let object_number =
+                                self.attribute_value(item, parse_atom1, "O Atom1 <object number>")?;
+                            atom_1 = id_table.get(&object_number).copied();
+                        }
+                        Ok("Atom2") => {
+                            let object_number =
+                                self.attribute_value(item, parse_atom2, "O Atom2 <object number>")?;
+                            atom_2 = id_table.get(&object_number).copied();
+                        }
+                        Ok("Type") => {
+                            bond_type =
+                                self.attribute_value(item, parse_bond_type, "I Type <integer>")?;
+                        }
+                        _ => (),
+                    }
+                }
+                if let (Some(a), Some(b)) = (atom_1, atom_2) {
+                    bonds.push(Bond::new(a, b), bond_type);
+                }
+                Ok(())
+            })?;
+        Ok(bonds)
+    }
+    pub fn build_lattice_model(&self) -> Result<LatticeModel<MsiModel>, MsiParseError> {
+        let settings = self.parse_attributes()?;
+        let lattice_vector = self.parse_lattice_vectors()?;
+        let atoms = self.parse_atoms()?;
+        let bonds = self.parse_bonds(&atoms)?;
+        let mut model = LatticeModel::new(lattice_vector, atoms, settings);
+        model.set_bonds(bonds);
+        Ok(model)
+    }
+    /// Back to `Loaded` with the input after the current model,
+    /// ready to look for the next model.
+    pub fn next_model(self) -> MsiParser<'a, Loaded> {
+        MsiParser {
+            to_parse: self.to_parse,
+            ..MsiParser::new(self.input)
+        }
     }
 }
 
-mod error;
-
 #[cfg(test)]
 mod test {
     use std::fs::read_to_string;
@@ -353,7 +665,7 @@ mod test {
     fn parsing_lattice() {
         let file_content = read_to_string("SAC_GDY_V.msi").unwrap();
         let parser = MsiParser::new(&file_content);
-        let mut parser = parser.starts().analyze();
+        let mut parser = parser.starts().unwrap().analyze().unwrap();
         println!("{:?}", parser.parse_atoms());
         parser.model_attributes.sort_by_key(|item| {
             let (_, key) = MsiParser::<Analyzed>::get_attribute_type(item).unwrap();
@@ -366,7 +678,7 @@ mod test {
     fn parsing_ads() {
         let file_content = read_to_string("C2H4.msi").unwrap();
         let parser = MsiParser::new(&file_content);
-        let mut parser = parser.starts().analyze();
+        let mut parser = parser.starts().unwrap().analyze().unwrap();
         println!("{:?}", parser.parse_atoms());
         parser.model_attributes.sort_by_key(|item| {
             let (_, key) = MsiParser::<Analyzed>::get_attribute_type(item).unwrap();
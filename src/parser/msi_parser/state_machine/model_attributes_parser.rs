@@ -5,22 +5,27 @@ use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::{char, space0, space1},
-    combinator::recognize,
+    combinator::{map_res, recognize},
     sequence::{delimited, preceded, separated_pair, terminated, tuple},
     IResult,
 };
 
-use crate::parser::{decimal, float};
+use crate::parser::{decimal, float, number};
 
 use super::{Analyzed, MsiParser};
 
-pub fn hashmap_attrs<'a>(attributes: &[&'a str]) -> HashMap<String, &'a str> {
+/// Attributes by their names. The attribute without a readable name is returned as error.
+pub fn hashmap_attrs<'a>(attributes: &[&'a str]) -> Result<HashMap<String, &'a str>, &'a str> {
     let mut hashmap: HashMap<String, &str> = HashMap::new();
-    attributes.iter().for_each(|item| {
-        let (attr_str, key) = MsiParser::<Analyzed>::get_attribute_type(item).unwrap();
-        hashmap.insert(key.into(), attr_str);
-    });
-    hashmap
+    attributes
+        .iter()
+        .try_for_each(|item| -> Result<(), &'a str> {
+            let (attr_str, key) =
+                MsiParser::<Analyzed>::get_attribute_type(item).map_err(|_| *item)?;
+            hashmap.insert(key.into(), attr_str);
+            Ok(())
+        })?;
+    Ok(hashmap)
 }
 
 pub fn parse_cry_display(input: &str) -> IResult<&str, (u32, u32)> {
@@ -40,11 +45,10 @@ pub fn parse_cry_display(input: &str) -> IResult<&str, (u32, u32)> {
 }
 
 pub fn parse_periodic_type(input: &str) -> IResult<&str, u8> {
-    let (rest, value_str) = preceded(
+    preceded(
         tuple((tag("I"), space1, tag("PeriodicType"), space1)),
-        decimal,
-    )(input)?;
-    Ok((rest, value_str.parse::<u8>().unwrap()))
+        map_res(decimal, |num: &str| num.parse::<u8>()),
+    )(input)
 }
 
 pub fn parse_space_group(input: &str) -> IResult<&str, &str> {
@@ -59,11 +63,10 @@ pub fn parse_space_group(input: &str) -> IResult<&str, &str> {
 }
 
 pub fn parse_cry_tolerance(input: &str) -> IResult<&str, f64> {
-    let (rest, value_str) = preceded(
+    preceded(
         tuple((tag("D"), space1, tag("CRY/TOLERANCE"), space1)),
-        float,
-    )(input)?;
-    Ok((rest, value_str.parse::<f64>().unwrap()))
+        number,
+    )(input)
 }
 
 pub fn parse_vector(input: &str) -> IResult<&str, Vector3<f64>> {
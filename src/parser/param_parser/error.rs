@@ -0,0 +1,35 @@
+use std::{error::Error, fmt::Display};
+
+#[derive(Debug)]
+/// Error type when parsing a `param` file.
+pub enum ParamParseError {
+    /// The value of a keyword can not be parsed into the expected type.
+    InvalidValue { keyword: String, value: String },
+    /// The unit after the value is not supported.
+    UnknownUnit { keyword: String, unit: String },
+    /// The `task` in the file differs from the type of `CastepParam<T>`.
+    TaskMismatch { expected: String, found: String },
+    /// A `%BLOCK` without its matching `%ENDBLOCK`.
+    UnclosedBlock(String),
+}
+
+impl Display for ParamParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamParseError::InvalidValue { keyword, value } => {
+                write!(f, "Invalid value for keyword {}: \"{}\"", keyword, value)
+            }
+            ParamParseError::UnknownUnit { keyword, unit } => {
+                write!(f, "Unsupported unit for keyword {}: {}", keyword, unit)
+            }
+            ParamParseError::TaskMismatch { expected, found } => {
+                write!(f, "Task mismatch: expected {}, found {}", expected, found)
+            }
+            ParamParseError::UnclosedBlock(block) => {
+                write!(f, "Block {} is not closed by %ENDBLOCK.", block)
+            }
+        }
+    }
+}
+
+impl Error for ParamParseError {}
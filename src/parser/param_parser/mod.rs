@@ -0,0 +1,242 @@
+use std::str::FromStr;
+
+use nom::{
+    character::complete::{space0, space1},
+    combinator::{all_consuming, opt, rest},
+    sequence::{preceded, terminated, tuple},
+};
+
+use crate::{
+    param_writer::castep_param::{CastepParam, Task},
+    parser::{block_end, block_start, keyword_line, number, strip_comment},
+    units::{energy_to_ev, force_to_ev_per_ang, length_to_ang, pressure_to_gpa},
+};
+
+pub use self::error::ParamParseError;
+
+mod error;
+
+#[derive(Debug, Clone, Copy)]
+/// The raw value of a keyword in a `param` file.
+/// # Example:
+/// `cut_off_energy : 500.0 eV` has the keyword `cut_off_energy`
+/// and the value `500.0 eV`.
+pub struct ParamValue<'a> {
+    keyword: &'a str,
+    value: &'a str,
+}
+
+impl<'a> ParamValue<'a> {
+    pub fn new(keyword: &'a str, value: &'a str) -> Self {
+        Self { keyword, value }
+    }
+
+    pub fn keyword(&self) -> &str {
+        self.keyword
+    }
+
+    fn invalid_value(&self) -> ParamParseError {
+        ParamParseError::InvalidValue {
+            keyword: self.keyword.into(),
+            value: self.value.into(),
+        }
+    }
+    /// The value as written.
+    pub fn as_str(&self) -> &'a str {
+        self.value
+    }
+    /// `true`/`false`, or the fortran style `T`/`F`.
+    pub fn as_bool(&self) -> Result<bool, ParamParseError> {
+        match self.value.to_lowercase().as_str() {
+            "true" | "t" => Ok(true),
+            "false" | "f" => Ok(false),
+            _ => Err(self.invalid_value()),
+        }
+    }
+
+    pub fn as_f64(&self) -> Result<f64, ParamParseError> {
+        all_consuming(terminated(number, space0))(self.value)
+            .map(|(_, value)| value)
+            .map_err(|_| self.invalid_value())
+    }
+    /// Integer values may be written as reals in `castep`, e.g. `spin : 2.0`.
+    pub fn as_u32(&self) -> Result<u32, ParamParseError> {
+        let value = self.as_f64()?;
+        if value >= 0.0 && value.fract() == 0.0 && value <= u32::MAX as f64 {
+            Ok(value as u32)
+        } else {
+            Err(self.invalid_value())
+        }
+    }
+
+    pub fn as_u8(&self) -> Result<u8, ParamParseError> {
+        u8::try_from(self.as_u32()?).map_err(|_| self.invalid_value())
+    }
+    /// Number followed by an optional unit, converted with the given function.
+    fn with_unit(&self, to_default: fn(&str) -> Option<f64>) -> Result<f64, ParamParseError> {
+        let (_, (value, unit)) =
+            all_consuming(tuple((number, opt(preceded(space1, rest)))))(self.value.trim())
+                .map_err(|_: nom::Err<nom::error::Error<&str>>| self.invalid_value())?;
+        match unit.map(str::trim) {
+            Some(unit) if !unit.is_empty() => {
+                let factor = to_default(unit).ok_or_else(|| ParamParseError::UnknownUnit {
+                    keyword: self.keyword.into(),
+                    unit: unit.into(),
+                })?;
+                Ok(value * factor)
+            }
+            _ => Ok(value),
+        }
+    }
+    /// Energy in eV.
+    pub fn as_energy(&self) -> Result<f64, ParamParseError> {
+        self.with_unit(energy_to_ev)
+    }
+    /// Length in Å.
+    pub fn as_length(&self) -> Result<f64, ParamParseError> {
+        self.with_unit(length_to_ang)
+    }
+    /// Force in eV/Å.
+    pub fn as_force(&self) -> Result<f64, ParamParseError> {
+        self.with_unit(force_to_ev_per_ang)
+    }
+    /// Pressure in GPa.
+    pub fn as_pressure(&self) -> Result<f64, ParamParseError> {
+        self.with_unit(pressure_to_gpa)
+    }
+}
+
+/// Both spellings of optimisation are accepted by `castep`.
+fn normalize_task(task: &str) -> String {
+    task.to_lowercase().replace("optimisation", "optimization")
+}
+
+/// Parse a `param` file into `CastepParam<T>`.
+/// Keywords are matched case-insensitively, and values with units are converted
+/// into the units used by `CastepParam<T>`. Unrecognised keywords and blocks are
+/// kept in `unknown_keywords()` and `unknown_blocks()`, and written back on export.
+impl<T> FromStr for CastepParam<T>
+where
+    T: Task + 'static,
+{
+    type Err = ParamParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keywords: Vec<(String, &str)> = Vec::new();
+        let mut blocks: Vec<(String, String)> = Vec::new();
+        let mut lines = s
+            .lines()
+            .map(|line| strip_comment(line).trim())
+            .filter(|line| !line.is_empty());
+        while let Some(line) = lines.next() {
+            if let Ok((_, block_name)) = block_start(line) {
+                let mut block_lines: Vec<&str> = Vec::new();
+                loop {
+                    match lines.next() {
+                        Some(block_line) => match block_end(block_line) {
+                            Ok((_, end_name)) if end_name.eq_ignore_ascii_case(block_name) => break,
+                            _ => block_lines.push(block_line),
+                        },
+                        None => return Err(ParamParseError::UnclosedBlock(block_name.into())),
+                    }
+                }
+                blocks.push((block_name.to_lowercase(), block_lines.join("\n")));
+            } else if let Ok((_, (keyword, value))) = keyword_line(line) {
+                keywords.push((keyword.to_lowercase(), value));
+            }
+        }
+        // `metals_method` decides which of the mixing keywords are applicable,
+        // so it goes first.
+        keywords.sort_by_key(|(keyword, _)| keyword != "metals_method");
+        let mut param = CastepParam::<T>::default();
+        for (keyword, value) in keywords.iter() {
+            match keyword.as_str() {
+                "task" => {
+                    let expected = T::TASK_NAME;
+                    if normalize_task(value) != normalize_task(expected) {
+                        return Err(ParamParseError::TaskMismatch {
+                            expected: expected.into(),
+                            found: value.to_string(),
+                        });
+                    }
+                }
+                // Always written as a fixed line on export.
+                "comment" => (),
+                _ => {
+                    if !param.set_keyword(&ParamValue::new(keyword, value))? {
+                        param.push_unknown_keyword(keyword, value);
+                    }
+                }
+            }
+        }
+        blocks
+            .into_iter()
+            .for_each(|(name, content)| param.push_unknown_block(&name, &content));
+        Ok(param)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{
+        param_writer::castep_param::{BandStructureParam, CastepParam, GeomOptParam},
+        units::HARTREE_TO_EV,
+    };
+
+    const PARAM: &str = r#"TASK : GeometryOptimisation
+comment : exported by Materials Studio
+XC_FUNCTIONAL : PBE
+spin_polarized = T
+spin : 2.0
+cut_off_energy : 20 Ha
+geom_force_tol : 0.05 eV/ang
+geom_stress_tol : 1 MPa
+geom_modulus_est : 100 GPa
+mix_charge_amp : 0.2
+metals_method : dm
+write_cell_structure : true   ! not modelled
+%BLOCK devel_code
+  PP=T
+%ENDBLOCK devel_code
+"#;
+
+    #[test]
+    fn parse_param_template() {
+        let param = CastepParam::<GeomOptParam>::from_str(PARAM).unwrap();
+        assert!((param.cut_off_energy() - 20.0 * HARTREE_TO_EV).abs() < 1e-10);
+        assert_eq!(param.spin(), 2.0);
+        assert!((param.extra_setting().geom_stress_tol() - 1e-3).abs() < 1e-12);
+        assert_eq!(param.extra_setting().geom_modulus_est(), Some(100.0));
+        assert_eq!(
+            param.unknown_keywords(),
+            &[("write_cell_structure".to_string(), "true".to_string())]
+        );
+        assert_eq!(param.unknown_blocks().len(), 1);
+        let exported = format!("{}", param);
+        assert!(exported.contains("mix_charge_amp :         0.200000000000000"));
+        assert!(exported.contains("write_cell_structure : true"));
+        assert!(exported.contains(&format!("geom_modulus_est :        {:18.15}\n", 100.0)));
+        assert!(!format!("{}", CastepParam::<GeomOptParam>::default()).contains("geom_modulus_est"));
+        let parsed_back = CastepParam::<GeomOptParam>::from_str(&exported).unwrap();
+        assert_eq!(parsed_back.unknown_keywords(), param.unknown_keywords());
+        assert!(CastepParam::<BandStructureParam>::from_str(PARAM).is_err());
+        let mut variable_cell = parsed_back;
+        variable_cell.set_variable_cell();
+        assert!(variable_cell.calculate_stress());
+        assert!(format!("{}", variable_cell).contains("finite_basis_corr :        2\n"));
+        let mut band_param: CastepParam<BandStructureParam> = variable_cell.into();
+        band_param.set_variable_cell();
+        assert!(!band_param.calculate_stress());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let param = CastepParam::<GeomOptParam>::from_str(PARAM).unwrap();
+        let json = serde_json::to_string(&param).unwrap();
+        let parsed_back: CastepParam<GeomOptParam> = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{}", param), format!("{}", parsed_back));
+    }
+}
@@ -0,0 +1,43 @@
+use std::{error::Error, fmt::Display};
+
+#[derive(Debug)]
+/// Error type when parsing a `POSCAR`/`CONTCAR` file.
+pub enum PoscarParseError {
+    /// The file ends before the named section.
+    UnexpectedEof(String),
+    /// A line does not follow the expected format of the named section.
+    InvalidLine { section: String, line: String },
+    /// Neither the species line (VASP 5) nor the comment line names the species.
+    MissingSpecies,
+    /// The number of species differs from the number of counts.
+    InconsistentCounts { species: usize, counts: usize },
+    /// The species does not match any element in the periodic table.
+    UnknownElement(String),
+    /// The lattice vectors are linearly dependent.
+    SingularLattice,
+}
+
+impl Display for PoscarParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PoscarParseError::UnexpectedEof(section) => {
+                write!(f, "The file ends before the {}.", section)
+            }
+            PoscarParseError::InvalidLine { section, line } => {
+                write!(f, "Invalid line for the {}: \"{}\"", section, line)
+            }
+            PoscarParseError::MissingSpecies => write!(f, "The species are not given."),
+            PoscarParseError::InconsistentCounts { species, counts } => write!(
+                f,
+                "InconsistentCounts: {} species with {} counts",
+                species, counts
+            ),
+            PoscarParseError::UnknownElement(symbol) => write!(f, "Unknown element: {}", symbol),
+            PoscarParseError::SingularLattice => {
+                write!(f, "The lattice vectors are not independent!")
+            }
+        }
+    }
+}
+
+impl Error for PoscarParseError {}
@@ -0,0 +1,275 @@
+use std::str::{FromStr, Lines};
+
+use nalgebra::{Matrix3, Point3, Vector3};
+use nom::{
+    character::complete::{space0, space1},
+    combinator::all_consuming,
+    multi::separated_list1,
+    sequence::delimited,
+    IResult,
+};
+
+use crate::{
+    atom::{AtomCollection, AtomCollectionBuilder},
+    builder_typestate::No,
+    lattice::{LatticeModel, LatticeVectors},
+    model_type::{poscar::PoscarModel, Settings},
+    parser::{lookup_species, number},
+};
+
+pub use self::error::PoscarParseError;
+
+mod error;
+
+/// Whitespace separated numbers.
+fn numbers(line: &str) -> IResult<&str, Vec<f64>> {
+    all_consuming(delimited(space0, separated_list1(space1, number), space0))(line)
+}
+
+/// `T`/`F` flag of selective dynamics. `T` means the coordinate is free to move.
+fn is_fixed(flag: &str) -> Option<bool> {
+    match flag.to_uppercase().as_str() {
+        "T" | ".TRUE." => Some(false),
+        "F" | ".FALSE." => Some(true),
+        _ => None,
+    }
+}
+
+fn next_line<'a>(lines: &mut Lines<'a>, section: &str) -> Result<&'a str, PoscarParseError> {
+    lines
+        .next()
+        .ok_or_else(|| PoscarParseError::UnexpectedEof(section.into()))
+}
+
+fn invalid_line(section: &str, line: &str) -> PoscarParseError {
+    PoscarParseError::InvalidLine {
+        section: section.into(),
+        line: line.into(),
+    }
+}
+
+/// Parse a `POSCAR`/`CONTCAR` file.
+/// Both `Direct` and `Cartesian` coordinates are supported. A negative scale
+/// factor is taken as the volume of the cell. The species are read from the
+/// VASP 5 species line, or from the comment line for VASP 4 files.
+/// `Selective dynamics` flags are stored as the fixed axes of atoms.
+/// Lattice and atom velocities after the positions are ignored.
+impl FromStr for LatticeModel<PoscarModel> {
+    type Err = PoscarParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        let comment = next_line(&mut lines, "comment")?;
+        let scale_line = next_line(&mut lines, "scale factor")?;
+        let scale = match numbers(scale_line) {
+            Ok((_, values)) if values.len() == 1 => values[0],
+            _ => return Err(invalid_line("scale factor", scale_line)),
+        };
+        let mut vectors = Matrix3::zeros();
+        for i in 0..3 {
+            let line = next_line(&mut lines, "lattice vectors")?;
+            match numbers(line) {
+                Ok((_, values)) if values.len() == 3 => {
+                    vectors.set_column(i, &Vector3::new(values[0], values[1], values[2]))
+                }
+                _ => return Err(invalid_line("lattice vectors", line)),
+            }
+        }
+        let volume = vectors.determinant();
+        if volume.abs() < 1e-10 {
+            return Err(PoscarParseError::SingularLattice);
+        }
+        let scale = if scale < 0.0 {
+            (scale.abs() / volume.abs()).cbrt()
+        } else {
+            scale
+        };
+        let vectors = vectors * scale;
+        let to_frac = vectors
+            .try_inverse()
+            .ok_or(PoscarParseError::SingularLattice)?;
+        // VASP 5 has the species line before the counts.
+        let line = next_line(&mut lines, "atom counts")?;
+        let (species, counts_line): (Vec<&str>, &str) = if numbers(line).is_ok() {
+            (comment.split_whitespace().collect(), line)
+        } else {
+            (
+                line.split_whitespace().collect(),
+                next_line(&mut lines, "atom counts")?,
+            )
+        };
+        let counts: Vec<usize> = counts_line
+            .split_whitespace()
+            .map(|count| count.parse::<usize>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid_line("atom counts", counts_line))?;
+        if species.len() < counts.len() {
+            return Err(PoscarParseError::MissingSpecies);
+        }
+        if species.len() != counts.len() {
+            return Err(PoscarParseError::InconsistentCounts {
+                species: species.len(),
+                counts: counts.len(),
+            });
+        }
+        let species: Vec<(String, u8)> = species
+            .iter()
+            .map(|sp| {
+                // Potentials in VASP 6 may be named like `Fe_pv`.
+                let element = sp.split(['_', '/']).next().unwrap_or(sp);
+                lookup_species(element)
+                    .ok_or_else(|| PoscarParseError::UnknownElement(sp.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        let mut line = next_line(&mut lines, "coordinate mode")?;
+        let selective_dynamics = line.trim_start().to_uppercase().starts_with('S');
+        if selective_dynamics {
+            line = next_line(&mut lines, "coordinate mode")?;
+        }
+        let is_cartesian = matches!(
+            line.trim_start()
+                .chars()
+                .next()
+                .map(|c| c.to_ascii_uppercase()),
+            Some('C') | Some('K')
+        );
+        let num_atom: usize = counts.iter().sum();
+        let mut element_symbols: Vec<String> = Vec::with_capacity(num_atom);
+        let mut atomic_nums: Vec<u8> = Vec::with_capacity(num_atom);
+        let mut xyz_coords: Vec<Point3<f64>> = Vec::with_capacity(num_atom);
+        let mut frac_xyz: Vec<Option<Point3<f64>>> = Vec::with_capacity(num_atom);
+        let mut fixed_axes: Vec<[bool; 3]> = Vec::with_capacity(num_atom);
+        for ((symbol, atomic_num), count) in species.iter().zip(counts.iter()) {
+            for _ in 0..*count {
+                let line = next_line(&mut lines, "positions")?;
+                let columns: Vec<&str> = line.split_whitespace().collect();
+                let coord: Vec<f64> = columns
+                    .iter()
+                    .take(3)
+                    .map(|v| all_consuming(number)(v).map(|(_, value)| value))
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| invalid_line("positions", line))?;
+                if coord.len() != 3 {
+                    return Err(invalid_line("positions", line));
+                }
+                let coord = Point3::new(coord[0], coord[1], coord[2]);
+                let (xyz, frac) = if is_cartesian {
+                    let xyz = coord * scale;
+                    (xyz, to_frac * xyz)
+                } else {
+                    (vectors * coord, coord)
+                };
+                let fixed = if selective_dynamics {
+                    let flags: Vec<bool> = columns
+                        .get(3..6)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|flag| is_fixed(flag))
+                        .collect::<Option<_>>()
+                        .filter(|flags: &Vec<bool>| flags.len() == 3)
+                        .ok_or_else(|| invalid_line("positions", line))?;
+                    [flags[0], flags[1], flags[2]]
+                } else {
+                    [false; 3]
+                };
+                element_symbols.push(symbol.clone());
+                atomic_nums.push(*atomic_num);
+                xyz_coords.push(xyz);
+                frac_xyz.push(Some(frac));
+                fixed_axes.push(fixed);
+            }
+        }
+        let atom_ids: Vec<u32> = (1..=num_atom as u32).collect();
+        let atoms: AtomCollection<PoscarModel> =
+            AtomCollectionBuilder::<PoscarModel, No>::new(num_atom)
+                .with_element_symbols(&element_symbols)
+                .unwrap()
+                .with_atomic_nums(&atomic_nums)
+                .unwrap()
+                .with_xyz_coords(&xyz_coords)
+                .unwrap()
+                .with_fractional_xyz(&frac_xyz)
+                .unwrap()
+                .with_atom_ids(&atom_ids)
+                .unwrap()
+                .with_fixed_axes(&fixed_axes)
+                .unwrap()
+                .finish()
+                .unwrap()
+                .build();
+        Ok(LatticeModel::new(
+            Some(LatticeVectors::new(vectors)),
+            atoms,
+            Settings::default(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use na::Matrix3;
+
+    use crate::{
+        lattice::LatticeVectors, model_type::DefaultExport, CellModel, LatticeModel, PoscarModel,
+    };
+
+    const POSCAR: &str = r#"Fe O
+-64.0
+  1.0 0.0 0.0
+  0.0 2.0 0.0
+  0.0 0.0 0.5
+  Fe O
+  2 1
+Selective dynamics
+Cartesian
+  0.5 0.5 0.25  T T T
+  0.0 0.5 0.0   T T F
+  0.0 0.0 0.0   F F F
+"#;
+
+    #[test]
+    fn parse_poscar() {
+        let model = LatticeModel::<PoscarModel>::from_str(POSCAR).unwrap();
+        let vectors = model.lattice_vectors().unwrap().vectors();
+        assert!((vectors.determinant() - 64.0).abs() < 1e-10);
+        assert_eq!(model.atoms().element_symbols(), &["Fe", "Fe", "O"]);
+        assert_eq!(model.atoms().fixed_axes()[1], [false, false, true]);
+        let frac = model.atoms().fractional_xyz()[0].unwrap();
+        assert!((frac.x - 0.5).abs() < 1e-10);
+        let cell_model: LatticeModel<CellModel> = model.into();
+        assert_eq!(cell_model.atoms().fixed_axes()[2], [true; 3]);
+    }
+
+    #[test]
+    fn poscar_round_trip() {
+        let model = LatticeModel::<PoscarModel>::from_str(POSCAR).unwrap();
+        let exported = model.export();
+        assert!(exported.contains("    O   Fe\n    1    2"));
+        let parsed_back = LatticeModel::<PoscarModel>::from_str(&exported).unwrap();
+        // Grouped in the order of `element_set()`, O goes first.
+        assert_eq!(parsed_back.atoms().element_symbols(), &["O", "Fe", "Fe"]);
+        assert_eq!(parsed_back.atoms().fixed_axes()[0], [true; 3]);
+        assert!(
+            (parsed_back.atoms().xyz_coords()[1] - model.atoms().xyz_coords()[0]).norm() < 1e-10
+        );
+    }
+
+    #[test]
+    fn export_singular_lattice() {
+        let mut model = LatticeModel::<PoscarModel>::from_str(POSCAR).unwrap();
+        *model.lattice_vectors_mut() = Some(LatticeVectors::new(Matrix3::zeros()));
+        let exported = model.export();
+        assert!(exported.contains("Cartesian\n"));
+        *model.lattice_vectors_mut() = None;
+        let exported = model.export();
+        assert!(exported.contains("Cartesian\n"));
+        assert!(exported
+            .lines()
+            .nth(2)
+            .unwrap()
+            .split_whitespace()
+            .all(|value| value.parse::<f64>() == Ok(0.0)));
+    }
+}
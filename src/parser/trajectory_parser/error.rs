@@ -0,0 +1,45 @@
+use std::{error::Error, fmt::Display, io};
+
+#[derive(Debug)]
+/// Error type when reading `.geom` and `.md` trajectories.
+pub enum TrajectoryError {
+    /// Failure when reading from the source.
+    Io(io::Error),
+    /// A tagged line does not follow the expected format.
+    InvalidLine(String),
+    /// The frame lacks the lattice or positions, e.g. the file is truncated.
+    IncompleteFrame,
+    /// The lattice vectors of the frame are linearly dependent.
+    SingularLattice,
+    /// The species does not match any element in the periodic table.
+    UnknownElement(String),
+    /// The number of ids from `TrjAux` differs from the atoms in the frame.
+    InconsistentIds { ids: usize, atoms: usize },
+}
+
+impl Display for TrajectoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrajectoryError::Io(e) => write!(f, "Error while reading trajectory: {}", e),
+            TrajectoryError::InvalidLine(line) => write!(f, "Invalid line: \"{}\"", line),
+            TrajectoryError::IncompleteFrame => write!(f, "The frame is incomplete."),
+            TrajectoryError::SingularLattice => {
+                write!(f, "The lattice vectors of the frame are not independent!")
+            }
+            TrajectoryError::UnknownElement(symbol) => write!(f, "Unknown element: {}", symbol),
+            TrajectoryError::InconsistentIds { ids, atoms } => write!(
+                f,
+                "InconsistentIds: {} ids for {} atoms in the frame",
+                ids, atoms
+            ),
+        }
+    }
+}
+
+impl Error for TrajectoryError {}
+
+impl From<io::Error> for TrajectoryError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
@@ -0,0 +1,383 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Lines},
+    path::Path,
+};
+
+use nalgebra::{Matrix3, Point3, Vector3};
+use nom::{
+    bytes::complete::take_till1,
+    character::complete::{space0, space1},
+    multi::many1,
+    sequence::{preceded, tuple},
+    IResult,
+};
+
+use crate::{
+    atom::AtomCollectionBuilder,
+    builder_typestate::No,
+    lattice::{LatticeModel, LatticeVectors},
+    model_type::Settings,
+    param_writer::ms_aux_files::TrjAux,
+    parser::{decimal, lookup_species, number},
+    units::{AU_TIME_TO_PS, BOHR_TO_ANG, HARTREE_TO_EV, HARTREE_TO_KELVIN},
+    CellModel,
+};
+
+pub use self::error::TrajectoryError;
+
+mod error;
+
+#[derive(Debug, Clone, Default)]
+/// A frame in the `.geom` or `.md` trajectory.
+/// All the values are kept in atomic units as written in the file.
+pub struct TrajectoryFrame {
+    /// Iteration number of geometry optimization (`<-- c`).
+    step: Option<u32>,
+    /// Time of the MD step (`<-- t`).
+    time: Option<f64>,
+    /// The first value of the `<-- E` line, the total energy.
+    energy: Option<f64>,
+    /// Kinetic energy of the ions, the third value of `<-- E` in MD.
+    kinetic_energy: Option<f64>,
+    /// Temperature of the MD step (`<-- T`), in hartree.
+    temperature: Option<f64>,
+    /// Lattice vectors (`<-- h`) as columns.
+    lattice: Matrix3<f64>,
+    /// Species of the atoms (`<-- R`).
+    species: Vec<String>,
+    /// Cartesian coordinates (`<-- R`).
+    positions: Vec<Point3<f64>>,
+    /// Velocities in MD (`<-- V`).
+    velocities: Option<Vec<Vector3<f64>>>,
+    /// Forces (`<-- F`).
+    forces: Vec<Vector3<f64>>,
+}
+
+/// Streaming reader of the `.geom` and `.md` trajectory files.
+/// Frames are parsed one at a time when iterating, so the whole
+/// trajectory never has to be kept in memory.
+pub struct TrajectoryReader<R: BufRead> {
+    lines: Lines<R>,
+}
+
+impl TrajectoryReader<BufReader<File>> {
+    /// Open the trajectory file at the given path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let file = File::open(path)?;
+        Ok(Self::new(BufReader::new(file)))
+    }
+}
+
+impl<R: BufRead> TrajectoryReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for TrajectoryReader<R> {
+    type Item = Result<TrajectoryFrame, TrajectoryError>;
+
+    /// Collect the lines until a blank line ends the frame.
+    /// The `BEGIN header` ... `END header` section is skipped.
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut frame_lines: Vec<String> = Vec::new();
+        let mut in_header = false;
+        for line in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            let trimmed = line.trim();
+            if trimmed.eq_ignore_ascii_case("BEGIN header") {
+                in_header = true;
+            } else if trimmed.eq_ignore_ascii_case("END header") {
+                in_header = false;
+            } else if in_header {
+                continue;
+            } else if trimmed.is_empty() {
+                if !frame_lines.is_empty() {
+                    return Some(TrajectoryFrame::from_lines(&frame_lines));
+                }
+            } else {
+                frame_lines.push(line);
+            }
+        }
+        // The last frame may not be followed by a blank line.
+        (!frame_lines.is_empty()).then(|| TrajectoryFrame::from_lines(&frame_lines))
+    }
+}
+
+/// Values before the `<--` tag.
+fn values_line(input: &str) -> IResult<&str, Vec<f64>> {
+    many1(preceded(space0, number))(input)
+}
+
+/// `Si              1   0.0  0.0  0.0`, before the `<-- R`, `<-- V` or `<-- F` tag.
+fn atom_line(input: &str) -> IResult<&str, (&str, Vector3<f64>)> {
+    let (rest, (_, species, _, x, y, z)) = tuple((
+        space0,
+        take_till1(|c: char| c.is_whitespace()),
+        preceded(space1, decimal),
+        preceded(space1, number),
+        preceded(space1, number),
+        preceded(space1, number),
+    ))(input)?;
+    Ok((rest, (species, Vector3::new(x, y, z))))
+}
+
+impl TrajectoryFrame {
+    /// Parse the lines of a frame, dispatched by the trailing tag.
+    fn from_lines(lines: &[String]) -> Result<Self, TrajectoryError> {
+        let mut frame = Self::default();
+        let mut lattice_rows: Vec<Vector3<f64>> = Vec::with_capacity(3);
+        let mut velocities: Vec<Vector3<f64>> = Vec::new();
+        let invalid_line = |line: &str| TrajectoryError::InvalidLine(line.into());
+        for line in lines {
+            let (values, tag) = match line.rsplit_once("<--") {
+                Some((values, tag)) => (values, tag.trim()),
+                None => return Err(invalid_line(line)),
+            };
+            match tag {
+                "c" => {
+                    let (_, step) =
+                        preceded(space0, decimal)(values).map_err(|_| invalid_line(line))?;
+                    frame.step = step.parse::<u32>().ok();
+                }
+                "t" | "E" | "T" => {
+                    let (_, numbers) = values_line(values).map_err(|_| invalid_line(line))?;
+                    match tag {
+                        "t" => frame.time = numbers.first().copied(),
+                        "T" => frame.temperature = numbers.first().copied(),
+                        _ => {
+                            frame.energy = numbers.first().copied();
+                            frame.kinetic_energy = numbers.get(2).copied();
+                        }
+                    }
+                }
+                "h" => {
+                    let (_, numbers) = values_line(values).map_err(|_| invalid_line(line))?;
+                    if numbers.len() != 3 {
+                        return Err(invalid_line(line));
+                    }
+                    lattice_rows.push(Vector3::from_column_slice(&numbers));
+                }
+                "R" | "V" | "F" => {
+                    let (_, (species, vector)) =
+                        atom_line(values).map_err(|_| invalid_line(line))?;
+                    match tag {
+                        "R" => {
+                            frame.species.push(species.into());
+                            frame.positions.push(Point3::from(vector));
+                        }
+                        "V" => velocities.push(vector),
+                        _ => frame.forces.push(vector),
+                    }
+                }
+                // Cell velocities, stress and pressure are not used.
+                _ => (),
+            }
+        }
+        if lattice_rows.len() != 3 || frame.positions.is_empty() {
+            return Err(TrajectoryError::IncompleteFrame);
+        }
+        frame.lattice = Matrix3::from_columns(&lattice_rows);
+        if !velocities.is_empty() {
+            frame.velocities = Some(velocities);
+        }
+        Ok(frame)
+    }
+
+    pub fn step(&self) -> Option<u32> {
+        self.step
+    }
+    /// Time in atomic units.
+    pub fn time(&self) -> Option<f64> {
+        self.time
+    }
+    /// Time in ps.
+    pub fn time_ps(&self) -> Option<f64> {
+        self.time.map(|t| t * AU_TIME_TO_PS)
+    }
+    /// Energy in hartree.
+    pub fn energy(&self) -> Option<f64> {
+        self.energy
+    }
+    /// Energy in eV.
+    pub fn energy_ev(&self) -> Option<f64> {
+        self.energy.map(|e| e * HARTREE_TO_EV)
+    }
+    /// Kinetic energy in hartree.
+    pub fn kinetic_energy(&self) -> Option<f64> {
+        self.kinetic_energy
+    }
+    /// Temperature in hartree.
+    pub fn temperature(&self) -> Option<f64> {
+        self.temperature
+    }
+    /// Temperature in K.
+    pub fn temperature_kelvin(&self) -> Option<f64> {
+        self.temperature.map(|t| t * HARTREE_TO_KELVIN)
+    }
+    /// Lattice vectors as columns, in bohr.
+    pub fn lattice(&self) -> &Matrix3<f64> {
+        &self.lattice
+    }
+
+    pub fn species(&self) -> &[String] {
+        self.species.as_ref()
+    }
+    /// Cartesian coordinates in bohr.
+    pub fn positions(&self) -> &[Point3<f64>] {
+        self.positions.as_ref()
+    }
+    /// Velocities in atomic units (bohr per atomic time unit).
+    pub fn velocities(&self) -> Option<&[Vector3<f64>]> {
+        self.velocities.as_deref()
+    }
+    /// Forces in hartree/bohr.
+    pub fn forces(&self) -> &[Vector3<f64>] {
+        self.forces.as_ref()
+    }
+    /// Forces in eV/Å.
+    pub fn forces_ev_per_ang(&self) -> Vec<Vector3<f64>> {
+        self.forces
+            .iter()
+            .map(|force| force * (HARTREE_TO_EV / BOHR_TO_ANG))
+            .collect()
+    }
+    /// Convert the frame to `LatticeModel<CellModel>` in Å.
+    /// Atom ids are assigned by the order in the frame, starting from 1.
+    pub fn to_lattice_model(&self) -> Result<LatticeModel<CellModel>, TrajectoryError> {
+        let atom_ids: Vec<u32> = (1..=self.positions.len() as u32).collect();
+        self.build_lattice_model(&atom_ids)
+    }
+    /// Convert the frame to `LatticeModel<CellModel>` in Å, with the atom ids
+    /// mapped back to the `msi` ids through the id list in `.trjaux`.
+    pub fn to_lattice_model_with_ids(
+        &self,
+        trjaux: &TrjAux,
+    ) -> Result<LatticeModel<CellModel>, TrajectoryError> {
+        if trjaux.atom_ids().len() != self.positions.len() {
+            return Err(TrajectoryError::InconsistentIds {
+                ids: trjaux.atom_ids().len(),
+                atoms: self.positions.len(),
+            });
+        }
+        self.build_lattice_model(trjaux.atom_ids())
+    }
+    fn build_lattice_model(
+        &self,
+        atom_ids: &[u32],
+    ) -> Result<LatticeModel<CellModel>, TrajectoryError> {
+        let num_atom = self.positions.len();
+        let lattice = self.lattice * BOHR_TO_ANG;
+        let to_frac = lattice
+            .try_inverse()
+            .ok_or(TrajectoryError::SingularLattice)?;
+        let (element_symbols, atomic_nums): (Vec<String>, Vec<u8>) = self
+            .species
+            .iter()
+            .map(|species| {
+                lookup_species(species)
+                    .ok_or_else(|| TrajectoryError::UnknownElement(species.into()))
+            })
+            .collect::<Result<Vec<(String, u8)>, TrajectoryError>>()?
+            .into_iter()
+            .unzip();
+        let xyz_coords: Vec<Point3<f64>> =
+            self.positions.iter().map(|xyz| xyz * BOHR_TO_ANG).collect();
+        let frac_xyz: Vec<Option<Point3<f64>>> =
+            xyz_coords.iter().map(|xyz| Some(to_frac * xyz)).collect();
+        let atoms = AtomCollectionBuilder::<CellModel, No>::new(num_atom)
+            .with_element_symbols(&element_symbols)
+            .unwrap()
+            .with_atomic_nums(&atomic_nums)
+            .unwrap()
+            .with_xyz_coords(&xyz_coords)
+            .unwrap()
+            .with_fractional_xyz(&frac_xyz)
+            .unwrap()
+            .with_atom_ids(atom_ids)
+            .unwrap()
+            .finish()
+            .unwrap()
+            .build();
+        Ok(LatticeModel::new(
+            Some(LatticeVectors::new(lattice)),
+            atoms,
+            Settings::default(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{param_writer::ms_aux_files::TrjAux, units::BOHR_TO_ANG};
+
+    use super::{TrajectoryError, TrajectoryReader};
+
+    const MD: &str = r#" BEGIN header
+
+ END header
+
+                    0.0000000000000000E+000                                                            <-- t
+                   -1.8533539734406317E+001   -1.8530000000000000E+001    3.5000000000000000E-003  <-- E
+                    2.3000000000000000E-003                                                            <-- T
+                    1.0000000000000000E+001    0.0000000000000000E+000    0.0000000000000000E+000  <-- h
+                    0.0000000000000000E+000    1.0000000000000000E+001    0.0000000000000000E+000  <-- h
+                    0.0000000000000000E+000    0.0000000000000000E+000    1.0000000000000000E+001  <-- h
+ O               1    1.0000000000000000E+000    0.0000000000000000E+000    0.0000000000000000E+000  <-- R
+ H               1    2.0000000000000000E+000    0.0000000000000000E+000    0.0000000000000000E+000  <-- R
+ O               1    1.0000000000000000E-004    0.0000000000000000E+000    0.0000000000000000E+000  <-- V
+ H               1    1.0000000000000000E-004    0.0000000000000000E+000    0.0000000000000000E+000  <-- V
+ O               1    1.0000000000000000E-002    0.0000000000000000E+000    0.0000000000000000E+000  <-- F
+ H               1   -1.0000000000000000E-002    0.0000000000000000E+000    0.0000000000000000E+000  <-- F
+
+                    4.1341373336493000E+001                                                            <-- t
+                   -1.8533000000000000E+001   -1.8530000000000000E+001    3.4000000000000000E-003  <-- E
+                    2.2000000000000000E-003                                                            <-- T
+                    1.0000000000000000E+001    0.0000000000000000E+000    0.0000000000000000E+000  <-- h
+                    0.0000000000000000E+000    1.0000000000000000E+001    0.0000000000000000E+000  <-- h
+                    0.0000000000000000E+000    0.0000000000000000E+000    1.0000000000000000E+001  <-- h
+ O               1    1.0040000000000000E+000    0.0000000000000000E+000    0.0000000000000000E+000  <-- R
+ H               1    2.0040000000000000E+000    0.0000000000000000E+000    0.0000000000000000E+000  <-- R
+"#;
+
+    #[test]
+    fn read_md_frames() {
+        let frames: Vec<_> = TrajectoryReader::new(MD.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].velocities().unwrap().len(), 2);
+        assert!((frames[1].time_ps().unwrap() - 1e-3).abs() < 1e-9);
+        assert!(frames[1].velocities().is_none());
+        let trjaux = TrjAux::from_str("# Atom IDs\n5\n3\n#Origin  0.0 0.0 0.0").unwrap();
+        let model = frames[0].to_lattice_model_with_ids(&trjaux).unwrap();
+        assert_eq!(model.atoms().atom_ids(), &[5, 3]);
+        assert!((model.atoms().xyz_coords()[1].x - 2.0 * BOHR_TO_ANG).abs() < 1e-10);
+        assert!((model.atoms().fractional_xyz()[1].unwrap().x - 0.2).abs() < 1e-10);
+    }
+
+    #[test]
+    fn singular_lattice() {
+        let singular = MD.replacen(
+            "0.0000000000000000E+000    0.0000000000000000E+000    1.0000000000000000E+001  <-- h",
+            "0.0000000000000000E+000    0.0000000000000000E+000    0.0000000000000000E+000  <-- h",
+            1,
+        );
+        let frame = TrajectoryReader::new(singular.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            frame.to_lattice_model(),
+            Err(TrajectoryError::SingularLattice)
+        ));
+    }
+}
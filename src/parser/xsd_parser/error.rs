@@ -0,0 +1,40 @@
+use std::{error::Error, fmt::Display};
+
+#[derive(Debug)]
+/// Error type when parsing a `xsd` file.
+pub enum XsdParseError {
+    /// A tag is not well-formed, e.g. an unclosed `<` or an unquoted attribute.
+    InvalidTag(String),
+    /// A required attribute is missing in the element.
+    MissingAttribute { element: String, attribute: String },
+    /// The value of an attribute can not be parsed.
+    InvalidValue { attribute: String, value: String },
+    /// The species does not match any element in the periodic table.
+    UnknownElement(String),
+    /// A `Bond` connects an `ID` which is not an `Atom3d`.
+    UnknownAtomReference(String),
+}
+
+impl Display for XsdParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XsdParseError::InvalidTag(tag) => write!(f, "Invalid tag: \"{}\"", tag),
+            XsdParseError::MissingAttribute { element, attribute } => {
+                write!(f, "Attribute {} is missing in {}.", attribute, element)
+            }
+            XsdParseError::InvalidValue { attribute, value } => {
+                write!(
+                    f,
+                    "Invalid value for attribute {}: \"{}\"",
+                    attribute, value
+                )
+            }
+            XsdParseError::UnknownElement(symbol) => write!(f, "Unknown element: {}", symbol),
+            XsdParseError::UnknownAtomReference(id) => {
+                write!(f, "Bond connects an unknown atom ID: {}", id)
+            }
+        }
+    }
+}
+
+impl Error for XsdParseError {}
@@ -0,0 +1,308 @@
+use std::{collections::HashMap, str::FromStr};
+
+use nalgebra::{Matrix3, Point3, Vector3};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till, take_till1, take_until},
+    character::complete::{char, multispace0, multispace1},
+    combinator::{all_consuming, opt},
+    multi::many0,
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
+    IResult,
+};
+
+use crate::{
+    atom::{AtomCollection, AtomCollectionBuilder},
+    bond::{Bond, BondType, Bonds},
+    builder_typestate::No,
+    lattice::{LatticeModel, LatticeVectors},
+    model_type::{xsd::XsdModel, Settings},
+    parser::{number, species_from_label},
+};
+
+pub use self::error::XsdParseError;
+
+mod error;
+
+/// A start tag or an empty-element tag, with unescaped attribute values.
+#[derive(Debug)]
+struct XmlElement<'a> {
+    name: &'a str,
+    attributes: HashMap<&'a str, String>,
+}
+
+/// Replace the predefined entities.
+fn unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// `key="value"`
+type Attribute<'a> = (&'a str, &'a str);
+
+fn attribute(input: &str) -> IResult<&str, Attribute<'_>> {
+    separated_pair(
+        take_till1(|c: char| c == '=' || c == '>' || c == '/' || c.is_whitespace()),
+        delimited(multispace0, char('='), multispace0),
+        alt((
+            delimited(char('"'), take_till(|c| c == '"'), char('"')),
+            delimited(char('\''), take_till(|c| c == '\''), char('\'')),
+        )),
+    )(input)
+}
+
+/// `<Name attr="value" ...>` or `<Name attr="value" .../>`
+fn start_tag(input: &str) -> IResult<&str, (&str, Vec<Attribute<'_>>)> {
+    delimited(
+        char('<'),
+        pair(
+            take_till1(|c: char| c == '>' || c == '/' || c.is_whitespace()),
+            many0(preceded(multispace1, attribute)),
+        ),
+        tuple((multispace0, opt(char('/')), char('>'))),
+    )(input)
+}
+
+/// The markups which are not elements: declarations, comments and end tags.
+fn skipped_markup(input: &str) -> IResult<&str, &str> {
+    alt((
+        delimited(tag("<?"), take_until("?>"), tag("?>")),
+        delimited(tag("<!--"), take_until("-->"), tag("-->")),
+        delimited(tag("<!"), take_until(">"), char('>')),
+        delimited(tag("</"), take_until(">"), char('>')),
+    ))(input)
+}
+
+/// All elements in the document order. The nesting is not needed for `xsd`.
+fn xml_elements(s: &str) -> Result<Vec<XmlElement<'_>>, XsdParseError> {
+    let mut elements = Vec::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        if let Ok((remaining, _)) = skipped_markup(rest) {
+            rest = remaining;
+            continue;
+        }
+        let (remaining, (name, attributes)) = start_tag(rest).map_err(|_| {
+            let tag: String = rest.chars().take_while(|c| *c != '\n').take(80).collect();
+            XsdParseError::InvalidTag(tag)
+        })?;
+        elements.push(XmlElement {
+            name,
+            attributes: attributes
+                .into_iter()
+                .map(|(key, value)| (key, unescape(value)))
+                .collect(),
+        });
+        rest = remaining;
+    }
+    Ok(elements)
+}
+
+impl<'a> XmlElement<'a> {
+    fn attribute(&self, attribute: &str) -> Result<&str, XsdParseError> {
+        self.attributes
+            .get(attribute)
+            .map(|value| value.as_str())
+            .ok_or_else(|| XsdParseError::MissingAttribute {
+                element: self.name.into(),
+                attribute: attribute.into(),
+            })
+    }
+    /// Comma separated numbers, e.g. `XYZ="0.5,0.5,0"`.
+    fn vector(&self, attribute: &str) -> Result<Vector3<f64>, XsdParseError> {
+        let value = self.attribute(attribute)?;
+        let invalid = || XsdParseError::InvalidValue {
+            attribute: attribute.into(),
+            value: value.into(),
+        };
+        let components: Vec<f64> = value
+            .split(',')
+            .map(|v| {
+                all_consuming(terminated(preceded(multispace0, number), multispace0))(v)
+                    .map(|(_, n)| n)
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+        match components.as_slice() {
+            [x, y, z] => Ok(Vector3::new(*x, *y, *z)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Parse a `xsd` file saved by `Materials Studio`.
+/// `AVector`, `BVector` and `CVector` of the `SpaceGroup` give the lattice, in which
+/// case `XYZ` of `Atom3d` is fractional, otherwise cartesian. The element comes from
+/// `Components`, or the `Name` when `Components` is absent. Atoms with the `XYZ` in
+/// `RestrictedProperties` are fixed. `Bond` elements are resolved to atom ids.
+impl FromStr for LatticeModel<XsdModel> {
+    type Err = XsdParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let elements = xml_elements(s)?;
+        let lattice_vectors = elements
+            .iter()
+            .find(|elm| elm.name == "SpaceGroup")
+            .map(
+                |space_group| -> Result<LatticeVectors<XsdModel>, XsdParseError> {
+                    Ok(LatticeVectors::new(Matrix3::from_columns(&[
+                        space_group.vector("AVector")?,
+                        space_group.vector("BVector")?,
+                        space_group.vector("CVector")?,
+                    ])))
+                },
+            )
+            .transpose()?;
+        let atom_elements: Vec<&XmlElement> =
+            elements.iter().filter(|elm| elm.name == "Atom3d").collect();
+        let num_atom = atom_elements.len();
+        let mut element_symbols: Vec<String> = Vec::with_capacity(num_atom);
+        let mut atomic_nums: Vec<u8> = Vec::with_capacity(num_atom);
+        let mut xyz_coords: Vec<Point3<f64>> = Vec::with_capacity(num_atom);
+        let mut frac_xyz: Vec<Option<Point3<f64>>> = Vec::with_capacity(num_atom);
+        let mut fixed_axes: Vec<[bool; 3]> = Vec::with_capacity(num_atom);
+        // `ID` of `Atom3d` to the atom id.
+        let mut id_table: HashMap<&str, u32> = HashMap::new();
+        for (i, atom) in atom_elements.iter().enumerate() {
+            let species = match atom.attributes.get("Components") {
+                Some(components) => components.as_str(),
+                None => atom.attribute("Name")?,
+            };
+            let (symbol, atomic_num) = species_from_label(species)
+                .ok_or_else(|| XsdParseError::UnknownElement(species.into()))?;
+            let coord = Point3::from(atom.vector("XYZ")?);
+            let (xyz, frac) = match lattice_vectors.as_ref() {
+                Some(lat_vec) => (lat_vec.vectors() * coord, Some(coord)),
+                None => (coord, None),
+            };
+            let is_fixed = atom
+                .attributes
+                .get("RestrictedProperties")
+                .map(|props| props.split(',').any(|p| p.trim().ends_with("XYZ")))
+                .unwrap_or(false);
+            element_symbols.push(symbol);
+            atomic_nums.push(atomic_num);
+            xyz_coords.push(xyz);
+            frac_xyz.push(frac);
+            fixed_axes.push([is_fixed; 3]);
+            if let Some(id) = atom.attributes.get("ID") {
+                id_table.insert(id.as_str(), i as u32 + 1);
+            }
+        }
+        let mut bonds: Bonds<XsdModel> = Bonds::default();
+        elements
+            .iter()
+            .filter(|elm| elm.name == "Bond")
+            .try_for_each(|bond| -> Result<(), XsdParseError> {
+                let connects = bond.attribute("Connects")?;
+                let atom_ids: Vec<u32> = connects
+                    .split(',')
+                    .map(|id| {
+                        id_table
+                            .get(id.trim())
+                            .copied()
+                            .ok_or_else(|| XsdParseError::UnknownAtomReference(id.into()))
+                    })
+                    .collect::<Result<_, _>>()?;
+                let bond_type = match bond.attributes.get("Type").map(|t| t.as_str()) {
+                    Some("Double") => BondType::Double,
+                    Some("Triple") => BondType::Triple,
+                    _ => BondType::Single,
+                };
+                match atom_ids.as_slice() {
+                    [a, b] => {
+                        bonds.push(Bond::new(*a, *b), bond_type);
+                        Ok(())
+                    }
+                    _ => Err(XsdParseError::InvalidValue {
+                        attribute: "Connects".into(),
+                        value: connects.into(),
+                    }),
+                }
+            })?;
+        let atom_ids: Vec<u32> = (1..=num_atom as u32).collect();
+        let atoms: AtomCollection<XsdModel> = AtomCollectionBuilder::<XsdModel, No>::new(num_atom)
+            .with_element_symbols(&element_symbols)
+            .unwrap()
+            .with_atomic_nums(&atomic_nums)
+            .unwrap()
+            .with_xyz_coords(&xyz_coords)
+            .unwrap()
+            .with_fractional_xyz(&frac_xyz)
+            .unwrap()
+            .with_atom_ids(&atom_ids)
+            .unwrap()
+            .with_fixed_axes(&fixed_axes)
+            .unwrap()
+            .finish()
+            .unwrap()
+            .build();
+        let mut model = LatticeModel::new(lattice_vectors, atoms, Settings::default());
+        model.set_bonds(bonds);
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::{
+        bond::BondType,
+        model_type::{xsd::XsdModel, DefaultExport},
+        CellModel, LatticeModel,
+    };
+
+    const XSD: &str = r#"<?xml version="1.0" encoding="latin1"?>
+<!DOCTYPE XSD []>
+<XSD Version="6.0">
+	<AtomisticTreeRoot ID="1" NumProperties="0" NumChildren="1">
+		<!-- trimmed from a Materials Studio export -->
+		<SymmetrySystem ID="2" Mapping="3" Children="4,5,6,7" Name="SymmetrySystem">
+			<MappingSet ID="3" SymmetryDefinition="7">
+				<MappingFamily ID="8" NumImageMappings="0">
+					<IdentityMapping ID="9" MappedObjects="4,5,6,7">
+						<Atom3d ID="4" Mapping="10" Parent="2" Name="C1" XYZ="0.1,0.2,0.3" Connections="6" Components="C" RestrictedProperties="FractionalXYZ"/>
+						<Atom3d ID="5" Mapping="11" Parent="2" Name="O" XYZ="0.1,0.2,0.4" Connections="6"/>
+						<Bond ID="6" Mapping="12" Parent="2" Connects="4,5" Type="Double"/>
+					</IdentityMapping>
+				</MappingFamily>
+			</MappingSet>
+			<SpaceGroup ID="7" Parent="2" AVector="10,0,0" BVector="0,10,0" CVector="0,0,10" GroupName="P1"/>
+		</SymmetrySystem>
+	</AtomisticTreeRoot>
+</XSD>
+"#;
+
+    #[test]
+    fn parse_xsd() {
+        let model = LatticeModel::<XsdModel>::from_str(XSD).unwrap();
+        let atoms = model.atoms();
+        assert_eq!(atoms.element_symbols(), &["C".to_string(), "O".to_string()]);
+        assert!((atoms.xyz_coords()[1].z - 4.0).abs() < 1e-10);
+        assert_eq!(atoms.fixed_axes(), &[[true; 3], [false; 3]]);
+        assert_eq!(model.bonds().bonds()[0].atom_ids(), (1, 2));
+        assert_eq!(model.bonds().bond_types(), &[BondType::Double]);
+    }
+
+    #[test]
+    fn xsd_round_trip() {
+        let model = LatticeModel::<XsdModel>::from_str(XSD).unwrap();
+        let exported = model.export();
+        let parsed_back = LatticeModel::<XsdModel>::from_str(&exported).unwrap();
+        assert_eq!(
+            parsed_back.lattice_vectors().unwrap().vectors(),
+            model.lattice_vectors().unwrap().vectors()
+        );
+        assert_eq!(parsed_back.atoms().fixed_axes(), model.atoms().fixed_axes());
+        assert_eq!(parsed_back.bonds().bond_types(), model.bonds().bond_types());
+        let cell_model: LatticeModel<CellModel> = parsed_back.into();
+        let frac = cell_model.atoms().fractional_xyz()[0].unwrap();
+        assert!((frac.y - 0.2).abs() < 1e-10);
+    }
+}
@@ -0,0 +1,83 @@
+//! Unit conversions used when reading from and writing to `castep` files.
+//! The models in this crate always store lengths in Å.
+
+/// 1 bohr (atomic unit of length) in Å.
+pub const BOHR_TO_ANG: f64 = 0.529_177_210_903;
+
+/// Conversion factor from the given length unit to Å.
+/// Units are matched case-insensitively, following the `castep` keywords.
+pub fn length_to_ang(unit: &str) -> Option<f64> {
+    match unit.to_lowercase().as_str() {
+        "ang" | "angstrom" => Some(1.0),
+        "bohr" | "a0" => Some(BOHR_TO_ANG),
+        "nm" => Some(10.0),
+        "cm" => Some(1e8),
+        "m" => Some(1e10),
+        _ => None,
+    }
+}
+
+/// 1 hartree (atomic unit of energy) in eV.
+pub const HARTREE_TO_EV: f64 = 27.211_386_245_988;
+
+/// Temperature in K of 1 hartree, i.e. E_h / k_B.
+pub const HARTREE_TO_KELVIN: f64 = 315_775.024_804_07;
+
+/// 1 atomic unit of time in ps.
+pub const AU_TIME_TO_PS: f64 = 2.418_884_326_585_7e-5;
+
+/// 1 eV/Å³ in GPa.
+pub const EV_PER_ANG3_TO_GPA: f64 = 160.217_663_4;
+
+/// Conversion factor from the given energy unit to eV.
+pub fn energy_to_ev(unit: &str) -> Option<f64> {
+    match unit.to_lowercase().as_str() {
+        "ev" => Some(1.0),
+        "mev" => Some(1e-3),
+        "ha" | "hartree" => Some(HARTREE_TO_EV),
+        "mha" => Some(HARTREE_TO_EV * 1e-3),
+        "ry" => Some(HARTREE_TO_EV / 2.0),
+        "mry" => Some(HARTREE_TO_EV / 2.0 * 1e-3),
+        "kj/mol" => Some(0.010_364_269_6),
+        "kcal/mol" => Some(0.043_364_104_2),
+        "j" => Some(6.241_509_074e18),
+        "erg" => Some(6.241_509_074e11),
+        _ => None,
+    }
+}
+
+/// Conversion factor from the given force unit (`energy/length`) to eV/Å.
+pub fn force_to_ev_per_ang(unit: &str) -> Option<f64> {
+    let (energy, length) = unit.split_once('/')?;
+    Some(energy_to_ev(energy)? / length_to_ang(length)?)
+}
+
+/// Conversion factor from the given pressure unit to GPa.
+pub fn pressure_to_gpa(unit: &str) -> Option<f64> {
+    match unit.to_lowercase().as_str() {
+        "gpa" => Some(1.0),
+        "mpa" => Some(1e-3),
+        "pa" => Some(1e-9),
+        "bar" => Some(1e-4),
+        "atm" => Some(1.013_25e-4),
+        "ev/ang**3" | "ev/ang^3" => Some(EV_PER_ANG3_TO_GPA),
+        "ha/bohr**3" | "ha/bohr^3" => {
+            Some(HARTREE_TO_EV / BOHR_TO_ANG.powi(3) * EV_PER_ANG3_TO_GPA)
+        }
+        _ => None,
+    }
+}
+
+/// 1 electron mass (atomic unit of mass) in amu.
+pub const ELECTRON_MASS_TO_AMU: f64 = 5.485_799_090_65e-4;
+
+/// Conversion factor from the given mass unit to amu.
+pub fn mass_to_amu(unit: &str) -> Option<f64> {
+    match unit.to_lowercase().as_str() {
+        "amu" => Some(1.0),
+        "me" => Some(ELECTRON_MASS_TO_AMU),
+        "g" => Some(6.022_140_76e23),
+        "kg" => Some(6.022_140_76e26),
+        _ => None,
+    }
+}